@@ -1,29 +1,33 @@
 extern crate typefunnel;
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::fs;
 use std::fs::File;
-use std::io;
 use std::io::Write;
+use std::process;
 use typefunnel::edit_warning;
+use typefunnel::error::Result;
 use typefunnel::source::HasSchema;
-use typefunnel::source::call::{ECMAScript, ECMAScriptModule};
+use typefunnel::source::call::{Asynchrony, ECMAScript, ECMAScriptModule};
 use typefunnel::constant::Constant;
 use typefunnel::web_service;
 use typefunnel::web_service::WebService;
 
 fn main() {
-  safe_main().unwrap();
+  if let Err(error) = safe_main() {
+    eprintln!("{}", error);
+    process::exit(1);
+  }
 }
 
-fn safe_main() -> io::Result<()> {
-  let source = Constant::AllOf(vec![
-    Constant::SignedInteger(42),
-    Constant::DoublePrecision(3.14),
-    Constant::AllOf(vec![
+fn safe_main() -> Result<()> {
+  let source = Constant::Record(vec![
+    ("answer".to_string(), Constant::SignedInteger(42)),
+    ("pi".to_string(), Constant::DoublePrecision(3.14)),
+    ("greetings".to_string(), Constant::AllOf(vec![
       Constant::String("Hello, world!".to_string()),
       Constant::String("Bye, world!".to_string()),
-    ]),
+    ])),
   ]);
 
   fs::create_dir_all("/tmp/typefunnel")?;
@@ -37,7 +41,7 @@ fn safe_main() -> io::Result<()> {
   Ok(())
 }
 
-fn generate_server<Source>(service: &WebService<Source>) -> io::Result<()>
+fn generate_server<Source>(service: &WebService<Source>) -> Result<()>
   where Source: HasSchema + ECMAScript {
   let mut file = File::create("/tmp/typefunnel/server.js")?;
   write!(file, "{}\n", edit_warning::ECMASCRIPT)?;
@@ -49,14 +53,15 @@ fn generate_server<Source>(service: &WebService<Source>) -> io::Result<()>
   Ok(())
 }
 
-fn generate_client<Source>(service: &WebService<Source>) -> io::Result<()>
+fn generate_client<Source>(service: &WebService<Source>) -> Result<()>
   where Source: HasSchema + ECMAScript {
   let module = ECMAScriptModule{
     calls: {
-      let mut calls = HashMap::new();
+      let mut calls = BTreeMap::new();
       calls.insert("remote".to_string(), (service as &HasSchema, service as &ECMAScript));
       calls
     },
+    asynchrony: Asynchrony::Callbacks,
   };
 
   {
@@ -66,9 +71,15 @@ fn generate_client<Source>(service: &WebService<Source>) -> io::Result<()>
   }
 
   {
-    let mut file = File::create("/tmp/typefunnel/client.purs")?;
+    let mut file = File::create("/tmp/typefunnel/Remote.purs")?;
     writeln!(file, "{}", edit_warning::PURESCRIPT)?;
-    module.purescript(&mut file)?;
+    module.purescript(&mut file, "Remote")?;
+  }
+
+  {
+    let mut file = File::create("/tmp/typefunnel/Remote.js")?;
+    writeln!(file, "{}", edit_warning::ECMASCRIPT)?;
+    module.purescript_foreign(&mut file)?;
   }
 
   Ok(())
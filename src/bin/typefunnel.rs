@@ -0,0 +1,82 @@
+extern crate typefunnel;
+
+use std::env;
+use std::path::Path;
+use std::process;
+use typefunnel::error::Result;
+use typefunnel::manifest::Manifest;
+
+static USAGE: &'static str = "Usage: typefunnel [--offline] [--check | --verify] [MANIFEST]
+
+Generate the targets listed in MANIFEST, which defaults to typefunnel.toml.
+
+Options:
+  --offline  Do not connect to the database; read the schema cache instead.
+  --check    Check that the files of the targets are up to date instead of
+             generating them.
+  --verify   Check the schema cache against the database instead of
+             generating the targets.";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+  Generate,
+  Check,
+  Verify,
+}
+
+fn main() {
+  let mut mode = Mode::Generate;
+  let mut offline = false;
+  let mut path = None;
+  for arg in env::args().skip(1) {
+    match arg.as_str() {
+      "-h" | "--help" => {
+        println!("{}", USAGE);
+        return;
+      },
+      "--offline" if !offline && mode != Mode::Verify => offline = true,
+      "--check" if mode == Mode::Generate => mode = Mode::Check,
+      "--verify" if mode == Mode::Generate && !offline => mode = Mode::Verify,
+      _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+      _ => {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+      },
+    }
+  }
+  let path = path.unwrap_or("typefunnel.toml".to_string());
+  match safe_main(Path::new(&path), mode, offline) {
+    Ok(true) => (),
+    Ok(false) => process::exit(1),
+    Err(error) => {
+      eprintln!("{}", error);
+      process::exit(1);
+    },
+  }
+}
+
+fn safe_main(path: &Path, mode: Mode, offline: bool) -> Result<bool> {
+  let mut manifest = Manifest::read(path)?;
+  let directory = path.parent().unwrap_or(Path::new(""));
+  if offline {
+    manifest.database = None;
+  }
+  match mode {
+    Mode::Generate => manifest.generate(directory)?,
+    Mode::Check => {
+      let stale = manifest.check(directory)?;
+      for target in &stale {
+        eprintln!("The target {} is out of date.", target.display());
+      }
+      return Ok(stale.is_empty());
+    },
+    Mode::Verify => {
+      let stale = manifest.verify(directory)?;
+      for query in &stale {
+        eprintln!("The schema cache is out of date for the query {}.", query);
+      }
+      return Ok(stale.is_empty());
+    },
+  }
+  Ok(true)
+}
@@ -1,15 +1,28 @@
 //! This module implements a source that exports constant data.
 
 use Schema;
-use source::call::{ECMAScript, ECMAScriptConvention};
+use error::{Error, Result};
+use escape;
+use source::call::{ECMAScript, ECMAScriptConvention, Rust, rust_hint,
+                   rust_type};
 use source::HasSchema;
+use std::cmp;
 use std::io;
 use std::rc::Rc;
 
+mod error {
+  pub static BYTE_STRING: &'static str =
+    "ECMAScript expressions cannot yet be generated for byte strings.";
+}
+
 /// Constant data.
 #[allow(missing_docs)]
 pub enum Constant {
   AllOf(Vec<Constant>),
+  Record(Vec<(String, Constant)>),
+  ArrayOf(Vec<Constant>),
+  Null,
+  Boolean(bool),
   SignedInteger(i32),
   SinglePrecision(f32),
   DoublePrecision(f64),
@@ -18,24 +31,38 @@ pub enum Constant {
 }
 
 impl HasSchema for Constant {
-  fn schema(&self) -> io::Result<(Rc<Schema>, Rc<Schema>)> {
+  fn schema(&self) -> Result<(Rc<Schema>, Rc<Schema>)> {
     Ok((Rc::new(Schema::AllOf(vec![])), Rc::new(output_schema(self))))
   }
 }
 
 impl ECMAScript for Constant {
-  fn ecmascript_call(&self, write: &mut io::Write) -> io::Result<()> {
+  fn ecmascript_call(&self, write: &mut io::Write) -> Result<()> {
     write!(write, "(function() {{\nreturn ")?;
-    ecmascript_expression(write, self)?;
+    ecmascript_expression(write, self, &output_schema(self))?;
     write!(write, ";\n}})")?;
     Ok(())
   }
 
-  fn ecmascript_convention(&self) -> io::Result<ECMAScriptConvention> {
+  fn ecmascript_convention(&self) -> Result<ECMAScriptConvention> {
     Ok(ECMAScriptConvention::Synchronous)
   }
 }
 
+impl Rust for Constant {
+  fn rust_context(&self) -> Result<String> {
+    Ok("()".to_string())
+  }
+
+  fn rust_call(&self, write: &mut io::Write, name: &str) -> Result<()> {
+    write!(write, "    Ok(")?;
+    rust_expression(write, self, &output_schema(self),
+                    &rust_hint(name, "output"))?;
+    write!(write, ")\n")?;
+    Ok(())
+  }
+}
+
 fn output_schema(constant: &Constant) -> Schema {
   match *constant {
     Constant::AllOf(ref elements) =>
@@ -44,6 +71,20 @@ fn output_schema(constant: &Constant) -> Schema {
         .map(output_schema)
         .collect(),
       ),
+    Constant::Record(ref fields) =>
+      Schema::Record(
+        fields.iter()
+        .map(|&(ref name, ref field)| (name.clone(), output_schema(field)))
+        .collect(),
+      ),
+    Constant::ArrayOf(ref elements) =>
+      Schema::ArrayOf(Box::new(
+        elements.iter()
+        .map(output_schema)
+        .fold(Schema::OneOf(vec![]), join),
+      )),
+    Constant::Null => Schema::Optional(Box::new(Schema::OneOf(vec![]))),
+    Constant::Boolean(_) => Schema::Boolean,
     Constant::SignedInteger(value) => Schema::SignedInteger(value, value),
     Constant::SinglePrecision(_) => Schema::SinglePrecision,
     Constant::DoublePrecision(_) => Schema::DoublePrecision,
@@ -52,22 +93,303 @@ fn output_schema(constant: &Constant) -> Schema {
   }
 }
 
-fn ecmascript_expression(write: &mut io::Write, constant: &Constant)
-  -> io::Result<()> {
+/// Return a schema that describes data described by either of the given
+/// schemas. The empty `OneOf` is the identity of this operation.
+fn join(left: Schema, right: Schema) -> Schema {
+  let (mut alternatives, mut right) = match (left, right) {
+    (Schema::Optional(left), right) | (right, Schema::Optional(left)) =>
+      return match join(*left, right) {
+        optional @ Schema::Optional(_) => optional,
+        joined => Schema::Optional(Box::new(joined)),
+      },
+    (left, Schema::OneOf(rights)) => return rights.into_iter().fold(left, join),
+    (Schema::OneOf(alternatives), right) => (alternatives, right),
+    (left, right) => (vec![left], right),
+  };
+  alternatives.retain(|alternative| {
+    if let (&Schema::SignedInteger(left_min, left_max),
+            &mut Schema::SignedInteger(ref mut right_min, ref mut right_max))
+         = (alternative, &mut right) {
+      *right_min = cmp::min(left_min, *right_min);
+      *right_max = cmp::max(left_max, *right_max);
+      false
+    } else {
+      alternative != &right
+    }
+  });
+  alternatives.push(right);
+  if alternatives.len() == 1 {
+    alternatives.pop().unwrap()
+  } else {
+    Schema::OneOf(alternatives)
+  }
+}
+
+/// Return whether constant data conforms to a schema.
+fn conforms(constant: &Constant, schema: &Schema) -> bool {
+  match (constant, schema) {
+    (_, &Schema::OneOf(ref alternatives)) =>
+      alternatives.iter().any(|alternative| conforms(constant, alternative)),
+    (&Constant::Null, &Schema::Optional(_)) => true,
+    (_, &Schema::Optional(ref schema)) => conforms(constant, schema),
+    (&Constant::AllOf(ref elements), &Schema::AllOf(ref schemas)) =>
+      elements.len() == schemas.len()
+      && elements.iter().zip(schemas).all(|(e, s)| conforms(e, s)),
+    (&Constant::Record(ref fields), &Schema::Record(ref schemas)) =>
+      fields.len() == schemas.len()
+      && fields.iter().zip(schemas)
+         .all(|(&(ref name, ref field), &(ref other, ref schema))| {
+           name == other && conforms(field, schema)
+         }),
+    (&Constant::ArrayOf(ref elements), &Schema::ArrayOf(ref schema)) =>
+      elements.iter().all(|element| conforms(element, schema)),
+    (&Constant::Boolean(_), &Schema::Boolean)
+    | (&Constant::SinglePrecision(_), &Schema::SinglePrecision)
+    | (&Constant::DoublePrecision(_), &Schema::DoublePrecision)
+    | (&Constant::ByteString(_), &Schema::ByteString)
+    | (&Constant::String(_), &Schema::String) => true,
+    (&Constant::SignedInteger(value), &Schema::SignedInteger(min, max)) =>
+      min <= value && value <= max,
+    _ => false,
+  }
+}
+
+/// Generate the ECMAScript representation of constant data conforming to a
+/// schema. Where the schema has alternatives, the data is tagged with the
+/// first alternative it conforms to, as by the serialization functions.
+fn ecmascript_expression(write: &mut io::Write, constant: &Constant,
+                         schema: &Schema) -> Result<()> {
+  match (constant, schema) {
+    (_, &Schema::OneOf(ref alternatives)) => {
+      let tag = alternatives.iter()
+        .position(|alternative| conforms(constant, alternative))
+        .expect("constant does not conform to its schema");
+      write!(write, "{{tag: {}, value: ", tag)?;
+      ecmascript_expression(write, constant, &alternatives[tag])?;
+      write!(write, "}}")?;
+      return Ok(());
+    },
+    (&Constant::Null, _) => (),
+    (_, &Schema::Optional(ref schema)) =>
+      return ecmascript_expression(write, constant, schema),
+    _ => (),
+  }
   match *constant {
     Constant::AllOf(ref elements) => {
+      let schemas = match *schema {
+        Schema::AllOf(ref schemas) => schemas,
+        _ => unreachable!(),
+      };
+      write!(write, "[\n")?;
+      for (element, schema) in elements.iter().zip(schemas) {
+        ecmascript_expression(write, element, schema)?;
+        write!(write, ",\n")?;
+      }
+      write!(write, "]")?;
+    },
+    Constant::ArrayOf(ref elements) => {
+      let schema = match *schema {
+        Schema::ArrayOf(ref schema) => schema,
+        _ => unreachable!(),
+      };
       write!(write, "[\n")?;
       for element in elements {
-        ecmascript_expression(write, element)?;
+        ecmascript_expression(write, element, schema)?;
+        write!(write, ",\n")?;
+      }
+      write!(write, "]")?;
+    },
+    Constant::Record(ref fields) => {
+      let schemas = match *schema {
+        Schema::Record(ref schemas) => schemas,
+        _ => unreachable!(),
+      };
+      write!(write, "{{\n")?;
+      for (&(ref name, ref field), &(_, ref schema))
+          in fields.iter().zip(schemas) {
+        write!(write, "{}: ", escape::ecmascript_string(name))?;
+        ecmascript_expression(write, field, schema)?;
         write!(write, ",\n")?;
       }
+      write!(write, "}}")?;
+    },
+    Constant::Null => write!(write, "null")?,
+    Constant::Boolean(value) => write!(write, "{}", value)?,
+    Constant::SignedInteger(value) => write!(write, "{}", value)?,
+    Constant::SinglePrecision(value) => write!(write, "{}", value)?,
+    Constant::DoublePrecision(value) => write!(write, "{}", value)?,
+    Constant::ByteString(_) =>
+      return Err(Error::Unsupported(error::BYTE_STRING.to_string())),
+    Constant::String(ref value) =>
+      write!(write, "{}", escape::ecmascript_string(value))?,
+  }
+  Ok(())
+}
+
+/// Generate a Rust expression for constant data conforming to a schema, of
+/// the type given by `rust_type` for the schema and hint. Where the schema
+/// has alternatives, the data is the variant of the first alternative it
+/// conforms to.
+fn rust_expression(write: &mut io::Write, constant: &Constant, schema: &Schema,
+                   hint: &str) -> Result<()> {
+  match (constant, schema) {
+    (_, &Schema::OneOf(ref alternatives)) => {
+      let tag = alternatives.iter()
+        .position(|alternative| conforms(constant, alternative))
+        .expect("constant does not conform to its schema");
+      rust_type(write, schema, hint)?;
+      write!(write, "::Alternative{}(", tag)?;
+      rust_expression(write, constant, &alternatives[tag],
+                      &rust_hint(hint, &tag.to_string()))?;
+      write!(write, ")")?;
+      return Ok(());
+    },
+    (&Constant::Null, _) => (),
+    (_, &Schema::Optional(ref schema)) => {
+      write!(write, "Some(")?;
+      rust_expression(write, constant, schema, hint)?;
+      write!(write, ")")?;
+      return Ok(());
+    },
+    _ => (),
+  }
+  match *constant {
+    Constant::AllOf(ref elements) => {
+      let schemas = match *schema {
+        Schema::AllOf(ref schemas) => schemas,
+        _ => unreachable!(),
+      };
+      write!(write, "(")?;
+      for (index, (element, schema))
+          in elements.iter().zip(schemas).enumerate() {
+        write!(write, "{}", if index == 0 { "" } else { ", " })?;
+        rust_expression(write, element, schema,
+                        &rust_hint(hint, &index.to_string()))?;
+      }
+      write!(write, "{})", if elements.len() == 1 { "," } else { "" })?;
+    },
+    Constant::ArrayOf(ref elements) => {
+      let schema = match *schema {
+        Schema::ArrayOf(ref schema) => schema,
+        _ => unreachable!(),
+      };
+      write!(write, "vec![")?;
+      for (index, element) in elements.iter().enumerate() {
+        write!(write, "{}", if index == 0 { "" } else { ", " })?;
+        rust_expression(write, element, schema, &rust_hint(hint, "element"))?;
+      }
       write!(write, "]")?;
-      Ok(())
     },
-    Constant::SignedInteger(value) => write!(write, "{}", value),
-    Constant::SinglePrecision(value) => write!(write, "{}", value),
-    Constant::DoublePrecision(value) => write!(write, "{}", value),
-    Constant::ByteString(_) => unimplemented!(),
-    Constant::String(ref value) => write!(write, "'{}'", value),
+    Constant::Record(ref fields) => {
+      let schemas = match *schema {
+        Schema::Record(ref schemas) => schemas,
+        _ => unreachable!(),
+      };
+      rust_type(write, schema, hint)?;
+      write!(write, " {{")?;
+      for (&(ref name, ref field), &(_, ref schema))
+          in fields.iter().zip(schemas) {
+        write!(write, " {}: ", escape::rust_value_name(name)?)?;
+        rust_expression(write, field, schema, &rust_hint(hint, name))?;
+        write!(write, ",")?;
+      }
+      write!(write, " }}")?;
+    },
+    Constant::Null => write!(write, "None")?,
+    Constant::Boolean(value) => write!(write, "{}", value)?,
+    Constant::SignedInteger(value) => write!(write, "{}", value)?,
+    Constant::SinglePrecision(value) if value.is_finite() =>
+      write!(write, "{:?}_f32", value)?,
+    Constant::DoublePrecision(value) if value.is_finite() =>
+      write!(write, "{:?}_f64", value)?,
+    Constant::SinglePrecision(value) =>
+      write!(write, "std::f32::{}", rust_non_finite(value as f64))?,
+    Constant::DoublePrecision(value) =>
+      write!(write, "std::f64::{}", rust_non_finite(value))?,
+    Constant::ByteString(ref value) => write!(write, "Bytes(vec!{:?})", value)?,
+    Constant::String(ref value) =>
+      write!(write, "{}.to_string()", escape::rust_string(value))?,
+  }
+  Ok(())
+}
+
+/// Return the name of the constant of Rust floating-point types for a number
+/// that is not finite, and has no literal.
+fn rust_non_finite(value: f64) -> &'static str {
+  if value.is_nan() {
+    "NAN"
+  } else if value > 0.0 {
+    "INFINITY"
+  } else {
+    "NEG_INFINITY"
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_array_of_widens_bounds() {
+    let constant = Constant::ArrayOf(vec![
+      Constant::SignedInteger(3),
+      Constant::String("".to_string()),
+      Constant::SignedInteger(-1),
+    ]);
+    let expected = Schema::ArrayOf(Box::new(Schema::OneOf(vec![
+      Schema::String,
+      Schema::SignedInteger(-1, 3),
+    ])));
+    assert_eq!(output_schema(&constant), expected);
+  }
+
+  #[test]
+  fn test_array_of_nullable() {
+    let constant = Constant::ArrayOf(vec![
+      Constant::Null,
+      Constant::SignedInteger(1),
+      Constant::Null,
+    ]);
+    let expected = Schema::ArrayOf(Box::new(Schema::Optional(Box::new(
+      Schema::SignedInteger(1, 1),
+    ))));
+    assert_eq!(output_schema(&constant), expected);
+  }
+
+  #[test]
+  fn test_ecmascript_tags_alternatives() {
+    let constant = Constant::ArrayOf(vec![
+      Constant::SignedInteger(1),
+      Constant::String("a".to_string()),
+      Constant::Null,
+    ]);
+    let mut output = vec![];
+    constant.ecmascript_call(&mut output).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), concat!(
+      "(function() {\nreturn [\n{tag: 0, value: 1},\n",
+      "{tag: 1, value: 'a'},\nnull,\n];\n})"));
+  }
+
+  #[test]
+  fn test_array_of_empty() {
+    let constant = Constant::ArrayOf(vec![]);
+    let expected = Schema::ArrayOf(Box::new(Schema::OneOf(vec![])));
+    assert_eq!(output_schema(&constant), expected);
+  }
+
+  #[test]
+  fn test_rust_non_finite() {
+    let constant = Constant::AllOf(vec![
+      Constant::SinglePrecision(1.5),
+      Constant::SinglePrecision(f32::NAN),
+      Constant::DoublePrecision(f64::INFINITY),
+      Constant::DoublePrecision(f64::NEG_INFINITY),
+    ]);
+    let mut output = vec![];
+    rust_expression(&mut output, &constant, &output_schema(&constant), "c")
+      .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), concat!(
+      "(1.5_f32, std::f32::NAN, std::f64::INFINITY, ",
+      "std::f64::NEG_INFINITY)"));
   }
 }
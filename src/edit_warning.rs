@@ -32,4 +32,5 @@ pub const PHP: &'static str = concat!("/* ", edit_warning!(), " */");
 pub const PURESCRIPT: &'static str = concat!("{- ", edit_warning!(), " -}");
 pub const RUST: &'static str = concat!("/* ", edit_warning!(), " */");
 pub const SCALA: &'static str = concat!("/* ", edit_warning!(), " */");
+pub const TYPESCRIPT: &'static str = concat!("/* ", edit_warning!(), " */");
 pub const XML: &'static str = concat!("<!-- ", edit_warning!(), " -->");
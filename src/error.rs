@@ -0,0 +1,98 @@
+//! This module defines the error type that is returned when extracting
+//! schemas from sources or generating assets fails.
+
+use postgres;
+use std::error;
+use std::fmt;
+use std::io;
+use std::result;
+
+/// The result of extracting schemas or generating assets.
+pub type Result<T> = result::Result<T, Error>;
+
+/// An error that occurred while extracting schemas or generating assets.
+#[derive(Debug)]
+pub enum Error {
+  /// Reading a source or writing a generated asset failed.
+  Io(io::Error),
+
+  /// The database rejected a query.
+  Database(postgres::error::Error),
+
+  /// The database could not be reached.
+  Connect(postgres::error::ConnectError),
+
+  /// A source uses a type that has no corresponding schema. The name of the
+  /// type is given.
+  UnsupportedType(String),
+
+  /// A source could not be parsed. A description of the problem is given.
+  Syntax(String),
+
+  /// A manifest is inconsistent. A description of the problem is given.
+  Manifest(String),
+
+  /// The schema cache cannot be used. A description of the problem is given.
+  Cache(String),
+
+  /// A source needs a database connection, but none is configured and no
+  /// cache can stand in for it. A description of the problem is given.
+  NoDatabase(String),
+
+  /// A source does not have the shape it was expected to have.
+  ShapeMismatch(String),
+
+  /// A generator cannot generate an asset for the given schema or data.
+  Unsupported(String),
+
+  /// A name cannot be made into a valid identifier in generated code.
+  InvalidName(String),
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Error::Io(ref error) => write!(f, "{}", error),
+      Error::Database(ref error) => write!(f, "{}", error),
+      Error::Connect(ref error) => write!(f, "{}", error),
+      Error::UnsupportedType(ref name) =>
+        write!(f, "The type {} has no corresponding schema.", name),
+      Error::Syntax(ref message) => write!(f, "{}", message),
+      Error::Manifest(ref message) => write!(f, "{}", message),
+      Error::Cache(ref message) => write!(f, "{}", message),
+      Error::NoDatabase(ref message) => write!(f, "{}", message),
+      Error::ShapeMismatch(ref message) => write!(f, "{}", message),
+      Error::Unsupported(ref message) => write!(f, "{}", message),
+      Error::InvalidName(ref message) => write!(f, "{}", message),
+    }
+  }
+}
+
+impl error::Error for Error {
+  fn source(&self) -> Option<&(error::Error + 'static)> {
+    match *self {
+      Error::Io(ref error) => Some(error),
+      Error::Database(ref error) => Some(error),
+      Error::Connect(ref error) => Some(error),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for Error {
+  fn from(error: io::Error) -> Error {
+    Error::Io(error)
+  }
+}
+
+impl From<postgres::error::Error> for Error {
+  fn from(error: postgres::error::Error) -> Error {
+    Error::Database(error)
+  }
+}
+
+impl From<postgres::error::ConnectError> for Error {
+  fn from(error: postgres::error::ConnectError) -> Error {
+    Error::Connect(error)
+  }
+}
@@ -0,0 +1,330 @@
+//! This module escapes names and text for inclusion in generated code, so
+//! that what comes from sources cannot break out of the literals and
+//! identifiers it is placed in. Names that cannot be made into valid
+//! identifiers are rejected.
+
+use error::{Error, Result};
+use serde_json::Value;
+
+/// The reserved words of ECMAScript, including those of strict mode, and the
+/// names that cannot be declared in strict mode, in which modules are.
+const ECMASCRIPT_RESERVED: &'static [&'static str] = &[
+  "arguments", "await", "break", "case", "catch", "class", "const",
+  "continue", "debugger", "default", "delete", "do", "else", "enum", "eval",
+  "export", "extends", "false", "finally", "for", "function", "if",
+  "implements", "import", "in", "instanceof", "interface", "let", "new",
+  "null", "package", "private", "protected", "public", "return", "static",
+  "super", "switch", "this", "throw", "true", "try", "typeof", "var", "void",
+  "while", "with", "yield",
+];
+
+/// The reserved words of PureScript.
+const PURESCRIPT_RESERVED: &'static [&'static str] = &[
+  "ado", "case", "class", "data", "derive", "do", "else", "false", "forall",
+  "foreign", "if", "import", "in", "infix", "infixl", "infixr", "instance",
+  "let", "module", "newtype", "of", "then", "true", "type", "where",
+];
+
+/// The keywords of Rust, including reserved ones.
+const RUST_RESERVED: &'static [&'static str] = &[
+  "abstract", "as", "async", "await", "become", "box", "break", "const",
+  "continue", "crate", "do", "dyn", "else", "enum", "extern", "false",
+  "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro",
+  "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+  "self", "static", "struct", "super", "trait", "true", "try", "type",
+  "typeof", "union", "unsafe", "unsized", "use", "virtual", "where", "while",
+  "yield",
+];
+
+/// Return an ECMAScript string literal with the given contents.
+pub fn ecmascript_string(text: &str) -> String {
+  let mut literal = String::with_capacity(text.len() + 2);
+  literal.push('\'');
+  for c in text.chars() {
+    match c {
+      '\\' => literal.push_str("\\\\"),
+      '\'' => literal.push_str("\\'"),
+      '\n' => literal.push_str("\\n"),
+      '\r' => literal.push_str("\\r"),
+      '\t' => literal.push_str("\\t"),
+      // Line terminators, and a tag end that would close a script element.
+      '\u{2028}' | '\u{2029}' | '<' =>
+        literal.push_str(&format!("\\u{:04x}", c as u32)),
+      c if c.is_control() && (c as u32) < 0x100 =>
+        literal.push_str(&format!("\\x{:02x}", c as u32)),
+      c => literal.push(c),
+    }
+  }
+  literal.push('\'');
+  literal
+}
+
+/// Return an ECMAScript expression for a JSON value.
+pub fn ecmascript_json(value: &Value) -> String {
+  // JSON is valid ECMAScript, except for these line terminators in strings.
+  // Tag ends are escaped as in string literals.
+  value.to_string()
+  .replace('\u{2028}', "\\u2028")
+  .replace('\u{2029}', "\\u2029")
+  .replace('<', "\\u003c")
+}
+
+/// Check that a name is a valid ECMAScript identifier, such as the name of an
+/// exported call.
+pub fn ecmascript_identifier(name: &str) -> Result<String> {
+  if is_ecmascript_identifier(name) && !ECMASCRIPT_RESERVED.contains(&name) {
+    Ok(name.to_string())
+  } else {
+    Err(invalid(name, "ECMAScript identifier"))
+  }
+}
+
+/// Return an ECMAScript property name, which is quoted unless it is an
+/// identifier.
+pub fn ecmascript_property(name: &str) -> String {
+  if is_ecmascript_identifier(name) {
+    name.to_string()
+  } else {
+    ecmascript_string(name)
+  }
+}
+
+/// Convert a name such as `user_status` to an ECMAScript type name such as
+/// `UserStatus`, for TypeScript.
+pub fn ecmascript_type_name(name: &str) -> Result<String> {
+  let type_name = pascal_case(name);
+  ecmascript_identifier(&type_name)
+    .map_err(|_| invalid(name, "TypeScript type name"))
+}
+
+fn is_ecmascript_identifier(name: &str) -> bool {
+  let mut chars = name.chars();
+  chars.next()
+  .map_or(false, |c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+  && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Convert a name such as `user_status` to a PureScript type name such as
+/// `UserStatus`.
+pub fn purescript_type_name(name: &str) -> Result<String> {
+  let type_name = pascal_case(name);
+  match type_name.chars().next() {
+    Some(c) if c.is_uppercase() => Ok(type_name),
+    _ => Err(invalid(name, "PureScript type name")),
+  }
+}
+
+/// Convert a name such as `user_status` to a PureScript value name such as
+/// `userStatus`.
+pub fn purescript_value_name(name: &str) -> Result<String> {
+  let type_name = pascal_case(name);
+  let mut chars = type_name.chars();
+  let value_name = chars.next().into_iter()
+    .flat_map(char::to_lowercase)
+    .chain(chars)
+    .collect::<String>();
+  purescript_identifier(&value_name)
+    .map_err(|_| invalid(name, "PureScript value name"))
+}
+
+/// Check that a name is a valid PureScript module name, such as
+/// `App.Generated.Client`.
+pub fn purescript_module_name(name: &str) -> Result<String> {
+  let valid = name.split('.').all(|segment| {
+    let mut chars = segment.chars();
+    chars.next().map_or(false, |c| c.is_uppercase())
+    && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '\'')
+  });
+  if valid {
+    Ok(name.to_string())
+  } else {
+    Err(invalid(name, "PureScript module name"))
+  }
+}
+
+/// Check that a name is a valid PureScript value name, such as the name of a
+/// foreign import.
+pub fn purescript_identifier(name: &str) -> Result<String> {
+  if is_purescript_identifier(name) && !PURESCRIPT_RESERVED.contains(&name) {
+    Ok(name.to_string())
+  } else {
+    Err(invalid(name, "PureScript identifier"))
+  }
+}
+
+/// Return a PureScript record label, which is quoted unless it is an
+/// identifier.
+pub fn purescript_label(name: &str) -> String {
+  if is_purescript_identifier(name) && !PURESCRIPT_RESERVED.contains(&name) {
+    name.to_string()
+  } else {
+    purescript_string(name)
+  }
+}
+
+fn is_purescript_identifier(name: &str) -> bool {
+  let mut chars = name.chars();
+  chars.next().map_or(false, |c| c.is_lowercase() || c == '_')
+  && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '\'')
+}
+
+/// Return a PureScript string literal with the given contents.
+pub fn purescript_string(text: &str) -> String {
+  let mut literal = String::with_capacity(text.len() + 2);
+  literal.push('"');
+  for c in text.chars() {
+    match c {
+      '\\' => literal.push_str("\\\\"),
+      '"' => literal.push_str("\\\""),
+      '\n' => literal.push_str("\\n"),
+      '\r' => literal.push_str("\\r"),
+      '\t' => literal.push_str("\\t"),
+      c if c.is_control() => literal.push_str(&format!("\\x{:06x}", c as u32)),
+      c => literal.push(c),
+    }
+  }
+  literal.push('"');
+  literal
+}
+
+/// Convert a name such as `user_status` to a Rust type name such as
+/// `UserStatus`.
+pub fn rust_type_name(name: &str) -> Result<String> {
+  let type_name = pascal_case(name);
+  match type_name.chars().next() {
+    Some(c) if c.is_ascii_uppercase() && type_name != "Self" => Ok(type_name),
+    _ => Err(invalid(name, "Rust type name")),
+  }
+}
+
+/// Convert a name such as `firstName` or `first-name` to a Rust value name
+/// such as `first_name`, for fields and functions. Keywords get a trailing
+/// underscore.
+pub fn rust_value_name(name: &str) -> Result<String> {
+  let mut value_name = String::with_capacity(name.len());
+  let mut previous = None;
+  for c in name.chars() {
+    if c.is_uppercase() {
+      if previous.map_or(false, |p: char| p.is_lowercase() || p.is_numeric()) {
+        value_name.push('_');
+      }
+      value_name.extend(c.to_lowercase());
+    } else if c.is_alphanumeric() {
+      value_name.push(c);
+    } else if !value_name.is_empty() && !value_name.ends_with('_') {
+      value_name.push('_');
+    }
+    previous = Some(c);
+  }
+  while value_name.ends_with('_') && !name.ends_with('_') {
+    value_name.pop();
+  }
+  match value_name.chars().next() {
+    Some(c) if c.is_ascii_alphabetic() => (),
+    Some('_') if value_name.len() > 1 => (),
+    _ => return Err(invalid(name, "Rust value name")),
+  }
+  if RUST_RESERVED.contains(&value_name.as_str()) {
+    value_name.push('_');
+  }
+  Ok(value_name)
+}
+
+/// Return a Rust string literal with the given contents.
+pub fn rust_string(text: &str) -> String {
+  let mut literal = String::with_capacity(text.len() + 2);
+  literal.push('"');
+  for c in text.chars() {
+    match c {
+      '\\' => literal.push_str("\\\\"),
+      '"' => literal.push_str("\\\""),
+      '\n' => literal.push_str("\\n"),
+      '\r' => literal.push_str("\\r"),
+      '\t' => literal.push_str("\\t"),
+      c if c.is_control() =>
+        literal.push_str(&format!("\\u{{{:x}}}", c as u32)),
+      c => literal.push(c),
+    }
+  }
+  literal.push('"');
+  literal
+}
+
+/// Percent-encode a name for use as a segment of a URL path. Only unreserved
+/// characters are left alone, so that the segment is also matched literally
+/// by Express routes.
+pub fn url_path_segment(name: &str) -> String {
+  let mut segment = String::with_capacity(name.len());
+  for &byte in name.as_bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' =>
+        segment.push(byte as char),
+      _ => segment.push_str(&format!("%{:02X}", byte)),
+    }
+  }
+  segment
+}
+
+/// Split a name at non-alphanumeric characters, and capitalize each word.
+fn pascal_case(name: &str) -> String {
+  name.split(|c: char| !c.is_alphanumeric())
+  .flat_map(|word| {
+    let mut chars = word.chars();
+    chars.next().into_iter()
+    .flat_map(char::to_uppercase)
+    .chain(chars)
+  })
+  .collect()
+}
+
+fn invalid(name: &str, kind: &str) -> Error {
+  Error::InvalidName(format!("The name {} cannot be made into a valid {}.",
+                             ecmascript_string(name), kind))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_ecmascript_string() {
+    assert_eq!(ecmascript_string("it's\n\\ </script>\u{2028}\u{0}"),
+               "'it\\'s\\n\\\\ \\u003c/script>\\u2028\\x00'");
+  }
+
+  #[test]
+  fn test_names() {
+    assert_eq!(ecmascript_identifier("getUser").map_err(|e| e.to_string()),
+               Ok("getUser".to_string()));
+    assert_eq!(ecmascript_identifier("delete").map_err(|e| e.to_string()),
+               Err(concat!("The name 'delete' cannot be made into a valid ",
+                           "ECMAScript identifier.").to_string()));
+    assert!(ecmascript_identifier("get-user").is_err());
+    assert!(ecmascript_identifier("eval").is_err());
+    assert!(ecmascript_identifier("arguments").is_err());
+    assert_eq!(ecmascript_property("first-name"), "'first-name'");
+    assert_eq!(purescript_type_name("user_status").map_err(|e| e.to_string()),
+               Ok("UserStatus".to_string()));
+    assert!(purescript_type_name("2fa").is_err());
+    assert_eq!(purescript_value_name("mood_very-happy")
+               .map_err(|e| e.to_string()),
+               Ok("moodVeryHappy".to_string()));
+    assert!(purescript_identifier("data").is_err());
+    assert!(purescript_module_name("App.Client").is_ok());
+    assert!(purescript_module_name("App..client").is_err());
+    assert_eq!(purescript_label("type"), "\"type\"");
+    assert_eq!(purescript_label("name"), "name");
+    assert_eq!(url_path_segment("a b/$c"), "a%20b%2F%24c");
+    assert_eq!(rust_type_name("user_status").map_err(|e| e.to_string()),
+               Ok("UserStatus".to_string()));
+    assert!(rust_type_name("2fa").is_err());
+    assert_eq!(rust_value_name("remoteSettings").map_err(|e| e.to_string()),
+               Ok("remote_settings".to_string()));
+    assert_eq!(rust_value_name("first-name").map_err(|e| e.to_string()),
+               Ok("first_name".to_string()));
+    assert_eq!(rust_value_name("type").map_err(|e| e.to_string()),
+               Ok("type_".to_string()));
+    assert!(rust_value_name("2fa").is_err());
+    assert_eq!(rust_string("\"a\\\n\u{0}"), "\"\\\"a\\\\\\n\\u{0}\"");
+  }
+}
@@ -0,0 +1,348 @@
+//! This module implements a stable JSON encoding of schemas, for exchanging
+//! them with other tools, and a source that reads schemas so encoded.
+//!
+//! Every schema is encoded as an object with a `type` member, which is the
+//! name of the `Schema` variant in snake case, and members for the data of
+//! the variant:
+//!
+//! | Type | Members |
+//! | --- | --- |
+//! | `all_of` | `elements`: array of schemas |
+//! | `record` | `fields`: array of objects with `name` and `schema` |
+//! | `array_of`, `optional` | `element`: schema |
+//! | `one_of` | `alternatives`: array of schemas |
+//! | `named` | `name`: string, `schema`: schema |
+//! | `enumeration` | `labels`: array of strings |
+//! | `signed_integer` | `minimum`, `maximum`: numbers |
+//! | `signed_integer_64` | `minimum`, `maximum`: decimal strings |
+//!
+//! The bounds of 64-bit integers are strings, because many JSON parsers
+//! cannot represent them as numbers. All other types have no members.
+
+use Schema;
+use error::{Error, Result};
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use serde_json::{self, Map, Value};
+use source::HasSchema;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::rc::Rc;
+
+/// Encode a schema.
+pub fn encode(schema: &Schema) -> Value {
+  let mut object = Map::new();
+  let mut member = |name: &str, value: Value| {
+    object.insert(name.to_string(), value);
+  };
+  let type_ = match *schema {
+    Schema::AllOf(ref elements) => {
+      member("elements", Value::Array(elements.iter().map(encode).collect()));
+      "all_of"
+    },
+    Schema::Record(ref fields) => {
+      member("fields", Value::Array(
+        fields.iter()
+        .map(|&(ref name, ref schema)| json!({
+          "name": name,
+          "schema": encode(schema),
+        }))
+        .collect()
+      ));
+      "record"
+    },
+    Schema::ArrayOf(ref element) => {
+      member("element", encode(element));
+      "array_of"
+    },
+    Schema::Optional(ref element) => {
+      member("element", encode(element));
+      "optional"
+    },
+    Schema::OneOf(ref alternatives) => {
+      member("alternatives",
+             Value::Array(alternatives.iter().map(encode).collect()));
+      "one_of"
+    },
+    Schema::Named(ref name, ref schema) => {
+      member("name", Value::String(name.clone()));
+      member("schema", encode(schema));
+      "named"
+    },
+    Schema::Enumeration(ref labels) => {
+      member("labels", json!(labels));
+      "enumeration"
+    },
+    Schema::Boolean => "boolean",
+    Schema::SignedInteger(minimum, maximum) => {
+      member("minimum", json!(minimum));
+      member("maximum", json!(maximum));
+      "signed_integer"
+    },
+    Schema::SignedInteger64(minimum, maximum) => {
+      member("minimum", Value::String(minimum.to_string()));
+      member("maximum", Value::String(maximum.to_string()));
+      "signed_integer_64"
+    },
+    Schema::Decimal => "decimal",
+    Schema::SinglePrecision => "single_precision",
+    Schema::DoublePrecision => "double_precision",
+    Schema::ByteString => "byte_string",
+    Schema::String => "string",
+    Schema::Uuid => "uuid",
+    Schema::Date => "date",
+    Schema::Timestamp => "timestamp",
+    Schema::TimestampWithTimeZone => "timestamp_with_time_zone",
+    Schema::Interval => "interval",
+    Schema::Json => "json",
+  };
+  object.insert("type".to_string(), Value::String(type_.to_string()));
+  Value::Object(object)
+}
+
+/// Decode a schema.
+pub fn decode(value: &Value) -> Result<Schema> {
+  decode_at(value, "")
+}
+
+/// Decode a schema found at a JSON pointer, which is used in error messages.
+fn decode_at(value: &Value, pointer: &str) -> Result<Schema> {
+  let invalid = |pointer: &str, expected: &str| Error::Syntax(
+    format!("Invalid schema at \"{}\": expected {}.", pointer, expected));
+  let member = |name: &str| value.get(name).ok_or_else(|| {
+    invalid(pointer, &format!("a member named {}", name))
+  });
+  let schemas = |name: &str| -> Result<Vec<Schema>> {
+    member(name)?.as_array().ok_or_else(|| {
+      invalid(&format!("{}/{}", pointer, name), "an array")
+    })?
+    .iter().enumerate()
+    .map(|(index, value)| {
+      decode_at(value, &format!("{}/{}/{}", pointer, name, index))
+    })
+    .collect()
+  };
+  let string = |value: &Value, pointer: &str| -> Result<String> {
+    value.as_str().map(str::to_string).ok_or_else(|| invalid(pointer, "a string"))
+  };
+
+  let type_ = string(member("type")?, &format!("{}/type", pointer))?;
+  match type_.as_str() {
+    "all_of" => Ok(Schema::AllOf(schemas("elements")?)),
+    "record" => {
+      let fields = member("fields")?.as_array().ok_or_else(|| {
+        invalid(&format!("{}/fields", pointer), "an array")
+      })?;
+      let mut record = vec![];
+      for (index, field) in fields.iter().enumerate() {
+        let pointer = format!("{}/fields/{}", pointer, index);
+        let name = field.get("name").ok_or_else(|| {
+          invalid(&pointer, "a member named name")
+        })?;
+        let schema = field.get("schema").ok_or_else(|| {
+          invalid(&pointer, "a member named schema")
+        })?;
+        record.push((string(name, &format!("{}/name", pointer))?,
+                     decode_at(schema, &format!("{}/schema", pointer))?));
+      }
+      Ok(Schema::Record(record))
+    },
+    "array_of" => Ok(Schema::ArrayOf(Box::new(
+      decode_at(member("element")?, &format!("{}/element", pointer))?))),
+    "optional" => Ok(Schema::Optional(Box::new(
+      decode_at(member("element")?, &format!("{}/element", pointer))?))),
+    "one_of" => Ok(Schema::OneOf(schemas("alternatives")?)),
+    "named" => Ok(Schema::Named(
+      string(member("name")?, &format!("{}/name", pointer))?,
+      Box::new(decode_at(member("schema")?, &format!("{}/schema", pointer))?),
+    )),
+    "enumeration" => {
+      let labels = member("labels")?.as_array().ok_or_else(|| {
+        invalid(&format!("{}/labels", pointer), "an array")
+      })?;
+      labels.iter().enumerate()
+      .map(|(index, label)| {
+        string(label, &format!("{}/labels/{}", pointer, index))
+      })
+      .collect::<Result<_>>()
+      .map(Schema::Enumeration)
+    },
+    "boolean" => Ok(Schema::Boolean),
+    "signed_integer" => {
+      let bound = |name: &str| -> Result<i32> {
+        member(name)?.as_i64()
+        .filter(|&bound| bound as i32 as i64 == bound)
+        .map(|bound| bound as i32)
+        .ok_or_else(|| {
+          invalid(&format!("{}/{}", pointer, name), "a 32-bit integer")
+        })
+      };
+      Ok(Schema::SignedInteger(bound("minimum")?, bound("maximum")?))
+    },
+    "signed_integer_64" => {
+      let bound = |name: &str| -> Result<i64> {
+        member(name)?.as_str()
+        .and_then(|bound| bound.parse().ok())
+        .ok_or_else(|| {
+          invalid(&format!("{}/{}", pointer, name),
+                  "a 64-bit integer in a string")
+        })
+      };
+      Ok(Schema::SignedInteger64(bound("minimum")?, bound("maximum")?))
+    },
+    "decimal" => Ok(Schema::Decimal),
+    "single_precision" => Ok(Schema::SinglePrecision),
+    "double_precision" => Ok(Schema::DoublePrecision),
+    "byte_string" => Ok(Schema::ByteString),
+    "string" => Ok(Schema::String),
+    "uuid" => Ok(Schema::Uuid),
+    "date" => Ok(Schema::Date),
+    "timestamp" => Ok(Schema::Timestamp),
+    "timestamp_with_time_zone" => Ok(Schema::TimestampWithTimeZone),
+    "interval" => Ok(Schema::Interval),
+    "json" => Ok(Schema::Json),
+    _ => Err(invalid(&format!("{}/type", pointer), "a known type")),
+  }
+}
+
+impl Serialize for Schema {
+  fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where S: Serializer {
+    encode(self).serialize(serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for Schema {
+  fn deserialize<D>(deserializer: D) -> ::std::result::Result<Schema, D::Error>
+    where D: Deserializer<'de> {
+    let value = Value::deserialize(deserializer)?;
+    decode(&value).map_err(de::Error::custom)
+  }
+}
+
+/// A source that reads its schemas from a JSON document of the form
+/// `{"input": schema, "output": schema}`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+  /// The input schema.
+  pub input: Rc<Schema>,
+
+  /// The output schema.
+  pub output: Rc<Schema>,
+}
+
+impl Document {
+  /// Return a document with the schemas of a source.
+  pub fn new(source: &HasSchema) -> Result<Document> {
+    let (input, output) = source.schema()?;
+    Ok(Document{input: input, output: output})
+  }
+
+  /// Read a document from a file.
+  pub fn read<P>(path: P) -> Result<Document> where P: AsRef<Path> {
+    let path = path.as_ref();
+    let mut text = String::new();
+    File::open(path)?.read_to_string(&mut text)?;
+    Document::parse(&text)
+    .map_err(|error| Error::Syntax(format!("{}: {}", path.display(), error)))
+  }
+
+  /// Parse a document.
+  pub fn parse(text: &str) -> Result<Document> {
+    let value: Value = serde_json::from_str(text)
+      .map_err(|error| Error::Syntax(error.to_string()))?;
+    let schema = |name: &str| -> Result<Rc<Schema>> {
+      let value = value.get(name).ok_or_else(|| Error::Syntax(
+        format!("Invalid document: expected a member named {}.", name)))?;
+      decode_at(value, &format!("/{}", name)).map(Rc::new)
+    };
+    Ok(Document{input: schema("input")?, output: schema("output")?})
+  }
+
+  /// Write the document.
+  pub fn write(&self, write: &mut io::Write) -> Result<()> {
+    let value = json!({
+      "input": encode(&self.input),
+      "output": encode(&self.output),
+    });
+    serde_json::to_writer_pretty(&mut *write, &value)
+      .map_err(|error| Error::Io(error.into()))?;
+    write!(write, "\n")?;
+    Ok(())
+  }
+}
+
+impl HasSchema for Document {
+  fn schema(&self) -> Result<(Rc<Schema>, Rc<Schema>)> {
+    Ok((self.input.clone(), self.output.clone()))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::{i32, i64};
+
+  #[test]
+  fn test_round_trip() {
+    let schema = Schema::Record(vec![
+      ("a".to_string(), Schema::AllOf(vec![Schema::Boolean, Schema::String])),
+      ("b".to_string(), Schema::ArrayOf(Box::new(Schema::Optional(Box::new(
+        Schema::OneOf(vec![Schema::Decimal, Schema::SinglePrecision])))))),
+      ("c".to_string(), Schema::Named("mood".to_string(), Box::new(
+        Schema::Enumeration(vec!["sad".to_string(), "happy".to_string()])))),
+      ("d".to_string(), Schema::SignedInteger(i32::MIN, i32::MAX)),
+      ("e".to_string(), Schema::SignedInteger64(i64::MIN, i64::MAX)),
+      ("f".to_string(), Schema::AllOf(vec![
+        Schema::DoublePrecision, Schema::ByteString, Schema::Uuid,
+        Schema::Date, Schema::Timestamp, Schema::TimestampWithTimeZone,
+        Schema::Interval, Schema::Json,
+      ])),
+    ]);
+    let text = serde_json::to_string(&schema).unwrap();
+    let decoded: Schema = serde_json::from_str(&text).unwrap();
+    assert_eq!(decoded, schema);
+  }
+
+  #[test]
+  fn test_encode() {
+    let schema = Schema::Optional(Box::new(
+      Schema::SignedInteger64(0, 4294967295)));
+    assert_eq!(encode(&schema), json!({
+      "type": "optional",
+      "element": {
+        "type": "signed_integer_64",
+        "minimum": "0",
+        "maximum": "4294967295",
+      },
+    }));
+  }
+
+  #[test]
+  fn test_decode_errors() {
+    let cases = vec![
+      (json!({"type": "float"}),
+       "Invalid schema at \"/type\": expected a known type."),
+      (json!({"type": "array_of"}),
+       "Invalid schema at \"\": expected a member named element."),
+      (json!({"type": "all_of", "elements": [{"type": "signed_integer",
+                                              "minimum": 0,
+                                              "maximum": 4294967295u32}]}),
+       "Invalid schema at \"/elements/0/maximum\": expected a 32-bit integer."),
+    ];
+    for (value, message) in cases {
+      let schema = decode(&value).map_err(|e| e.to_string());
+      assert_eq!(schema, Err(message.to_string()));
+    }
+  }
+
+  #[test]
+  fn test_document() {
+    let text = r#"{"input": {"type": "all_of", "elements": []},
+                   "output": {"type": "string"}}"#;
+    let document = Document::parse(text).map_err(|e| e.to_string());
+    assert_eq!(document.and_then(|d| d.schema().map_err(|e| e.to_string())),
+               Ok((Rc::new(Schema::AllOf(vec![])), Rc::new(Schema::String))));
+  }
+}
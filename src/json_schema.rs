@@ -0,0 +1,654 @@
+//! This module generates JSON Schema (draft 2020-12) documents from schemas.
+//! The documents describe the JSON representation used by the generated
+//! ECMAScript serialization functions, so 64-bit integers and decimals are
+//! strings.
+//!
+//! Named schemas are placed in `$defs` and referenced, so that each is
+//! described once.
+//!
+//! This module also implements a source that reads JSON Schema documents. It
+//! understands the subset of JSON Schema that has a corresponding schema,
+//! which includes the generated documents, and rejects the rest.
+
+use Schema;
+use error::{Error, Result};
+use serde_json::{self, Map, Value};
+use source::HasSchema;
+use std::collections::BTreeMap;
+use std::f32;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::rc::Rc;
+use std::{i32, i64};
+
+/// The pattern of the strings that represent 64-bit integers.
+const INTEGER_PATTERN: &'static str = "^-?[0-9]+$";
+
+/// The keywords that do not affect the schema.
+const ANNOTATIONS: &'static [&'static str] = &[
+  "$schema", "$id", "$comment", "$defs", "definitions", "title",
+  "description", "default", "examples", "deprecated", "readOnly", "writeOnly",
+];
+
+/// The base of the references to named schemas in generated documents.
+const DEFS: &'static str = "#/$defs/";
+
+/// The URI of the JSON Schema dialect of generated documents.
+pub const DIALECT: &'static str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Return JSON Schema documents for the input and output schemas of a source.
+pub fn documents(source: &HasSchema) -> Result<(Value, Value)> {
+  let (input, output) = source.schema()?;
+  Ok((document(&input), document(&output)))
+}
+
+/// Return a JSON Schema document for a schema.
+pub fn document(schema: &Schema) -> Value {
+  let mut defs = BTreeMap::new();
+  let root = convert(schema, DEFS, &mut defs);
+  let mut object = match root {
+    Value::Object(object) => object,
+    root => {
+      let mut object = Map::new();
+      object.insert("allOf".to_string(), Value::Array(vec![root]));
+      object
+    },
+  };
+  object.insert("$schema".to_string(), Value::String(DIALECT.to_string()));
+  if !defs.is_empty() {
+    object.insert("$defs".to_string(), json!(defs));
+  }
+  Value::Object(object)
+}
+
+/// Return a JSON Schema document that bundles the input and output schemas of
+/// many calls. The schemas of a call named `c` are found at `#/$defs/c.input`
+/// and `#/$defs/c.output`, next to the named schemas they share.
+pub fn bundle<'a, I>(calls: I) -> Result<Value>
+  where I: IntoIterator<Item = (&'a str, &'a HasSchema)> {
+  let mut defs = BTreeMap::new();
+  let mut roots = vec![];
+  for (name, source) in calls {
+    let (input, output) = source.schema()?;
+    roots.push((format!("{}.input", name), convert(&input, DEFS, &mut defs)));
+    roots.push((format!("{}.output", name), convert(&output, DEFS, &mut defs)));
+  }
+  defs.extend(roots);
+  Ok(json!({
+    "$schema": DIALECT,
+    "$defs": defs,
+  }))
+}
+
+/// Convert a schema, adding the named schemas it contains to `defs`. Named
+/// schemas are referenced by `base` followed by their name, so `defs` must be
+/// placed at `base` in the document.
+pub fn convert(schema: &Schema, base: &str, defs: &mut BTreeMap<String, Value>)
+  -> Value {
+  match *schema {
+    Schema::AllOf(ref elements) => json!({
+      "type": "array",
+      "prefixItems": elements.iter()
+        .map(|element| convert(element, base, defs))
+        .collect::<Vec<_>>(),
+      "minItems": elements.len(),
+      "items": false,
+    }),
+    Schema::Record(ref fields) => {
+      let mut properties = Map::new();
+      let mut required = vec![];
+      for &(ref name, ref field) in fields {
+        properties.insert(name.clone(), convert(field, base, defs));
+        match *field {
+          Schema::Optional(_) => (),
+          _ => required.push(name.clone()),
+        }
+      }
+      json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+      })
+    },
+    Schema::ArrayOf(ref element) => json!({
+      "type": "array",
+      "items": convert(element, base, defs),
+    }),
+    Schema::Optional(ref element) => json!({
+      "anyOf": [convert(element, base, defs), {"type": "null"}],
+    }),
+    Schema::OneOf(ref alternatives) if alternatives.is_empty() =>
+      Value::Bool(false),
+    Schema::OneOf(ref alternatives) => json!({
+      "oneOf": alternatives.iter()
+        .map(|alternative| convert(alternative, base, defs))
+        .collect::<Vec<_>>(),
+    }),
+    Schema::Named(ref name, ref schema) => {
+      if !defs.contains_key(name) {
+        // Insert a placeholder first, in case the schema refers to itself.
+        defs.insert(name.clone(), Value::Bool(true));
+        let def = convert(schema, base, defs);
+        defs.insert(name.clone(), def);
+      }
+      json!({"$ref": format!("{}{}", base, pointer_token(name))})
+    },
+    Schema::Enumeration(ref labels) => json!({
+      "type": "string",
+      "enum": labels,
+    }),
+    Schema::Boolean => json!({"type": "boolean"}),
+    Schema::SignedInteger(minimum, maximum) => json!({
+      "type": "integer",
+      "minimum": minimum,
+      "maximum": maximum,
+    }),
+    Schema::SignedInteger64(_, _) => json!({
+      "type": "string",
+      "pattern": INTEGER_PATTERN,
+    }),
+    Schema::Decimal => json!({"type": "string"}),
+    Schema::SinglePrecision => json!({
+      "type": "number",
+      "minimum": -f32::MAX as f64,
+      "maximum": f32::MAX as f64,
+    }),
+    Schema::DoublePrecision => json!({"type": "number"}),
+    Schema::ByteString => json!({
+      "type": "string",
+      "contentEncoding": "base64",
+    }),
+    Schema::String => json!({"type": "string"}),
+    Schema::Uuid => json!({"type": "string", "format": "uuid"}),
+    Schema::Date => json!({"type": "string", "format": "date"}),
+    Schema::Timestamp
+    | Schema::TimestampWithTimeZone
+    | Schema::Interval => json!({"type": "string"}),
+    Schema::Json => Value::Bool(true),
+  }
+}
+
+/// Escape a name for use as a JSON Pointer reference token.
+fn pointer_token(name: &str) -> String {
+  name.replace('~', "~0").replace('/', "~1")
+}
+
+/// A source whose input and output schemas are read from JSON Schema
+/// documents.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Source {
+  /// The input schema.
+  pub input: Rc<Schema>,
+
+  /// The output schema.
+  pub output: Rc<Schema>,
+}
+
+impl Source {
+  /// Create a source from JSON Schema documents for the input and output.
+  pub fn new(input: &Value, output: &Value) -> Result<Source> {
+    Ok(Source{
+      input: Rc::new(to_schema(input, input)?),
+      output: Rc::new(to_schema(output, output)?),
+    })
+  }
+
+  /// Read a source from files with JSON Schema documents for the input and
+  /// output.
+  pub fn read<P, Q>(input: P, output: Q) -> Result<Source>
+    where P: AsRef<Path>, Q: AsRef<Path> {
+    Source::new(&read_document(input.as_ref())?,
+                &read_document(output.as_ref())?)
+  }
+
+  /// Create a source for a call in a document generated by `bundle`.
+  pub fn bundled(document: &Value, name: &str) -> Result<Source> {
+    let def = |suffix: &str| {
+      let pointer = format!("/$defs/{}", pointer_token(&format!("{}.{}", name,
+                                                               suffix)));
+      let schema = document.pointer(&pointer)
+        .ok_or_else(|| invalid(&format!("#{}", pointer), "is missing"))?;
+      Converter{document: document, references: vec![]}
+      .convert(schema, &format!("#{}", pointer))
+    };
+    Ok(Source{input: Rc::new(def("input")?), output: Rc::new(def("output")?)})
+  }
+}
+
+impl HasSchema for Source {
+  fn schema(&self) -> Result<(Rc<Schema>, Rc<Schema>)> {
+    Ok((self.input.clone(), self.output.clone()))
+  }
+}
+
+fn read_document(path: &Path) -> Result<Value> {
+  let mut text = String::new();
+  File::open(path)?.read_to_string(&mut text)?;
+  serde_json::from_str(&text)
+  .map_err(|error| Error::Syntax(format!("{}: {}", path.display(), error)))
+}
+
+/// Convert a JSON Schema to a schema. References are resolved against
+/// `document`, and may only refer to its `$defs`, `definitions` or, as in
+/// OpenAPI documents, `components/schemas`. Referenced schemas become named
+/// schemas.
+pub fn to_schema(document: &Value, schema: &Value) -> Result<Schema> {
+  Converter{document: document, references: vec![]}.convert(schema, "#")
+}
+
+struct Converter<'a> {
+  document: &'a Value,
+
+  /// The references being converted, to detect recursion.
+  references: Vec<String>,
+}
+
+impl<'a> Converter<'a> {
+  fn convert(&mut self, schema: &Value, pointer: &str) -> Result<Schema> {
+    let object = match *schema {
+      Value::Bool(true) => return Ok(Schema::Json),
+      Value::Bool(false) => return Ok(Schema::OneOf(vec![])),
+      Value::Object(ref object) => object,
+      _ => return Err(invalid(pointer, "is not a schema")),
+    };
+    let keyword = |name: &str| object.get(name);
+
+    let keywords = object.keys()
+      .filter(|key| !ANNOTATIONS.contains(&key.as_str()))
+      .map(|key| key.as_str())
+      .collect::<Vec<_>>();
+    if keywords.is_empty() {
+      return Ok(Schema::Json);
+    }
+
+    if let Some(reference) = keyword("$ref") {
+      expect_only(object, pointer, &["$ref"])?;
+      return self.reference(reference, pointer);
+    }
+
+    for &(name, exclusive) in &[("oneOf", true), ("anyOf", false)] {
+      if let Some(alternatives) = keyword(name) {
+        expect_only(object, pointer, &[name])?;
+        return self.alternatives(alternatives, exclusive,
+                                 &format!("{}/{}", pointer, name));
+      }
+    }
+
+    if let Some(elements) = keyword("allOf") {
+      expect_only(object, pointer, &["allOf"])?;
+      return match elements.as_array() {
+        Some(elements) if elements.len() == 1 =>
+          self.convert(&elements[0], &format!("{}/allOf/0", pointer)),
+        _ => Err(invalid(&format!("{}/allOf", pointer),
+                         "has more than one element")),
+      };
+    }
+
+    if let Some(labels) = keyword("enum") {
+      expect_only(object, pointer, &["enum", "type"])?;
+      return labels.as_array()
+        .and_then(|labels| {
+          labels.iter()
+          .map(|label| label.as_str().map(str::to_string))
+          .collect::<Option<Vec<_>>>()
+        })
+        .map(Schema::Enumeration)
+        .ok_or_else(|| invalid(&format!("{}/enum", pointer),
+                               "has labels that are not strings"));
+    }
+
+    match keyword("type") {
+      Some(&Value::String(ref type_)) => self.typed(object, type_, pointer),
+      Some(&Value::Array(ref types)) => {
+        let non_null = types.iter()
+          .filter(|type_| type_.as_str() != Some("null"))
+          .collect::<Vec<_>>();
+        match non_null.as_slice() {
+          [&Value::String(ref type_)] if types.len() == 2 => {
+            let schema = self.typed(object, type_, pointer)?;
+            Ok(Schema::Optional(Box::new(schema)))
+          },
+          [&Value::String(ref type_)] if types.len() == 1 =>
+            self.typed(object, type_, pointer),
+          _ => Err(invalid(&format!("{}/type", pointer),
+                           "has more than one type besides null")),
+        }
+      },
+      _ => Err(invalid(pointer, &format!("has unsupported keyword {}",
+                                         keywords[0]))),
+    }
+  }
+
+  fn reference(&mut self, reference: &Value, pointer: &str) -> Result<Schema> {
+    let reference = reference.as_str()
+      .ok_or_else(|| invalid(&format!("{}/$ref", pointer), "is not a string"))?;
+    let name = ["#/$defs/", "#/definitions/", "#/components/schemas/"].iter()
+      .filter(|prefix| reference.starts_with(*prefix))
+      .map(|prefix| &reference[prefix.len()..])
+      .find(|name| !name.contains('/'))
+      .ok_or_else(|| invalid(&format!("{}/$ref", pointer),
+                             "does not refer to a local definition"))?;
+    if self.references.iter().any(|other| other == reference) {
+      return Err(invalid(&format!("{}/$ref", pointer), "is recursive"));
+    }
+    let schema = self.document.pointer(&reference[1..])
+      .ok_or_else(|| invalid(&format!("{}/$ref", pointer),
+                             "refers to a missing definition"))?;
+    self.references.push(reference.to_string());
+    let converted = self.convert(schema, reference);
+    self.references.pop();
+    let name = name.replace("~1", "/").replace("~0", "~");
+    Ok(Schema::Named(name, Box::new(converted?)))
+  }
+
+  /// Convert the alternatives of `oneOf` or `anyOf`. A null alternative makes
+  /// the schema optional. Other than that, `anyOf` is only supported for a
+  /// single alternative, as it does not require the alternatives to be
+  /// mutually exclusive.
+  fn alternatives(&mut self, alternatives: &Value, exclusive: bool,
+                  pointer: &str) -> Result<Schema> {
+    let alternatives = alternatives.as_array()
+      .ok_or_else(|| invalid(pointer, "is not an array"))?;
+    let mut nullable = false;
+    let mut schemas = vec![];
+    for (index, alternative) in alternatives.iter().enumerate() {
+      if *alternative == json!({"type": "null"}) {
+        nullable = true;
+      } else {
+        schemas.push(self.convert(alternative,
+                                  &format!("{}/{}", pointer, index))?);
+      }
+    }
+    let schema = match schemas.len() {
+      1 => schemas.pop().unwrap(),
+      _ if exclusive => Schema::OneOf(schemas),
+      _ => return Err(invalid(pointer, "has more than one alternative")),
+    };
+    if nullable {
+      Ok(Schema::Optional(Box::new(schema)))
+    } else {
+      Ok(schema)
+    }
+  }
+
+  fn typed(&mut self, object: &Map<String, Value>, type_: &str, pointer: &str)
+    -> Result<Schema> {
+    match type_ {
+      "null" => {
+        expect_only(object, pointer, &["type"])?;
+        Ok(Schema::Optional(Box::new(Schema::OneOf(vec![]))))
+      },
+      "boolean" => {
+        expect_only(object, pointer, &["type"])?;
+        Ok(Schema::Boolean)
+      },
+      "integer" => {
+        expect_only(object, pointer, &["type", "minimum", "maximum"])?;
+        let bound = |name: &str, default: i32| match object.get(name) {
+          None => Ok(default),
+          Some(bound) => bound.as_i64()
+            .filter(|&bound| bound as i32 as i64 == bound)
+            .map(|bound| bound as i32)
+            .ok_or_else(|| invalid(&format!("{}/{}", pointer, name),
+                                   "is not a 32-bit integer")),
+        };
+        Ok(Schema::SignedInteger(bound("minimum", i32::MIN)?,
+                                 bound("maximum", i32::MAX)?))
+      },
+      "number" => {
+        expect_only(object, pointer, &["type", "minimum", "maximum"])?;
+        let single = (-f32::MAX as f64, f32::MAX as f64);
+        let bounds = (object.get("minimum").and_then(Value::as_f64),
+                      object.get("maximum").and_then(Value::as_f64));
+        match bounds {
+          (None, None) => Ok(Schema::DoublePrecision),
+          (Some(minimum), Some(maximum)) if (minimum, maximum) == single =>
+            Ok(Schema::SinglePrecision),
+          _ => Err(invalid(pointer, "has unsupported bounds")),
+        }
+      },
+      "string" => {
+        expect_only(object, pointer,
+                    &["type", "format", "pattern", "contentEncoding"])?;
+        let keyword = |name: &str| object.get(name).and_then(Value::as_str);
+        match (keyword("format"), keyword("pattern"),
+               keyword("contentEncoding")) {
+          (_, Some(INTEGER_PATTERN), None) =>
+            Ok(Schema::SignedInteger64(i64::MIN, i64::MAX)),
+          (_, Some(_), _) => Err(invalid(pointer, "has an unsupported pattern")),
+          (_, None, Some("base64")) => Ok(Schema::ByteString),
+          (_, None, Some(_)) =>
+            Err(invalid(pointer, "has an unsupported content encoding")),
+          (Some("uuid"), None, None) => Ok(Schema::Uuid),
+          (Some("date"), None, None) => Ok(Schema::Date),
+          (Some("date-time"), None, None) => Ok(Schema::TimestampWithTimeZone),
+          (_, None, None) => Ok(Schema::String),
+        }
+      },
+      "array" => {
+        if let Some(elements) = object.get("prefixItems") {
+          expect_only(object, pointer,
+                      &["type", "prefixItems", "items", "minItems"])?;
+          let elements = elements.as_array().ok_or_else(|| {
+            invalid(&format!("{}/prefixItems", pointer), "is not an array")
+          })?;
+          let closed = object.get("items") == Some(&Value::Bool(false));
+          let complete = object.get("minItems").and_then(Value::as_u64)
+            == Some(elements.len() as u64);
+          if !closed || !complete {
+            return Err(invalid(pointer, concat!("is not closed with items ",
+                                                "false and minItems")));
+          }
+          let elements = elements.iter().enumerate()
+            .map(|(index, element)| {
+              self.convert(element, &format!("{}/prefixItems/{}", pointer, index))
+            })
+            .collect::<Result<_>>()?;
+          Ok(Schema::AllOf(elements))
+        } else {
+          expect_only(object, pointer, &["type", "items"])?;
+          let element = match object.get("items") {
+            Some(items) => self.convert(items, &format!("{}/items", pointer))?,
+            None => Schema::Json,
+          };
+          Ok(Schema::ArrayOf(Box::new(element)))
+        }
+      },
+      "object" => {
+        expect_only(object, pointer, &["type", "properties", "required",
+                                       "additionalProperties"])?;
+        match object.get("additionalProperties") {
+          None | Some(&Value::Bool(_)) => (),
+          Some(_) => return Err(invalid(&format!("{}/additionalProperties",
+                                                 pointer),
+                                        "is not a boolean")),
+        }
+        let required = object.get("required")
+          .and_then(Value::as_array)
+          .map_or(vec![], |required| {
+            required.iter().filter_map(Value::as_str).collect()
+          });
+        let empty = Map::new();
+        let properties = match object.get("properties") {
+          Some(&Value::Object(ref properties)) => properties,
+          None => &empty,
+          Some(_) => return Err(invalid(&format!("{}/properties", pointer),
+                                        "is not an object")),
+        };
+        let mut fields = vec![];
+        for (name, property) in properties {
+          let property_pointer = format!("{}/properties/{}", pointer,
+                                         pointer_token(name));
+          let schema = self.convert(property, &property_pointer)?;
+          let schema = match schema {
+            Schema::Optional(_) => schema,
+            _ if !required.contains(&name.as_str()) =>
+              Schema::Optional(Box::new(schema)),
+            _ => schema,
+          };
+          fields.push((name.clone(), schema));
+        }
+        Ok(Schema::Record(fields))
+      },
+      _ => Err(invalid(&format!("{}/type", pointer), "is not a known type")),
+    }
+  }
+}
+
+/// Check that an object has no keywords besides annotations and `allowed`.
+fn expect_only(object: &Map<String, Value>, pointer: &str, allowed: &[&str])
+  -> Result<()> {
+  match object.keys().find(|key| {
+    !ANNOTATIONS.contains(&key.as_str()) && !allowed.contains(&key.as_str())
+  }) {
+    Some(key) =>
+      Err(invalid(pointer, &format!("has unsupported keyword {}", key))),
+    None => Ok(()),
+  }
+}
+
+fn invalid(pointer: &str, problem: &str) -> Error {
+  Error::Unsupported(format!("The JSON Schema at \"{}\" {}.", pointer, problem))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use constant::Constant;
+
+  #[test]
+  fn test_document() {
+    let mood = Schema::Named("mood".to_string(), Box::new(
+      Schema::Enumeration(vec!["sad".to_string(), "happy".to_string()])));
+    let schema = Schema::AllOf(vec![
+      Schema::SignedInteger(0, 10),
+      Schema::Record(vec![
+        ("a".to_string(), mood.clone()),
+        ("b".to_string(), Schema::Optional(Box::new(mood))),
+      ]),
+      Schema::OneOf(vec![Schema::Boolean, Schema::String]),
+    ]);
+    assert_eq!(document(&schema), json!({
+      "$schema": DIALECT,
+      "type": "array",
+      "prefixItems": [
+        {"type": "integer", "minimum": 0, "maximum": 10},
+        {
+          "type": "object",
+          "properties": {
+            "a": {"$ref": "#/$defs/mood"},
+            "b": {"anyOf": [{"$ref": "#/$defs/mood"}, {"type": "null"}]},
+          },
+          "required": ["a"],
+          "additionalProperties": false,
+        },
+        {"oneOf": [{"type": "boolean"}, {"type": "string"}]},
+      ],
+      "minItems": 3,
+      "items": false,
+      "$defs": {
+        "mood": {"type": "string", "enum": ["sad", "happy"]},
+      },
+    }));
+  }
+
+  #[test]
+  fn test_document_boolean() {
+    assert_eq!(document(&Schema::Json), json!({
+      "$schema": DIALECT,
+      "allOf": [true],
+    }));
+  }
+
+  #[test]
+  fn test_to_schema() {
+    let document = json!({
+      "type": "object",
+      "properties": {
+        "id": {"type": "integer", "minimum": 0},
+        "name": {"type": ["string", "null"]},
+        "email": {"type": "string", "format": "email"},
+        "mood": {"$ref": "#/$defs/mood"},
+        "tags": {"type": "array", "items": {"type": "string"}},
+        "score": {"oneOf": [{"type": "number"}, {"type": "boolean"}]},
+      },
+      "required": ["id", "name", "mood", "tags", "score"],
+      "$defs": {
+        "mood": {"enum": ["sad", "happy"]},
+      },
+    });
+    let schema = to_schema(&document, &document).map_err(|e| e.to_string());
+    assert_eq!(schema, Ok(Schema::Record(vec![
+      ("id".to_string(), Schema::SignedInteger(0, i32::MAX)),
+      ("name".to_string(), Schema::Optional(Box::new(Schema::String))),
+      ("email".to_string(), Schema::Optional(Box::new(Schema::String))),
+      ("mood".to_string(), Schema::Named("mood".to_string(), Box::new(
+        Schema::Enumeration(vec!["sad".to_string(), "happy".to_string()])))),
+      ("tags".to_string(), Schema::ArrayOf(Box::new(Schema::String))),
+      ("score".to_string(), Schema::OneOf(vec![Schema::DoublePrecision,
+                                               Schema::Boolean])),
+    ])));
+  }
+
+  #[test]
+  fn test_to_schema_round_trip() {
+    let schema = Schema::AllOf(vec![
+      Schema::Record(vec![
+        ("a".to_string(), Schema::SignedInteger(-1, 1)),
+        ("b".to_string(), Schema::Optional(Box::new(Schema::SinglePrecision))),
+      ]),
+      Schema::SignedInteger64(i64::MIN, i64::MAX),
+      Schema::ByteString,
+      Schema::Uuid,
+      Schema::Date,
+      Schema::Json,
+    ]);
+    let document = document(&schema);
+    assert_eq!(to_schema(&document, &document).map_err(|e| e.to_string()),
+               Ok(schema));
+  }
+
+  #[test]
+  fn test_to_schema_errors() {
+    let cases = vec![
+      (json!({"type": "string", "minLength": 1}),
+       "The JSON Schema at \"#\" has unsupported keyword minLength."),
+      (json!({"type": "integer", "maximum": 4294967295u32}),
+       "The JSON Schema at \"#/maximum\" is not a 32-bit integer."),
+      (json!({"$defs": {"a": {"type": "array", "items": {"$ref": "#/$defs/a"}}},
+              "$ref": "#/$defs/a"}),
+       "The JSON Schema at \"#/$defs/a/items/$ref\" is recursive."),
+      (json!({"anyOf": [{"type": "string"}, {"type": "boolean"}]}),
+       "The JSON Schema at \"#/anyOf\" has more than one alternative."),
+    ];
+    for (document, message) in cases {
+      let schema = to_schema(&document, &document).map_err(|e| e.to_string());
+      assert_eq!(schema, Err(message.to_string()));
+    }
+  }
+
+  #[test]
+  fn test_bundled_source() {
+    let source = Constant::Boolean(true);
+    let bundle = bundle(vec![("c", &source as &HasSchema)]).unwrap();
+    let bundled = Source::bundled(&bundle, "c").map_err(|e| e.to_string());
+    assert_eq!(bundled.map(|source| source.output),
+               Ok(Rc::new(Schema::Boolean)));
+  }
+
+  #[test]
+  fn test_bundle() {
+    let source = Constant::Boolean(true);
+    let bundle = bundle(vec![("c", &source as &HasSchema)]);
+    assert_eq!(bundle.map_err(|e| e.to_string()), Ok(json!({
+      "$schema": DIALECT,
+      "$defs": {
+        "c.input": {"type": "array", "prefixItems": [], "minItems": 0,
+                    "items": false},
+        "c.output": {"type": "boolean"},
+      },
+    })));
+  }
+}
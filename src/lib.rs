@@ -9,29 +9,70 @@
 //! source will invoke the SQL query.
 
 extern crate postgres;
+extern crate roxmltree;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate toml;
 
 pub mod constant;
 pub mod edit_warning;
+pub mod error;
+pub mod escape;
+pub mod interchange;
+pub mod json_schema;
+pub mod manifest;
+pub mod openapi;
 pub mod postgresql;
 pub mod serialization;
 pub mod source;
 pub mod web_service;
+pub mod wsdl;
 
 /// A schema describes the structure of data. Schemas are generated from
 /// sources, and are used to generate web services, serialization functions,
 /// user interfaces, and so on.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Schema {
   /// The data described is a heterogeneous sequence of values.
   AllOf(Vec<Schema>),
 
+  /// The data described is a heterogeneous collection of values, each
+  /// identified by a unique name.
+  Record(Vec<(String, Schema)>),
+
+  /// The data described is a homogeneous sequence of zero or more values.
+  ArrayOf(Box<Schema>),
+
+  /// The data described is either absent (null) or conforms to a schema.
+  Optional(Box<Schema>),
+
   /// The data described conforms to one of many schemas.
   OneOf(Vec<Schema>),
 
+  /// The data described conforms to a schema that has been given a name by
+  /// its source, such as a user-defined type.
+  Named(String, Box<Schema>),
+
+  /// The data described is one of a fixed set of labels.
+  Enumeration(Vec<String>),
+
+  /// The data described is either true or false.
+  Boolean,
+
   /// The data described is an integer with a lower and upper bound (both
   /// inclusive).
   SignedInteger(i32, i32),
 
+  /// The data described is a 64-bit integer with a lower and upper bound
+  /// (both inclusive).
+  SignedInteger64(i64, i64),
+
+  /// The data described is an arbitrary-precision decimal number.
+  Decimal,
+
   /// The data described is a single-precision floating-point number.
   SinglePrecision,
 
@@ -43,4 +84,22 @@ pub enum Schema {
 
   /// The data described is arbitrary Unicode text.
   String,
+
+  /// The data described is a universally unique identifier.
+  Uuid,
+
+  /// The data described is a calendar date.
+  Date,
+
+  /// The data described is a date and time of day without a time zone.
+  Timestamp,
+
+  /// The data described is a date and time of day with a time zone.
+  TimestampWithTimeZone,
+
+  /// The data described is a time span.
+  Interval,
+
+  /// The data described is an arbitrary JSON value.
+  Json,
 }
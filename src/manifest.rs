@@ -0,0 +1,840 @@
+//! This module implements project manifests, which list the sources of a
+//! project and the assets to generate from them. Manifests are written in
+//! TOML, or in JSON if their file name ends in `.json`:
+//!
+//! ```toml
+//! database = "postgres://postgres@localhost/app"
+//! cache = "typefunnel.cache.json"
+//!
+//! [[sources]]
+//! kind = "sql"
+//! path = "queries.sql"
+//!
+//! [[sources]]
+//! kind = "query"
+//! name = "now"
+//! query = "SELECT now()"
+//! output = "scalar"
+//!
+//! [[sources]]
+//! kind = "constant"
+//! name = "settings"
+//! value = { answer = 42 }
+//!
+//! [[sources]]
+//! kind = "wsdl"
+//! path = "legacy.wsdl"
+//!
+//! [[sources]]
+//! kind = "openapi"
+//! path = "partner.json"
+//!
+//! [[sources]]
+//! kind = "web_service"
+//! name = "remoteSettings"
+//! call = "settings"
+//!
+//! [[targets]]
+//! language = "ecmascript"
+//! path = "generated/client.js"
+//! ```
+//!
+//! Paths are relative to the directory that contains the manifest. If the
+//! manifest gives a schema cache, the descriptions of SQL queries are stored
+//! in it when generating with a database, and read from it when generating
+//! without one.
+
+use constant::Constant;
+use edit_warning;
+use error::{Error, Result};
+use interchange;
+use openapi;
+use postgres::{Connection, TlsMode};
+use postgresql::{self, InputShape, OutputShape, Query};
+use postgresql::cache::Cache;
+use serde_json;
+use source::call::{Asynchrony, Call, ECMAScript, ECMAScriptModule, Promised,
+                   Rust, RustModule, TypeScriptOutput};
+use source::HasSchema;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use toml;
+use web_service::{self, WebService};
+use wsdl;
+
+mod error {
+  pub static DATABASE: &'static str =
+    concat!("The manifest lists SQL sources, but gives neither a database ",
+            "nor a schema cache.");
+
+  pub static VERIFY: &'static str =
+    "Verifying requires a manifest that gives a database and a schema cache.";
+}
+
+/// A project manifest.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+  /// The URL of the database against which SQL sources are checked.
+  pub database: Option<String>,
+
+  /// The path to the schema cache of the SQL sources.
+  pub cache: Option<PathBuf>,
+
+  /// The sources of the project.
+  #[serde(default)]
+  pub sources: Vec<Source>,
+
+  /// The assets to generate.
+  #[serde(default)]
+  pub targets: Vec<Target>,
+}
+
+/// A source listed in a manifest.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Source {
+  /// A SQL file with annotated queries, as read by `postgresql::file`. Each
+  /// query is a call with the name given by its annotation.
+  Sql {
+    /// The path to the SQL file.
+    path: PathBuf,
+  },
+
+  /// A single SQL query.
+  Query {
+    /// The name of the call.
+    name: String,
+
+    /// The SQL query.
+    query: String,
+
+    /// The expected shape of the query parameters.
+    #[serde(default)]
+    input: Input,
+
+    /// The expected shape of the query result.
+    output: Output,
+  },
+
+  /// Constant data.
+  Constant {
+    /// The name of the call.
+    name: String,
+
+    /// The data. Integers that fit in 32 bits become signed integers, other
+    /// numbers become double-precision floating-point numbers, and arrays
+    /// become homogeneous arrays.
+    value: serde_json::Value,
+  },
+
+  /// A WSDL file, as read by `wsdl`. Each operation is a call with the name
+  /// of the operation.
+  Wsdl {
+    /// The path to the WSDL file.
+    path: PathBuf,
+  },
+
+  /// An OpenAPI document in JSON, as read by `openapi`. Each operation is a
+  /// call with the name of its `operationId`.
+  #[serde(rename = "openapi")]
+  OpenApi {
+    /// The path to the OpenAPI document.
+    path: PathBuf,
+  },
+
+  /// A web service that serves another call.
+  WebService {
+    /// The name of the call, which is also the path of the web service.
+    name: String,
+
+    /// The name of the served call.
+    call: String,
+  },
+}
+
+/// The expected shape of the parameters of a query in a manifest.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum Input {
+  Row,
+  Scalar,
+}
+
+impl Default for Input {
+  fn default() -> Input {
+    Input::Row
+  }
+}
+
+/// The expected shape of the result of a query in a manifest. The names match
+/// the annotations of SQL files.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum Output {
+  One,
+  Many,
+  Scalar,
+}
+
+/// An asset listed in a manifest.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+  /// The kind of asset.
+  pub language: Language,
+
+  /// The path to which the asset is written.
+  pub path: PathBuf,
+
+  /// The names of the calls to include. All calls are included if absent.
+  pub calls: Option<Vec<String>>,
+
+  /// For servers, the ECMAScript expression passed as the first argument to
+  /// the served calls, such as a database client.
+  pub context: Option<String>,
+
+  /// For ECMAScript, TypeScript and PureScript, whether asynchronous calls
+  /// return Promises instead of taking callbacks. For servers, whether the
+  /// served calls are awaited as Promises in async handlers.
+  #[serde(default)]
+  pub promises: bool,
+
+  /// For PureScript, the name of the module. It defaults to the file name of
+  /// the path without its extension.
+  pub module: Option<String>,
+
+  /// For OpenAPI documents, the title of the API. It defaults to typefunnel.
+  pub title: Option<String>,
+
+  /// For OpenAPI documents, the version of the API. It defaults to 0.0.0.
+  pub version: Option<String>,
+}
+
+/// The kind of asset to generate.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+  /// An ECMAScript module that exports the calls.
+  #[serde(rename = "ecmascript")]
+  ECMAScript,
+
+  /// A PureScript module with the calls, and next to it the foreign module
+  /// that implements them, whose path has the extension `.js`.
+  #[serde(rename = "purescript")]
+  PureScript,
+
+  /// TypeScript declarations for the ECMAScript module if the path ends in
+  /// `.d.ts`, and otherwise a TypeScript module with the calls.
+  #[serde(rename = "typescript")]
+  TypeScript,
+
+  /// A Rust module with the calls, as generated by `RustModule::rust`.
+  Rust,
+
+  /// An ECMAScript module that exports an Express application serving the
+  /// web services among the calls.
+  Server,
+
+  /// A JSON object with the input and output schemas of each call, in the
+  /// encoding of the `interchange` module.
+  Interchange,
+
+  /// A JSON Schema document that bundles the input and output schemas of the
+  /// calls.
+  JsonSchema,
+
+  /// An OpenAPI document that describes the web services among the calls, as
+  /// served by the `server` target.
+  #[serde(rename = "openapi")]
+  OpenApi,
+}
+
+impl Manifest {
+  /// Read a manifest from a file.
+  pub fn read<P>(path: P) -> Result<Manifest> where P: AsRef<Path> {
+    let path = path.as_ref();
+    let mut text = String::new();
+    File::open(path)?.read_to_string(&mut text)?;
+    if path.extension().map_or(false, |extension| extension == "json") {
+      serde_json::from_str(&text)
+      .map_err(|error| Error::Syntax(format!("{}: {}", path.display(), error)))
+    } else {
+      toml::from_str(&text)
+      .map_err(|error| Error::Syntax(format!("{}: {}", path.display(), error)))
+    }
+  }
+
+  /// Generate the targets of the manifest. Relative paths are resolved
+  /// against `directory`.
+  pub fn generate<P>(&self, directory: P) -> Result<()> where P: AsRef<Path> {
+    let directory = directory.as_ref();
+    let connection = self.connect()?;
+    let cache = self.read_cache(directory)?;
+
+    for (path, output) in self.outputs(directory, connection.as_ref(),
+                                       cache.as_ref())? {
+      let path = directory.join(path);
+      if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+      }
+      File::create(path)?.write_all(&output)?;
+    }
+
+    if let (Some(_), Some(cache), Some(path)) =
+        (connection.as_ref(), cache.as_ref(), self.cache.as_ref()) {
+      for (_, query) in self.queries(directory, connection.as_ref(),
+                                     Some(cache))? {
+        query.schema()?;
+      }
+      cache.prune();
+      cache.write(directory.join(path))?;
+    }
+
+    Ok(())
+  }
+
+  /// Generate the targets of the manifest without writing them, and return
+  /// the paths of those whose files are missing or differ from what would be
+  /// generated. The paths are as given in the manifest.
+  pub fn check<P>(&self, directory: P) -> Result<Vec<PathBuf>>
+    where P: AsRef<Path> {
+    let directory = directory.as_ref();
+    let connection = self.connect()?;
+    let cache = self.read_cache(directory)?;
+
+    let mut stale = vec![];
+    for (path, output) in self.outputs(directory, connection.as_ref(),
+                                       cache.as_ref())? {
+      let mut existing = vec![];
+      let fresh = match File::open(directory.join(&path)) {
+        Ok(mut file) => {
+          file.read_to_end(&mut existing)?;
+          existing == output
+        },
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => false,
+        Err(error) => return Err(error.into()),
+      };
+      if !fresh {
+        stale.push(path);
+      }
+    }
+    Ok(stale)
+  }
+
+  /// Generate the contents of the targets of the manifest, paired with their
+  /// paths as given in the manifest. The same manifest and sources always
+  /// give the same contents.
+  fn outputs(&self, directory: &Path, connection: Option<&Connection>,
+             cache: Option<&Cache>) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let queries = self.queries(directory, connection, cache)?;
+
+    let mut constants = vec![];
+    let mut operations = vec![];
+    let mut endpoints = vec![];
+    let mut services = vec![];
+    for source in &self.sources {
+      match *source {
+        Source::Constant{ref name, ref value} =>
+          constants.push((name.clone(), constant(value))),
+        Source::Wsdl{ref path} =>
+          for operation in wsdl::read(directory.join(path))? {
+            operations.push((operation.name.clone(), operation));
+          },
+        Source::OpenApi{ref path} =>
+          for operation in openapi::read(directory.join(path))? {
+            endpoints.push((operation.name.clone(), operation));
+          },
+        Source::WebService{ref name, ref call} =>
+          services.push((name.clone(), call.clone())),
+        _ => (),
+      }
+    }
+
+    let mut calls: Vec<(String, &Call)> = vec![];
+    for &(ref name, ref query) in &queries {
+      calls.push((name.clone(), query));
+    }
+    for &(ref name, ref constant) in &constants {
+      calls.push((name.clone(), constant));
+    }
+    for &(ref name, ref operation) in &operations {
+      calls.push((name.clone(), operation));
+    }
+    for &(ref name, ref endpoint) in &endpoints {
+      calls.push((name.clone(), endpoint));
+    }
+    let services = services.into_iter()
+      .map(|(name, call)| Ok(WebService{name: name, source: find(&calls, &call)?}))
+      .collect::<Result<Vec<_>>>()?;
+    for service in &services {
+      calls.push((service.name.clone(), service));
+    }
+    for (index, &(ref name, _)) in calls.iter().enumerate() {
+      if calls[..index].iter().any(|&(ref other, _)| other == name) {
+        return Err(Error::Manifest(
+          format!("The call {} is defined more than once.", name)));
+      }
+    }
+
+    let mut outputs = vec![];
+    for target in &self.targets {
+      let mut selected = vec![];
+      match target.calls {
+        Some(ref names) =>
+          for name in names {
+            selected.push((name.clone(), find(&calls, name)?));
+          },
+        None => selected.extend(calls.iter().cloned()),
+      }
+
+      let mut output = vec![];
+      match target.language {
+        Language::ECMAScript => {
+          write!(output, "{}\n", edit_warning::ECMASCRIPT)?;
+          module(&selected, target).ecmascript(&mut output)?;
+        },
+        Language::TypeScript => {
+          let declarations = target.path.to_str()
+            .map_or(false, |path| path.ends_with(".d.ts"));
+          write!(output, "{}\n", edit_warning::TYPESCRIPT)?;
+          module(&selected, target).typescript(&mut output, if declarations {
+            TypeScriptOutput::Declarations
+          } else {
+            TypeScriptOutput::Module
+          })?;
+        },
+        Language::PureScript => {
+          let name = match (target.module.as_ref(), target.path.file_stem()) {
+            (Some(name), _) => name.clone(),
+            (None, Some(stem)) => stem.to_string_lossy().into_owned(),
+            (None, None) => String::new(),
+          };
+          write!(output, "{}\n", edit_warning::PURESCRIPT)?;
+          module(&selected, target).purescript(&mut output, &name)?;
+          let mut foreign = vec![];
+          write!(foreign, "{}\n", edit_warning::ECMASCRIPT)?;
+          module(&selected, target).purescript_foreign(&mut foreign)?;
+          outputs.push((target.path.with_extension("js"), foreign));
+        },
+        Language::Rust => {
+          write!(output, "{}\n", edit_warning::RUST)?;
+          RustModule{
+            calls: selected.iter()
+              .map(|&(ref name, call)| {
+                (name.clone(), (call as &HasSchema, call as &Rust))
+              })
+              .collect(),
+          }.rust(&mut output)?;
+        },
+        Language::Server => {
+          let context = target.context.as_ref().map_or("null", |c| c.as_str());
+          write!(output, "{}\n", edit_warning::ECMASCRIPT)?;
+          web_service::ecmascript::serve(&mut output, |write| {
+            for service in &services {
+              if !selected.iter().any(|&(ref name, _)| *name == service.name) {
+                continue;
+              }
+              if target.promises {
+                let promised = Promised(service.source);
+                let service = WebService{name: service.name.clone(),
+                                         source: &promised};
+                web_service::ecmascript::handle(write, &service, context)?;
+              } else {
+                web_service::ecmascript::handle(write, service, context)?;
+              }
+            }
+            Ok(())
+          })?;
+          write!(output, "module.exports = app;\n")?;
+        },
+        Language::JsonSchema =>
+          module(&selected, target).json_schema(&mut output)?,
+        Language::OpenApi => {
+          let services = services.iter()
+            .filter(|service| {
+              selected.iter().any(|&(ref name, _)| *name == service.name)
+            });
+          let document = web_service::openapi::document(
+            target.title.as_ref().map_or("typefunnel", |t| t.as_str()),
+            target.version.as_ref().map_or("0.0.0", |v| v.as_str()),
+            services,
+          )?;
+          serde_json::to_writer_pretty(&mut output, &document)
+            .map_err(|error| Error::Io(error.into()))?;
+          write!(output, "\n")?;
+        },
+        Language::Interchange => {
+          let mut schemas = BTreeMap::new();
+          for &(ref name, call) in &selected {
+            let (input, output) = call.schema()?;
+            schemas.insert(name.clone(), json!({
+              "input": interchange::encode(&input),
+              "output": interchange::encode(&output),
+            }));
+          }
+          serde_json::to_writer_pretty(&mut output, &schemas)
+            .map_err(|error| Error::Io(error.into()))?;
+          write!(output, "\n")?;
+        },
+      }
+
+      outputs.push((target.path.clone(), output));
+    }
+    Ok(outputs)
+  }
+
+  /// Describe the SQL sources of the manifest again using the database, and
+  /// return the queries whose descriptions are missing from the schema cache
+  /// or differ from the cached ones.
+  pub fn verify<P>(&self, directory: P) -> Result<Vec<String>>
+    where P: AsRef<Path> {
+    let directory = directory.as_ref();
+    let (connection, cache) = match (self.connect()?, self.read_cache(directory)?) {
+      (Some(connection), Some(cache)) => (connection, cache),
+      _ => return Err(Error::Manifest(error::VERIFY.to_string())),
+    };
+    let mut stale = cache.verify(&connection)?;
+    for (_, query) in self.queries(directory, None, Some(&cache))? {
+      if cache.get(&query.query).is_none() {
+        stale.push(query.query);
+      }
+    }
+    Ok(stale)
+  }
+
+  /// Return the SQL sources of the manifest, described using the given
+  /// connection or cache.
+  fn queries<'a>(&self, directory: &Path, connection: Option<&'a Connection>,
+                 cache: Option<&'a Cache>) -> Result<Vec<(String, Query<'a>)>> {
+    let mut queries = vec![];
+    for source in &self.sources {
+      match *source {
+        Source::Sql{ref path} =>
+          queries.extend(postgresql::file::read(connection, cache,
+                                                directory.join(path))?),
+        Source::Query{ref name, ref query, input, output} =>
+          queries.push((name.clone(), Query{
+            connection: connection,
+            cache: cache,
+            query: query.clone(),
+            input_shape: match input {
+              Input::Row => InputShape::Row,
+              Input::Scalar => InputShape::Scalar,
+            },
+            output_shape: match output {
+              Output::One => OutputShape::Row,
+              Output::Many => OutputShape::Table,
+              Output::Scalar => OutputShape::Scalar,
+            },
+          })),
+        _ => (),
+      }
+    }
+    Ok(queries)
+  }
+
+  /// Read the schema cache, if the manifest gives one. A missing cache file
+  /// is an empty cache.
+  fn read_cache(&self, directory: &Path) -> Result<Option<Cache>> {
+    match self.cache {
+      Some(ref path) if directory.join(path).exists() =>
+        Ok(Some(Cache::read(directory.join(path))?)),
+      Some(_) => Ok(Some(Cache::new())),
+      None => Ok(None),
+    }
+  }
+
+  /// Connect to the database if there are SQL sources and the manifest gives
+  /// a database.
+  fn connect(&self) -> Result<Option<Connection>> {
+    let sql = self.sources.iter().any(|source| match *source {
+      Source::Sql{..} | Source::Query{..} => true,
+      _ => false,
+    });
+    match self.database {
+      Some(ref url) if sql => Ok(Some(Connection::connect(url.as_str(),
+                                                          TlsMode::None)?)),
+      None if sql && self.cache.is_none() =>
+        Err(Error::Manifest(error::DATABASE.to_string())),
+      _ => Ok(None),
+    }
+  }
+}
+
+fn find<'a>(calls: &[(String, &'a Call)], name: &str) -> Result<&'a Call> {
+  calls.iter()
+  .find(|&&(ref other, _)| other == name)
+  .map(|&(_, call)| call)
+  .ok_or_else(|| Error::Manifest(format!("There is no call named {}.", name)))
+}
+
+fn module<'a>(calls: &[(String, &'a Call)], target: &Target)
+  -> ECMAScriptModule<'a> {
+  ECMAScriptModule{
+    calls: calls.iter()
+      .map(|&(ref name, call)| {
+        (name.clone(), (call as &HasSchema, call as &ECMAScript))
+      })
+      .collect::<BTreeMap<_, _>>(),
+    asynchrony: if target.promises {
+      Asynchrony::Promises
+    } else {
+      Asynchrony::Callbacks
+    },
+  }
+}
+
+/// Convert a JSON value to constant data.
+fn constant(value: &serde_json::Value) -> Constant {
+  match *value {
+    serde_json::Value::Null => Constant::Null,
+    serde_json::Value::Bool(value) => Constant::Boolean(value),
+    serde_json::Value::Number(ref number) =>
+      match number.as_i64() {
+        Some(value) if value as i32 as i64 == value =>
+          Constant::SignedInteger(value as i32),
+        _ => Constant::DoublePrecision(number.as_f64().unwrap_or(0.0)),
+      },
+    serde_json::Value::String(ref value) => Constant::String(value.clone()),
+    serde_json::Value::Array(ref elements) =>
+      Constant::ArrayOf(elements.iter().map(constant).collect()),
+    serde_json::Value::Object(ref fields) =>
+      Constant::Record(
+        fields.iter()
+        .map(|(name, value)| (name.clone(), constant(value)))
+        .collect()
+      ),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::env;
+  use std::process;
+
+  /// Create an empty temporary directory for the test of the given name. The
+  /// process ID is part of the name so that concurrent runs do not collide.
+  fn temp_directory(test: &str) -> PathBuf {
+    let directory = env::temp_dir()
+      .join(format!("typefunnel_{}_{}", test, process::id()));
+    if directory.exists() {
+      fs::remove_dir_all(&directory).unwrap();
+    }
+    fs::create_dir_all(&directory).unwrap();
+    directory
+  }
+
+  #[test]
+  fn test_generate() {
+    let directory = temp_directory("test_generate");
+    let manifest: Manifest = toml::from_str(r#"
+      [[sources]]
+      kind = "constant"
+      name = "settings"
+      value = { answer = 42, names = ["a", "b"] }
+
+      [[sources]]
+      kind = "web_service"
+      name = "remoteSettings"
+      call = "settings"
+
+      [[targets]]
+      language = "ecmascript"
+      path = "client.js"
+      calls = ["remoteSettings"]
+
+      [[targets]]
+      language = "server"
+      path = "server.js"
+
+      [[targets]]
+      language = "interchange"
+      path = "schemas.json"
+      calls = ["settings"]
+
+      [[targets]]
+      language = "typescript"
+      path = "client.d.ts"
+      calls = ["remoteSettings"]
+
+      [[targets]]
+      language = "openapi"
+      path = "openapi.json"
+      title = "Settings"
+
+      [[targets]]
+      language = "rust"
+      path = "client.rs"
+    "#).unwrap();
+    manifest.generate(&directory).map_err(|e| e.to_string()).unwrap();
+
+    let mut client = String::new();
+    File::open(directory.join("client.js")).unwrap()
+    .read_to_string(&mut client).unwrap();
+    assert!(client.starts_with(edit_warning::ECMASCRIPT));
+    assert!(client.contains("exports.remoteSettings = "));
+    assert!(!client.contains("exports.settings = "));
+
+    let mut server = String::new();
+    File::open(directory.join("server.js")).unwrap()
+    .read_to_string(&mut server).unwrap();
+    assert!(server.contains("app.post('/remoteSettings'"));
+
+    let mut schemas = String::new();
+    File::open(directory.join("schemas.json")).unwrap()
+    .read_to_string(&mut schemas).unwrap();
+    let schemas: serde_json::Value = serde_json::from_str(&schemas).unwrap();
+    assert_eq!(schemas["settings"]["output"]["type"], "record");
+
+    let mut declarations = String::new();
+    File::open(directory.join("client.d.ts")).unwrap()
+    .read_to_string(&mut declarations).unwrap();
+    assert!(declarations.contains(concat!(
+      "export declare const remoteSettings: (context: any, input: [], ",
+      "onSuccess: (output: { answer: number; names: Array<string>; }) => void, ",
+      "onError: (error: any) => void) => void;")));
+
+    let mut openapi = String::new();
+    File::open(directory.join("openapi.json")).unwrap()
+    .read_to_string(&mut openapi).unwrap();
+    let openapi: serde_json::Value = serde_json::from_str(&openapi).unwrap();
+    assert_eq!(openapi["info"]["title"], "Settings");
+    assert!(openapi["paths"]["/remoteSettings"]["post"].is_object());
+    assert!(openapi["paths"]["/settings"].is_null());
+
+    let mut rust = String::new();
+    File::open(directory.join("client.rs")).unwrap()
+    .read_to_string(&mut rust).unwrap();
+    assert!(rust.starts_with(edit_warning::RUST));
+    assert!(rust.contains(concat!(
+      "pub fn remote_settings(context: &str, input: ()) -> ",
+      "Result<RemoteSettingsOutput, Error> {\n")));
+    assert!(rust.contains(concat!(
+      "pub fn settings(context: (), input: ()) -> ",
+      "Result<SettingsOutput, Error> {\n")));
+
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn test_server_promises() {
+    let directory = temp_directory("test_server_promises");
+    File::create(directory.join("openapi.json")).unwrap()
+    .write_all(br#"{
+      "openapi": "3.0.3",
+      "info": {"title": "Ping", "version": "1.0"},
+      "servers": [{"url": "https://example.com/api"}],
+      "paths": {
+        "/ping": {
+          "post": {
+            "operationId": "ping",
+            "responses": {"200": {"description": "Pong."}}
+          }
+        }
+      }
+    }"#).unwrap();
+    let manifest: Manifest = toml::from_str(r#"
+      [[sources]]
+      kind = "openapi"
+      path = "openapi.json"
+
+      [[sources]]
+      kind = "web_service"
+      name = "relay"
+      call = "ping"
+
+      [[targets]]
+      language = "server"
+      path = "server.js"
+
+      [[targets]]
+      language = "server"
+      path = "promised_server.js"
+      promises = true
+    "#).unwrap();
+    manifest.generate(&directory).map_err(|e| e.to_string()).unwrap();
+
+    let mut server = String::new();
+    File::open(directory.join("server.js")).unwrap()
+    .read_to_string(&mut server).unwrap();
+    assert!(server.contains("app.post('/relay', function(req, res) {\n"));
+    assert!(server.contains("call(null, input, function(output) {\n"));
+
+    let mut promised_server = String::new();
+    File::open(directory.join("promised_server.js")).unwrap()
+    .read_to_string(&mut promised_server).unwrap();
+    assert!(promised_server.contains(
+      "app.post('/relay', async function(req, res) {\n"));
+    assert!(promised_server.contains("output = await call(null, input);\n"));
+
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn test_check() {
+    let directory = temp_directory("test_check");
+    let manifest: Manifest = toml::from_str(r#"
+      [[sources]]
+      kind = "constant"
+      name = "b"
+      value = 1
+
+      [[sources]]
+      kind = "constant"
+      name = "a"
+      value = 2
+
+      [[targets]]
+      language = "ecmascript"
+      path = "client.js"
+
+      [[targets]]
+      language = "purescript"
+      path = "Client.purs"
+    "#).unwrap();
+    manifest.generate(&directory).map_err(|e| e.to_string()).unwrap();
+    assert_eq!(manifest.check(&directory).map_err(|e| e.to_string()),
+               Ok(vec![]));
+
+    let mut client = String::new();
+    File::open(directory.join("client.js")).unwrap()
+    .read_to_string(&mut client).unwrap();
+    assert!(client.find("exports.a = ") < client.find("exports.b = "));
+
+    File::create(directory.join("client.js")).unwrap()
+    .write_all(b"exports.a = 2;\n").unwrap();
+    fs::remove_file(directory.join("Client.purs")).unwrap();
+    assert_eq!(manifest.check(&directory).map_err(|e| e.to_string()),
+               Ok(vec![PathBuf::from("client.js"),
+                       PathBuf::from("Client.purs")]));
+
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn test_generate_errors() {
+    let directory = temp_directory("test_generate_errors");
+    let cases = vec![
+      ("[[sources]]\nkind = \"query\"\nname = \"a\"\nquery = \"SELECT 1\"\n\
+        output = \"one\"",
+       error::DATABASE.to_string()),
+      ("[[sources]]\nkind = \"web_service\"\nname = \"a\"\ncall = \"b\"",
+       "There is no call named b.".to_string()),
+      ("[[sources]]\nkind = \"constant\"\nname = \"a\"\nvalue = 1\n\
+        [[sources]]\nkind = \"web_service\"\nname = \"a\"\ncall = \"a\"",
+       "The call a is defined more than once.".to_string()),
+    ];
+    for (text, message) in cases {
+      let manifest: Manifest = toml::from_str(text).unwrap();
+      let result = manifest.generate(&directory);
+      assert_eq!(result.map_err(|e| e.to_string()), Err(message));
+    }
+    fs::remove_dir_all(&directory).unwrap();
+  }
+}
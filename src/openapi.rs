@@ -0,0 +1,591 @@
+//! This module implements the OpenAPI source. It reads OpenAPI 3 documents in
+//! JSON, and yields a call for each operation.
+//!
+//! The input of a call is a record with a field for each parameter, and a
+//! field named `body` for the request body. The output of a call is the body
+//! of the 200 response, or an empty tuple if that response has no body. Only
+//! JSON bodies, and path, query and header parameters, are supported.
+//!
+//! Schemas are converted as by `json_schema::to_schema`, after rewriting the
+//! OpenAPI 3.0 `nullable` keyword and the `format` of numbers. Integers with
+//! the `int64` format become double-precision floating-point numbers, since
+//! that is how ECMAScript reads them from JSON.
+
+use Schema;
+use error::{Error, Result};
+use escape;
+use json_schema;
+use serde_json::{self, Map, Value};
+use serialization::ecmascript::{deserialize, serialize};
+use source::HasSchema;
+use source::call::{ECMAScript, ECMAScriptConvention, Rust};
+use std::f32;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::rc::Rc;
+
+mod error {
+  pub static RUST: &'static str =
+    "Rust calls cannot yet be generated for OpenAPI operations.";
+}
+
+/// The HTTP methods that may have operations, in the order they are read.
+const METHODS: &'static [&'static str] = &[
+  "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// The keywords of OpenAPI schemas that do not affect the schema.
+const ANNOTATIONS: &'static [&'static str] = &[
+  "example", "xml", "externalDocs", "discriminator",
+];
+
+/// An operation of an OpenAPI document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Operation {
+  /// The name of the operation, which is its `operationId`.
+  pub name: String,
+
+  /// The HTTP method, in upper case.
+  pub method: String,
+
+  /// The path template, such as `/users/{id}`.
+  pub path: String,
+
+  /// The URL of the first server of the document, if any.
+  pub server: Option<String>,
+
+  /// The parameters, in the order of the fields of the input record.
+  pub parameters: Vec<Parameter>,
+
+  /// Whether the operation has a request body.
+  pub body: bool,
+
+  /// Whether the 200 response has a body.
+  pub response: bool,
+
+  /// The input schema.
+  pub input: Rc<Schema>,
+
+  /// The output schema.
+  pub output: Rc<Schema>,
+}
+
+/// A parameter of an operation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameter {
+  /// The name of the parameter, which is also the name of its field.
+  pub name: String,
+
+  /// Where the parameter is sent.
+  pub location: Location,
+}
+
+/// Where a parameter is sent.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[allow(missing_docs)]
+pub enum Location {
+  Path,
+  Query,
+  Header,
+}
+
+/// Read the operations from an OpenAPI file.
+pub fn read<P>(path: P) -> Result<Vec<Operation>> where P: AsRef<Path> {
+  let path = path.as_ref();
+  let mut text = String::new();
+  File::open(path)?.read_to_string(&mut text)?;
+  let document = serde_json::from_str(&text)
+    .map_err(|error| Error::Syntax(format!("{}: {}", path.display(), error)))?;
+  operations(&document)
+}
+
+/// Return the operations of an OpenAPI document.
+pub fn operations(document: &Value) -> Result<Vec<Operation>> {
+  match document.get("openapi").and_then(Value::as_str) {
+    Some(version) if version.starts_with("3.") => (),
+    _ => return Err(Error::Unsupported(
+      "Only OpenAPI 3 documents are supported.".to_string())),
+  }
+  let schemas = document.pointer("/components/schemas")
+    .and_then(Value::as_object)
+    .map_or(Map::new(), |schemas| {
+      schemas.iter()
+      .map(|(name, schema)| (name.clone(), normalize(schema)))
+      .collect()
+    });
+  let converter = Converter{
+    document: document,
+    schemas: json!({"components": {"schemas": schemas}}),
+  };
+  let server = document.pointer("/servers/0/url")
+    .and_then(Value::as_str)
+    .map(str::to_string);
+
+  let mut operations = vec![];
+  let empty = Map::new();
+  let paths = document.get("paths").and_then(Value::as_object).unwrap_or(&empty);
+  for (path, item) in paths {
+    let item = converter.resolve(item)?;
+    for method in METHODS {
+      if let Some(operation) = item.get(*method) {
+        let pointer = format!("/paths/{}/{}", pointer_token(path), method);
+        let mut operation = converter.operation(item, operation, &pointer)?;
+        operation.method = method.to_uppercase();
+        operation.path = path.clone();
+        operation.server = server.clone();
+        operations.push(operation);
+      }
+    }
+  }
+  Ok(operations)
+}
+
+struct Converter<'a> {
+  document: &'a Value,
+
+  /// A document with the normalized schema components, against which the
+  /// references in normalized schemas are resolved.
+  schemas: Value,
+}
+
+impl<'a> Converter<'a> {
+  fn operation(&self, item: &Value, operation: &Value, pointer: &str)
+    -> Result<Operation> {
+    let name = operation.get("operationId").and_then(Value::as_str)
+      .ok_or_else(|| invalid(pointer, "has no operationId"))?;
+
+    let mut parameters = vec![];
+    let mut fields = vec![];
+    let item_pointer = &pointer[..pointer.rfind('/').unwrap_or(0)];
+    let mut all = vec![];
+    for &(object, object_pointer) in &[(item, item_pointer), (operation, pointer)] {
+      if let Some(parameters) = object.get("parameters").and_then(Value::as_array) {
+        for (index, parameter) in parameters.iter().enumerate() {
+          all.push((parameter, format!("{}/parameters/{}", object_pointer, index)));
+        }
+      }
+    }
+    for (index, &(parameter, ref parameter_pointer)) in all.iter().enumerate() {
+      let parameter = self.resolve(parameter)?;
+      let name = parameter.get("name").and_then(Value::as_str)
+        .ok_or_else(|| invalid(parameter_pointer, "has no name"))?;
+      let location = match parameter.get("in").and_then(Value::as_str) {
+        Some("path") => Location::Path,
+        Some("query") => Location::Query,
+        Some("header") => Location::Header,
+        _ => return Err(invalid(parameter_pointer,
+                                "is not in the path, query or headers")),
+      };
+      // Parameters of the operation override those of the path item.
+      let overridden = all[index + 1..].iter()
+        .filter_map(|&(other, _)| self.resolve(other).ok())
+        .any(|other| {
+          other.get("name") == parameter.get("name")
+          && other.get("in") == parameter.get("in")
+        });
+      if overridden {
+        continue;
+      }
+      if fields.iter().any(|&(ref other, _)| other == name) {
+        return Err(invalid(parameter_pointer,
+                           "has the name of another parameter"));
+      }
+      let schema = parameter.get("schema")
+        .ok_or_else(|| invalid(parameter_pointer, "has no schema"))?;
+      let required = location == Location::Path
+        || parameter.get("required") == Some(&Value::Bool(true));
+      fields.push((name.to_string(), self.schema(schema, required)?));
+      parameters.push(Parameter{name: name.to_string(), location: location});
+    }
+
+    let body = match operation.get("requestBody") {
+      Some(body) => {
+        let body = self.resolve(body)?;
+        let body_pointer = format!("{}/requestBody", pointer);
+        if fields.iter().any(|&(ref other, _)| other == "body") {
+          return Err(invalid(&body_pointer, "conflicts with a parameter"));
+        }
+        let schema = json_content(body, &body_pointer)?
+          .ok_or_else(|| invalid(&body_pointer, "has no content"))?;
+        let required = body.get("required") == Some(&Value::Bool(true));
+        fields.push(("body".to_string(), self.schema(schema, required)?));
+        true
+      },
+      None => false,
+    };
+
+    let response_pointer = format!("{}/responses/200", pointer);
+    let response = operation.pointer("/responses/200")
+      .ok_or_else(|| invalid(pointer, "has no 200 response"))?;
+    let output = match json_content(self.resolve(response)?, &response_pointer)? {
+      Some(schema) => Some(self.schema(schema, true)?),
+      None => None,
+    };
+
+    Ok(Operation{
+      name: name.to_string(),
+      method: String::new(),
+      path: String::new(),
+      server: None,
+      parameters: parameters,
+      body: body,
+      response: output.is_some(),
+      input: Rc::new(Schema::Record(fields)),
+      output: Rc::new(output.unwrap_or(Schema::AllOf(vec![]))),
+    })
+  }
+
+  /// Convert a schema, which is optional unless it is required.
+  fn schema(&self, schema: &Value, required: bool) -> Result<Schema> {
+    let schema = json_schema::to_schema(&self.schemas, &normalize(schema))?;
+    match schema {
+      Schema::Optional(_) => Ok(schema),
+      _ if !required => Ok(Schema::Optional(Box::new(schema))),
+      _ => Ok(schema),
+    }
+  }
+
+  /// Follow the reference of a reference object, if it is one.
+  fn resolve(&self, value: &'a Value) -> Result<&'a Value> {
+    let mut value = value;
+    let mut references = vec![];
+    while let Some(reference) = value.get("$ref") {
+      let reference = reference.as_str().filter(|r| r.starts_with('#'))
+        .ok_or_else(|| Error::Unsupported(
+          "Only local references are supported.".to_string()))?;
+      if references.contains(&reference) {
+        return Err(invalid(&reference[1..], "is recursive"));
+      }
+      references.push(reference);
+      value = self.document.pointer(&reference[1..])
+        .ok_or_else(|| invalid(&reference[1..], "does not exist"))?;
+    }
+    Ok(value)
+  }
+}
+
+/// Return the schema of the JSON content of a request body or response, if
+/// it has content.
+fn json_content<'a>(object: &'a Value, pointer: &str)
+  -> Result<Option<&'a Value>> {
+  match object.get("content").and_then(Value::as_object) {
+    None => Ok(None),
+    Some(content) if content.is_empty() => Ok(None),
+    Some(content) => content.get("application/json")
+      .map(|media| media.get("schema").unwrap_or(&Value::Bool(true)))
+      .map(Some)
+      .ok_or_else(|| invalid(pointer, "has no JSON content")),
+  }
+}
+
+/// Rewrite an OpenAPI schema into a JSON Schema understood by
+/// `json_schema::to_schema`.
+fn normalize(schema: &Value) -> Value {
+  let object = match *schema {
+    Value::Object(ref object) => object,
+    _ => return schema.clone(),
+  };
+  let mut normalized = Map::new();
+  for (key, value) in object {
+    let value = match key.as_str() {
+      "nullable" => continue,
+      key if ANNOTATIONS.contains(&key) => continue,
+      "properties" => Value::Object(
+        value.as_object().map_or(Map::new(), |properties| {
+          properties.iter()
+          .map(|(name, property)| (name.clone(), normalize(property)))
+          .collect()
+        })
+      ),
+      "allOf" | "anyOf" | "oneOf" | "prefixItems" => Value::Array(
+        value.as_array().map_or(vec![], |schemas| {
+          schemas.iter().map(normalize).collect()
+        })
+      ),
+      "items" | "additionalProperties" | "not" => normalize(value),
+      _ => value.clone(),
+    };
+    normalized.insert(key.clone(), value);
+  }
+
+  let type_ = object.get("type").and_then(Value::as_str);
+  match (type_, object.get("format").and_then(Value::as_str)) {
+    (Some("integer"), Some("int32")) => {
+      normalized.remove("format");
+    },
+    (Some("integer"), Some("int64")) => {
+      normalized.remove("format");
+      normalized.insert("type".to_string(), json!("number"));
+    },
+    (Some("number"), Some("float")) => {
+      normalized.remove("format");
+      normalized.insert("minimum".to_string(), json!(-f32::MAX as f64));
+      normalized.insert("maximum".to_string(), json!(f32::MAX as f64));
+    },
+    (Some("number"), Some("double")) => {
+      normalized.remove("format");
+    },
+    _ => (),
+  }
+
+  if object.get("nullable") == Some(&Value::Bool(true)) {
+    json!({"anyOf": [Value::Object(normalized), {"type": "null"}]})
+  } else {
+    Value::Object(normalized)
+  }
+}
+
+fn pointer_token(name: &str) -> String {
+  name.replace('~', "~0").replace('/', "~1")
+}
+
+fn invalid(pointer: &str, problem: &str) -> Error {
+  Error::Unsupported(format!("The OpenAPI object at \"#{}\" {}.", pointer,
+                             problem))
+}
+
+impl HasSchema for Operation {
+  fn schema(&self) -> Result<(Rc<Schema>, Rc<Schema>)> {
+    Ok((self.input.clone(), self.output.clone()))
+  }
+}
+
+impl Rust for Operation {
+  fn rust_context(&self) -> Result<String> {
+    Err(Error::Unsupported(error::RUST.to_string()))
+  }
+
+  fn rust_call(&self, _: &mut io::Write, _: &str) -> Result<()> {
+    Err(Error::Unsupported(error::RUST.to_string()))
+  }
+}
+
+impl ECMAScript for Operation {
+  fn ecmascript_call(&self, write: &mut io::Write) -> Result<()> {
+    let literal = escape::ecmascript_string;
+
+    write!(write, "(function() {{\n")?;
+    write!(write, "var serialize = ")?;
+    serialize(write, &self.input)?;
+    write!(write, ";\n")?;
+    write!(write, "var deserialize = ")?;
+    deserialize(write, &self.output)?;
+    write!(write, ";\n")?;
+
+    write!(write, "return function(url, input, onSuccess, onError) {{\n")?;
+    write!(write, "try {{\n")?;
+    write!(write, "input = serialize(input);\n")?;
+    write!(write, "}} catch (error) {{\n")?;
+    write!(write, "onError(error);\n")?;
+    write!(write, "return;\n")?;
+    write!(write, "}}\n")?;
+
+    // Substitute the path parameters into the path template.
+    write!(write, "var path = ''")?;
+    let mut rest = self.path.as_str();
+    while let Some(start) = rest.find('{') {
+      let end = match rest[start..].find('}') {
+        Some(end) => start + end,
+        None => break,
+      };
+      write!(write, " + {}", literal(&rest[..start]))?;
+      write!(write, " + encodeURIComponent(input[{}])",
+             literal(&rest[start + 1..end]))?;
+      rest = &rest[end + 1..];
+    }
+    write!(write, " + {};\n", literal(rest))?;
+
+    write!(write, "var query = [];\n")?;
+    write!(write, "var headers = [];\n")?;
+    for parameter in &self.parameters {
+      let name = literal(&parameter.name);
+      match parameter.location {
+        Location::Path => continue,
+        Location::Query => {
+          write!(write, "if (input[{0}] !== null && input[{0}] !== undefined) {{\n", name)?;
+          write!(write, "[].concat(input[{}]).forEach(function(value) {{\n", name)?;
+          write!(write, "query.push(encodeURIComponent({}) + '=' + encodeURIComponent(value));\n", name)?;
+          write!(write, "}});\n")?;
+          write!(write, "}}\n")?;
+        },
+        Location::Header => {
+          write!(write, "if (input[{0}] !== null && input[{0}] !== undefined) {{\n", name)?;
+          write!(write, "headers.push([{0}, String(input[{0}])]);\n", name)?;
+          write!(write, "}}\n")?;
+        },
+      }
+    }
+
+    write!(write, "var xhr = new XMLHttpRequest();\n")?;
+    write!(write, "xhr.addEventListener('load', function() {{\n")?;
+    write!(write, "if (xhr.status !== 200) {{\n")?;
+    write!(write, "onError(new Error('The request failed with status ' + xhr.status + '.'));\n")?;
+    write!(write, "return;\n")?;
+    write!(write, "}}\n")?;
+    if self.response {
+      write!(write, "var output;\n")?;
+      write!(write, "try {{\n")?;
+      write!(write, "output = deserialize(JSON.parse(xhr.responseText));\n")?;
+      write!(write, "}} catch (error) {{\n")?;
+      write!(write, "onError(error);\n")?;
+      write!(write, "return;\n")?;
+      write!(write, "}}\n")?;
+      write!(write, "onSuccess(output);\n")?;
+    } else {
+      write!(write, "onSuccess([]);\n")?;
+    }
+    write!(write, "}});\n")?;
+    write!(write, "xhr.addEventListener('error', function() {{\n")?;
+    write!(write, "onError(new Error('The HTTP request failed.'));\n")?;
+    write!(write, "}});\n")?;
+    write!(write, "if (url === null || url === undefined) {{\n")?;
+    write!(write, "url = {};\n", literal(self.server.as_ref().map_or("", |s| s.as_str())))?;
+    write!(write, "}}\n")?;
+    write!(write, "xhr.open({}, url + path + (query.length === 0 ? '' : '?' + query.join('&')));\n",
+           literal(&self.method))?;
+    write!(write, "headers.forEach(function(header) {{\n")?;
+    write!(write, "xhr.setRequestHeader(header[0], header[1]);\n")?;
+    write!(write, "}});\n")?;
+    if self.body {
+      write!(write, "if (input['body'] === null || input['body'] === undefined) {{\n")?;
+      write!(write, "xhr.send();\n")?;
+      write!(write, "}} else {{\n")?;
+      write!(write, "xhr.setRequestHeader('Content-Type', 'application/json');\n")?;
+      write!(write, "xhr.send(JSON.stringify(input['body']));\n")?;
+      write!(write, "}}\n")?;
+    } else {
+      write!(write, "xhr.send();\n")?;
+    }
+    write!(write, "}};\n")?;
+    write!(write, "}})()")?;
+    Ok(())
+  }
+
+  fn ecmascript_convention(&self) -> Result<ECMAScriptConvention> {
+    Ok(ECMAScriptConvention::Asynchronous)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::i32;
+
+  fn document() -> Value {
+    json!({
+      "openapi": "3.0.3",
+      "info": {"title": "Users", "version": "1.0"},
+      "servers": [{"url": "https://example.com/api"}],
+      "paths": {
+        "/users/{id}": {
+          "parameters": [
+            {"name": "id", "in": "path", "required": true,
+             "schema": {"type": "integer", "format": "int32"}},
+          ],
+          "get": {
+            "operationId": "getUser",
+            "parameters": [
+              {"name": "fields", "in": "query",
+               "schema": {"type": "array", "items": {"type": "string"}}},
+            ],
+            "responses": {
+              "200": {
+                "description": "The user.",
+                "content": {
+                  "application/json": {
+                    "schema": {"$ref": "#/components/schemas/User"},
+                  },
+                },
+              },
+            },
+          },
+          "delete": {
+            "operationId": "deleteUser",
+            "responses": {"200": {"description": "The user was deleted."}},
+          },
+        },
+      },
+      "components": {
+        "schemas": {
+          "User": {
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+              "name": {"type": "string", "example": "Alice"},
+              "age": {"type": "integer", "nullable": true},
+              "score": {"type": "number", "format": "double"},
+            },
+          },
+        },
+      },
+    })
+  }
+
+  #[test]
+  fn test_operations() {
+    let operations = operations(&document()).map_err(|e| e.to_string());
+    let user = Schema::Named("User".to_string(), Box::new(Schema::Record(vec![
+      ("name".to_string(), Schema::String),
+      ("age".to_string(), Schema::Optional(Box::new(
+        Schema::SignedInteger(i32::MIN, i32::MAX)))),
+      ("score".to_string(), Schema::Optional(Box::new(
+        Schema::DoublePrecision))),
+    ])));
+    let id = Parameter{name: "id".to_string(), location: Location::Path};
+    let id_field = ("id".to_string(), Schema::SignedInteger(i32::MIN, i32::MAX));
+    assert_eq!(operations, Ok(vec![
+      Operation{
+        name: "getUser".to_string(),
+        method: "GET".to_string(),
+        path: "/users/{id}".to_string(),
+        server: Some("https://example.com/api".to_string()),
+        parameters: vec![
+          id.clone(),
+          Parameter{name: "fields".to_string(), location: Location::Query},
+        ],
+        body: false,
+        response: true,
+        input: Rc::new(Schema::Record(vec![
+          id_field.clone(),
+          ("fields".to_string(), Schema::Optional(Box::new(
+            Schema::ArrayOf(Box::new(Schema::String))))),
+        ])),
+        output: Rc::new(user),
+      },
+      Operation{
+        name: "deleteUser".to_string(),
+        method: "DELETE".to_string(),
+        path: "/users/{id}".to_string(),
+        server: Some("https://example.com/api".to_string()),
+        parameters: vec![id],
+        body: false,
+        response: false,
+        input: Rc::new(Schema::Record(vec![id_field])),
+        output: Rc::new(Schema::AllOf(vec![])),
+      },
+    ]));
+  }
+
+  #[test]
+  fn test_operations_errors() {
+    let mut without_id = document();
+    without_id["paths"]["/users/{id}"]["get"]
+      .as_object_mut().unwrap().remove("operationId");
+    let mut cookie = document();
+    cookie["paths"]["/users/{id}"]["parameters"][0]["in"] = json!("cookie");
+    let cases = vec![
+      (json!({"openapi": "2.0"}),
+       "Only OpenAPI 3 documents are supported."),
+      (without_id,
+       "The OpenAPI object at \"#/paths/~1users~1{id}/get\" has no operationId."),
+      (cookie,
+       concat!("The OpenAPI object at \"#/paths/~1users~1{id}/parameters/0\" ",
+               "is not in the path, query or headers.")),
+    ];
+    for (document, message) in cases {
+      assert_eq!(operations(&document).map_err(|e| e.to_string()),
+                 Err(message.to_string()));
+    }
+  }
+}
@@ -0,0 +1,180 @@
+//! A cache of query descriptions, so that schemas and calls can be generated
+//! without a database. The cache is stored as a JSON file that is meant to be
+//! checked in next to the queries. Entries are keyed by a hash of the query
+//! text, so that a changed query is described again. User-defined types are
+//! recorded by name rather than OID, so that a cache holds for every database
+//! with the same types.
+
+use Schema;
+use error::{Error, Result};
+use postgres::Connection;
+use postgresql::ecmascript::{TypeReference, Types};
+use serde_json;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// The description of a query, from which its schemas and calls are derived.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Description {
+  /// The query as written, with named or positional parameters.
+  pub query: String,
+
+  /// The type and schema of each parameter, in order of position.
+  pub parameters: Vec<(TypeReference, Schema)>,
+
+  /// The name and schema of each result column, taking nullability into
+  /// account.
+  pub columns: Vec<(String, Schema)>,
+
+  /// The array, domain and composite types used by the query.
+  pub types: Types,
+}
+
+/// A cache of query descriptions.
+pub struct Cache {
+  entries: RefCell<BTreeMap<String, Description>>,
+  fresh: RefCell<BTreeSet<String>>,
+}
+
+impl Cache {
+  /// Create an empty cache.
+  pub fn new() -> Cache {
+    Cache{entries: RefCell::new(BTreeMap::new()),
+          fresh: RefCell::new(BTreeSet::new())}
+  }
+
+  /// Read a cache from a file.
+  pub fn read<P>(path: P) -> Result<Cache> where P: AsRef<Path> {
+    let path = path.as_ref();
+    let mut text = String::new();
+    File::open(path)?.read_to_string(&mut text)?;
+    let entries = serde_json::from_str(&text)
+      .map_err(|error| Error::Syntax(format!("{}: {}", path.display(), error)))?;
+    Ok(Cache{entries: RefCell::new(entries), fresh: RefCell::new(BTreeSet::new())})
+  }
+
+  /// Write the cache to a file. The entries are written in order of key, so
+  /// that the file only changes when the descriptions do.
+  pub fn write<P>(&self, path: P) -> Result<()> where P: AsRef<Path> {
+    let text = serde_json::to_string_pretty(&*self.entries.borrow())
+      .map_err(|error| Error::Unsupported(error.to_string()))?;
+    write!(File::create(path)?, "{}\n", text)?;
+    Ok(())
+  }
+
+  /// Return the description of a query.
+  pub fn get(&self, query: &str) -> Option<Description> {
+    self.entries.borrow().get(&key(query))
+    .filter(|description| description.query == query)
+    .cloned()
+  }
+
+  /// Return the description of a query if it was inserted into this cache,
+  /// rather than read from a file.
+  pub fn get_fresh(&self, query: &str) -> Option<Description> {
+    if self.fresh.borrow().contains(&key(query)) {
+      self.get(query)
+    } else {
+      None
+    }
+  }
+
+  /// Insert the description of a query, replacing any existing description.
+  pub fn insert(&self, description: Description) {
+    let key = key(&description.query);
+    self.fresh.borrow_mut().insert(key.clone());
+    self.entries.borrow_mut().insert(key, description);
+  }
+
+  /// Remove the descriptions that were read from a file and not inserted
+  /// since.
+  pub fn prune(&self) {
+    let fresh = self.fresh.borrow();
+    let mut entries = self.entries.borrow_mut();
+    let stale = entries.keys()
+      .filter(|key| !fresh.contains(*key))
+      .cloned()
+      .collect::<Vec<_>>();
+    for key in stale {
+      entries.remove(&key);
+    }
+  }
+
+  /// Describe every query in the cache again using a database, and return the
+  /// queries whose descriptions differ from the cached ones.
+  pub fn verify(&self, connection: &Connection) -> Result<Vec<String>> {
+    let mut drifted = vec![];
+    for description in self.entries.borrow().values() {
+      let (rewritten, _) = super::rewrite(&description.query)?;
+      match super::describe(connection, &description.query, &rewritten) {
+        Ok(ref current) if current == description => (),
+        _ => drifted.push(description.query.clone()),
+      }
+    }
+    Ok(drifted)
+  }
+}
+
+/// Return the cache key of a query, the hexadecimal 64-bit FNV-1a hash of its
+/// text. Unlike the hashers in the standard library, it is stable across
+/// platforms and releases.
+fn key(query: &str) -> String {
+  let hash = query.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+    (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+  });
+  format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use postgres::TlsMode;
+  use postgresql::{InputShape, OutputShape, Query};
+  use source::HasSchema;
+  use std::env;
+
+  #[test]
+  fn test_key() {
+    assert_eq!(key(""), "cbf29ce484222325");
+    assert_eq!(key("a"), "af63dc4c8601ec8c");
+  }
+
+  #[test]
+  fn test_cache() {
+    let connection = match env::var("TYPEFUNNEL_TEST_POSTGRESQL") {
+      Ok(connection_str) =>
+        match Connection::connect(connection_str, TlsMode::None) {
+          Ok(connection) => connection,
+          _ => return println!("Skipping test: could not connect to PostgreSQL"),
+        },
+      _ => return println!("Skipping test: no PostgreSQL connection information given"),
+    };
+    let cache = Cache::new();
+    let mut query = Query{
+      connection: Some(&connection),
+      cache: Some(&cache),
+      query: "SELECT :a :: int AS b".to_string(),
+      input_shape: InputShape::Row,
+      output_shape: OutputShape::Row,
+    };
+    let online = query.schema().map_err(|e| e.to_string());
+    query.connection = None;
+    let offline = query.schema().map_err(|e| e.to_string());
+    assert!(online.is_ok());
+    assert_eq!(online, offline);
+    assert_eq!(cache.verify(&connection).map_err(|e| e.to_string()),
+               Ok(vec![]));
+
+    let mut description = cache.get(&query.query).unwrap();
+    description.columns[0].0 = "c".to_string();
+    cache.insert(description);
+    assert_eq!(cache.verify(&connection).map_err(|e| e.to_string()),
+               Ok(vec![query.query.clone()]));
+
+    query.query = "SELECT 1".to_string();
+    assert!(query.schema().is_err());
+  }
+}
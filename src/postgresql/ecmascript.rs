@@ -0,0 +1,291 @@
+//! Generation of ECMAScript code that converts between the textual
+//! representation of PostgreSQL values and their ECMAScript representation,
+//! on top of the type parsers of the `pg` package.
+//!
+//! Temporal values are kept in their textual representation instead of being
+//! parsed into `Date` objects, which lack precision and time zone
+//! information, and OIDs like 64-bit integers, which are represented by
+//! strings. The `pg` package does not know about user-defined types, so
+//! arrays, domains and composite types are taken care of here.
+//!
+//! The OIDs of user-defined types differ between databases, so these types
+//! are referred to by name, and the generated code looks up their OIDs in the
+//! database it is used with.
+
+use escape;
+use postgres::stmt::Statement;
+use postgres::types::{Kind, Oid, Type};
+use postgresql::nullability::quote_identifier;
+use std::collections::BTreeSet;
+use std::io;
+
+/// The variable to which the code generated by `Types::declare` expects the
+/// `pg` package to be bound.
+pub const PG: &'static str = "$pg";
+
+/// The OIDs of `oid`, `date`, `timestamp`, `timestamptz` and `interval`,
+/// whose values are kept in their textual representation.
+const TEXTUAL_OIDS: &'static str = "26, 1082, 1114, 1184, 1186";
+
+/// The lowest OID that PostgreSQL assigns to objects created after the
+/// database cluster is initialized. Types with lower OIDs are built in, and
+/// have the same OID in every database.
+const FIRST_NORMAL_OID: Oid = 16384;
+
+/// A reference to a type: the OID of a built-in type, or the qualified name
+/// of any other type.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum TypeReference {
+  Oid(Oid),
+  Name(String),
+}
+
+impl TypeReference {
+  /// Refer to a type. Temporary schemas are referred to as `pg_temp`, which
+  /// is the temporary schema of whichever session looks the type up.
+  pub fn new(type_: &Type) -> TypeReference {
+    if type_.oid() < FIRST_NORMAL_OID {
+      return TypeReference::Oid(type_.oid());
+    }
+    let schema = if type_.schema().starts_with("pg_temp") {
+      "pg_temp"
+    } else {
+      type_.schema()
+    };
+    TypeReference::Name(format!("{}.{}", quote_identifier(schema),
+                                quote_identifier(type_.name())))
+  }
+
+  /// Return an ECMAScript expression that evaluates to the OID of the type,
+  /// given the OIDs of the named types in `oids`.
+  pub fn ecmascript(&self) -> String {
+    match *self {
+      TypeReference::Oid(oid) => oid.to_string(),
+      TypeReference::Name(ref name) =>
+        format!("oids[{}]", escape::ecmascript_string(name)),
+    }
+  }
+
+  /// Add the name of the type to `names`, if it is referred to by name.
+  pub fn collect<'a>(&'a self, names: &mut BTreeSet<&'a str>) {
+    if let TypeReference::Name(ref name) = *self {
+      names.insert(name);
+    }
+  }
+}
+
+/// The array, domain and composite types used by a statement.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Types {
+  arrays: Vec<(TypeReference, TypeReference)>,
+  domains: Vec<(TypeReference, TypeReference)>,
+  composites: Vec<(TypeReference, Vec<(String, TypeReference)>)>,
+}
+
+impl Types {
+  /// Collect the types used by the parameters and columns of a statement.
+  pub fn new(statement: &Statement) -> Types {
+    let mut types = Types{arrays: vec![], domains: vec![], composites: vec![]};
+    for type_ in statement.param_types() {
+      types.add(type_);
+    }
+    for column in statement.columns() {
+      types.add(column.type_());
+    }
+    types
+  }
+
+  fn add(&mut self, type_: &Type) {
+    let reference = TypeReference::new(type_);
+    match *type_.kind() {
+      Kind::Array(ref element) =>
+        if !self.arrays.iter().any(|&(ref other, _)| *other == reference) {
+          self.arrays.push((reference, TypeReference::new(element)));
+          self.add(element);
+        },
+      Kind::Domain(ref base) =>
+        if !self.domains.iter().any(|&(ref other, _)| *other == reference) {
+          self.domains.push((reference, TypeReference::new(base)));
+          self.add(base);
+        },
+      Kind::Composite(ref fields) =>
+        if !self.composites.iter().any(|&(ref other, _)| *other == reference) {
+          self.composites.push((
+            reference,
+            fields.iter()
+            .map(|field| {
+              (field.name().to_string(), TypeReference::new(field.type_()))
+            })
+            .collect(),
+          ));
+          for field in fields {
+            self.add(field.type_());
+          }
+        },
+      _ => (),
+    }
+  }
+
+  /// Add the names of the types that are referred to by name to `names`.
+  pub fn collect<'a>(&'a self, names: &mut BTreeSet<&'a str>) {
+    for &(ref array, ref element) in &self.arrays {
+      array.collect(names);
+      element.collect(names);
+    }
+    for &(ref domain, ref base) in &self.domains {
+      domain.collect(names);
+      base.collect(names);
+    }
+    for &(ref composite, ref fields) in &self.composites {
+      composite.collect(names);
+      for &(_, ref field) in fields {
+        field.collect(names);
+      }
+    }
+  }
+
+  /// Generate ECMAScript statements that declare `getTypeParser`, to be
+  /// passed as the `types` option of a query, and `encode`, which converts a
+  /// value of the type with the given OID into a query parameter. The `pg`
+  /// package must be bound to the variable `PG`, and the OIDs of the types
+  /// collected by `collect` to the properties of `oids` named after them.
+  pub fn declare(&self, write: &mut io::Write) -> io::Result<()> {
+    write!(write, "var types = {}.types;\n", PG)?;
+    write!(write, "var arrays = {{}};\n")?;
+    for &(ref array, ref element) in &self.arrays {
+      write!(write, "arrays[{}] = {};\n", array.ecmascript(),
+             element.ecmascript())?;
+    }
+    write!(write, "var domains = {{}};\n")?;
+    for &(ref domain, ref base) in &self.domains {
+      write!(write, "domains[{}] = {};\n", domain.ecmascript(),
+             base.ecmascript())?;
+    }
+    write!(write, "var composites = {{}};\n")?;
+    for &(ref composite, ref fields) in &self.composites {
+      write!(write, "composites[{}] = [", composite.ecmascript())?;
+      for &(ref name, ref field) in fields {
+        write!(write, "[{}, {}], ", escape::ecmascript_string(name),
+               field.ecmascript())?;
+      }
+      write!(write, "];\n")?;
+    }
+
+    write!(write, "var getTypeParser = function(oid, format) {{\n")?;
+    write!(write, "if ([{}].indexOf(oid) !== -1) {{\n", TEXTUAL_OIDS)?;
+    write!(write, "return String;\n")?;
+    write!(write, "}}\n")?;
+    write!(write, "if (arrays.hasOwnProperty(oid)) {{\n")?;
+    write!(write, "var parseElement = getTypeParser(arrays[oid], format);\n")?;
+    write!(write, "return function(text) {{\n")?;
+    write!(write, "return types.arrayParser.create(text, parseElement).parse();\n")?;
+    write!(write, "}};\n")?;
+    write!(write, "}}\n")?;
+    write!(write, "if (domains.hasOwnProperty(oid)) {{\n")?;
+    write!(write, "return getTypeParser(domains[oid], format);\n")?;
+    write!(write, "}}\n")?;
+    write!(write, "if (composites.hasOwnProperty(oid)) {{\n")?;
+    write!(write, "return function(text) {{\n")?;
+    write!(write, "return parseComposite(text, composites[oid]);\n")?;
+    write!(write, "}};\n")?;
+    write!(write, "}}\n")?;
+    write!(write, "return types.getTypeParser(oid, format);\n")?;
+    write!(write, "}};\n")?;
+
+    write!(write, "var encode = function(oid, value) {{\n")?;
+    write!(write, "if (value === null || value === undefined) {{\n")?;
+    write!(write, "return null;\n")?;
+    write!(write, "}}\n")?;
+    write!(write, "if (arrays.hasOwnProperty(oid)) {{\n")?;
+    write!(write, "return value.map(function(element) {{\n")?;
+    write!(write, "return encode(arrays[oid], element);\n")?;
+    write!(write, "}});\n")?;
+    write!(write, "}}\n")?;
+    write!(write, "if (domains.hasOwnProperty(oid)) {{\n")?;
+    write!(write, "return encode(domains[oid], value);\n")?;
+    write!(write, "}}\n")?;
+    write!(write, "if (composites.hasOwnProperty(oid)) {{\n")?;
+    write!(write, "return {{toPostgres: function() {{\n")?;
+    write!(write, "return formatComposite(value, composites[oid]);\n")?;
+    write!(write, "}}}};\n")?;
+    write!(write, "}}\n")?;
+    write!(write, "return value;\n")?;
+    write!(write, "}};\n")?;
+
+    if !self.composites.is_empty() {
+      declare_composite_functions(write)?;
+    }
+    Ok(())
+  }
+}
+
+/// Generate ECMAScript statements that declare `parseComposite` and
+/// `formatComposite`, which convert between the textual representation of
+/// composite values and objects, and `formatField`, which converts an encoded
+/// value into the textual representation of a field.
+fn declare_composite_functions(write: &mut io::Write) -> io::Result<()> {
+  write!(write, "var parseComposite = function(text, fields) {{\n")?;
+  write!(write, "var values = [];\n")?;
+  write!(write, "var value = '';\n")?;
+  write!(write, "var quoted = false;\n")?;
+  write!(write, "var present = false;\n")?;
+  write!(write, "for (var i = 1; i < text.length - 1; ++i) {{\n")?;
+  write!(write, "var c = text[i];\n")?;
+  write!(write, "if (quoted && c === '\\\\') {{\n")?;
+  write!(write, "value += text[++i];\n")?;
+  write!(write, "}} else if (quoted && c === '\"' && text[i + 1] === '\"') {{\n")?;
+  write!(write, "value += text[++i];\n")?;
+  write!(write, "}} else if (c === '\"') {{\n")?;
+  write!(write, "quoted = !quoted;\n")?;
+  write!(write, "present = true;\n")?;
+  write!(write, "}} else if (!quoted && c === ',') {{\n")?;
+  write!(write, "values.push(present ? value : null);\n")?;
+  write!(write, "value = '';\n")?;
+  write!(write, "present = false;\n")?;
+  write!(write, "}} else {{\n")?;
+  write!(write, "value += c;\n")?;
+  write!(write, "present = true;\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "values.push(present ? value : null);\n")?;
+  write!(write, "var result = {{}};\n")?;
+  write!(write, "fields.forEach(function(field, index) {{\n")?;
+  write!(write, "result[field[0]] = values[index] === null ? null\n")?;
+  write!(write, "  : getTypeParser(field[1], 'text')(values[index]);\n")?;
+  write!(write, "}});\n")?;
+  write!(write, "return result;\n")?;
+  write!(write, "}};\n")?;
+
+  write!(write, "var formatComposite = function(value, fields) {{\n")?;
+  write!(write, "return '(' + fields.map(function(field) {{\n")?;
+  write!(write, "var text = formatField(encode(field[1], value[field[0]]));\n")?;
+  write!(write, "return text === null ? ''\n")?;
+  write!(write, "  : '\"' + text.replace(/([\"\\\\])/g, '\\\\$1') + '\"';\n")?;
+  write!(write, "}}).join(',') + ')';\n")?;
+  write!(write, "}};\n")?;
+
+  write!(write, "var formatField = function(value) {{\n")?;
+  write!(write, "if (value === null || value === undefined) {{\n")?;
+  write!(write, "return null;\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "if (Buffer.isBuffer(value)) {{\n")?;
+  write!(write, "return '\\\\x' + value.toString('hex');\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "if (Array.isArray(value)) {{\n")?;
+  write!(write, "return '{{' + value.map(function(element) {{\n")?;
+  write!(write, "var text = formatField(element);\n")?;
+  write!(write, "return text === null ? 'NULL'\n")?;
+  write!(write, "  : '\"' + text.replace(/([\"\\\\])/g, '\\\\$1') + '\"';\n")?;
+  write!(write, "}}).join(',') + '}}';\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "if (typeof value.toPostgres === 'function') {{\n")?;
+  write!(write, "return value.toPostgres();\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "if (typeof value === 'object') {{\n")?;
+  write!(write, "return JSON.stringify(value);\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "return String(value);\n")?;
+  write!(write, "}};\n")?;
+  Ok(())
+}
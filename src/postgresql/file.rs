@@ -0,0 +1,176 @@
+//! This module implements a source that reads many queries from a SQL file.
+//! Each query is preceded by an annotation comment that gives its name and
+//! the shape of its result, and optionally the shape of its parameters:
+//!
+//! ```sql
+//! -- name: GetUser :one
+//! SELECT id, name FROM users WHERE id = :id;
+//!
+//! -- name: GetUserName :scalar :scalar_input
+//! SELECT name FROM users WHERE id = $1;
+//! ```
+//!
+//! The result shapes are `:one` (a row), `:many` (a table) and `:scalar`. The
+//! parameter shapes are `:row_input`, the default, and `:scalar_input`.
+
+use error::{Error, Result};
+use postgres::Connection;
+use postgresql::{InputShape, OutputShape, Query};
+use postgresql::cache::Cache;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+mod error {
+  pub static ORPHAN_STATEMENT: &'static str =
+    "The SQL file contains a statement that is not preceded by an annotation.";
+}
+
+/// Read the annotated queries from a SQL file, in order of occurrence. The
+/// queries are described using the given connection or cache.
+pub fn read<'a, P>(connection: Option<&'a Connection>,
+                   cache: Option<&'a Cache>, path: P)
+  -> Result<Vec<(String, Query<'a>)>> where P: AsRef<Path> {
+  let mut text = String::new();
+  File::open(path)?.read_to_string(&mut text)?;
+  parse(connection, cache, &text)
+}
+
+/// Parse the annotated queries in the text of a SQL file, in order of
+/// occurrence.
+pub fn parse<'a>(connection: Option<&'a Connection>,
+                 cache: Option<&'a Cache>, text: &str)
+  -> Result<Vec<(String, Query<'a>)>> {
+  Ok(
+    annotated_queries(text)?.into_iter()
+    .map(|(name, query, input_shape, output_shape)| (name, Query{
+      connection: connection,
+      cache: cache,
+      query: query,
+      input_shape: input_shape,
+      output_shape: output_shape,
+    }))
+    .collect()
+  )
+}
+
+fn annotated_queries(text: &str)
+  -> Result<Vec<(String, String, InputShape, OutputShape)>> {
+  let mut queries: Vec<(String, String, InputShape, OutputShape)> = vec![];
+  for (number, line) in text.lines().enumerate() {
+    if let Some((name, input_shape, output_shape)) =
+        annotation(number + 1, line)? {
+      if queries.iter().any(|query| query.0 == name) {
+        return Err(Error::Syntax(
+          format!("Line {}: the query {} is defined twice.", number + 1, name)));
+      }
+      queries.push((name, String::new(), input_shape, output_shape));
+    } else if let Some(query) = queries.last_mut() {
+      query.1.push_str(line);
+      query.1.push('\n');
+    } else if !line.trim().is_empty() && !line.trim().starts_with("--") {
+      return Err(Error::Syntax(error::ORPHAN_STATEMENT.to_string()));
+    }
+  }
+  for query in &mut queries {
+    query.1 = query.1.trim().trim_end_matches(';').trim_end().to_string();
+  }
+  Ok(queries)
+}
+
+/// Parse an annotation comment such as `-- name: GetUser :one`. Return
+/// `None` if the line is not an annotation.
+fn annotation(number: usize, line: &str)
+  -> Result<Option<(String, InputShape, OutputShape)>> {
+  let line = line.trim();
+  if !line.starts_with("--") || !line[2..].trim_start().starts_with("name:") {
+    return Ok(None);
+  }
+  let rest = &line[2..].trim_start()["name:".len()..];
+  let mut words = rest.split_whitespace();
+  let name = match words.next() {
+    Some(name) if !name.starts_with(':') => name.to_string(),
+    _ => return Err(Error::Syntax(
+      format!("Line {}: the annotation does not name the query.", number))),
+  };
+  let mut input_shape = None;
+  let mut output_shape = None;
+  for word in words {
+    let (input, output) = match word {
+      ":one" => (None, Some(OutputShape::Row)),
+      ":many" => (None, Some(OutputShape::Table)),
+      ":scalar" => (None, Some(OutputShape::Scalar)),
+      ":row_input" => (Some(InputShape::Row), None),
+      ":scalar_input" => (Some(InputShape::Scalar), None),
+      _ => return Err(Error::Syntax(
+        format!("Line {}: unknown annotation {}.", number, word))),
+    };
+    if input.is_some() && input_shape.is_some()
+       || output.is_some() && output_shape.is_some() {
+      return Err(Error::Syntax(
+        format!("Line {}: the annotation {} conflicts with another.",
+                number, word)));
+    }
+    input_shape = input_shape.or(input);
+    output_shape = output_shape.or(output);
+  }
+  match output_shape {
+    Some(output_shape) =>
+      Ok(Some((name, input_shape.unwrap_or(InputShape::Row), output_shape))),
+    None => Err(Error::Syntax(
+      format!("Line {}: the annotation does not give the result shape of {}.",
+              number, name))),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_annotated_queries() {
+    let text = "-- Users.\n\
+                \n\
+                -- name: GetUser :one\n\
+                SELECT id, name\n\
+                FROM users WHERE id = :id;\n\
+                \n\
+                --name: GetUserName :scalar :scalar_input\n\
+                SELECT name FROM users WHERE id = $1;\n\
+                -- name: GetUsers   :many\n\
+                SELECT id, name FROM users\n";
+    let queries = annotated_queries(text).map_err(|e| e.to_string());
+    assert_eq!(queries, Ok(vec![
+      ("GetUser".to_string(),
+       "SELECT id, name\nFROM users WHERE id = :id".to_string(),
+       InputShape::Row, OutputShape::Row),
+      ("GetUserName".to_string(),
+       "SELECT name FROM users WHERE id = $1".to_string(),
+       InputShape::Scalar, OutputShape::Scalar),
+      ("GetUsers".to_string(),
+       "SELECT id, name FROM users".to_string(),
+       InputShape::Row, OutputShape::Table),
+    ]));
+  }
+
+  #[test]
+  fn test_annotated_queries_errors() {
+    let cases = vec![
+      ("SELECT 1;\n-- name: A :one\nSELECT 1;",
+       error::ORPHAN_STATEMENT.to_string()),
+      ("-- name: A\nSELECT 1;",
+       "Line 1: the annotation does not give the result shape of A."
+       .to_string()),
+      ("-- name: A :exec\nSELECT 1;",
+       "Line 1: unknown annotation :exec.".to_string()),
+      ("-- name: A :one :many\nSELECT 1;",
+       "Line 1: the annotation :many conflicts with another.".to_string()),
+      ("-- name: A :one\nSELECT 1;\n-- name: A :many\nSELECT 1;",
+       "Line 3: the query A is defined twice.".to_string()),
+    ];
+    for (text, message) in cases {
+      let queries = annotated_queries(text).map_err(|e| e.to_string());
+      assert_eq!(queries, Err(message));
+    }
+  }
+}
@@ -0,0 +1,213 @@
+//! A minimal SQL lexer that finds the parameters in a query. It knows just
+//! enough of the PostgreSQL lexical structure to skip over string literals,
+//! quoted identifiers and comments.
+
+use std::ops::Range;
+
+/// A parameter occurring in a query.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Parameter {
+  /// A positional parameter such as `$1`.
+  Positional(usize),
+
+  /// A named parameter such as `:name` or `@name`.
+  Named(String),
+}
+
+/// Return the parameters in a query, together with the byte ranges at which
+/// they occur.
+pub fn parameters(query: &str) -> Vec<(Range<usize>, Parameter)> {
+  let bytes = query.as_bytes();
+  let mut parameters = vec![];
+  let mut i = 0;
+  while i < bytes.len() {
+    let follows_identifier = i > 0 && is_identifier(bytes[i - 1]);
+    i = match bytes[i] {
+      b'\'' => {
+        let escapes = follows_identifier
+          && (bytes[i - 1] == b'E' || bytes[i - 1] == b'e')
+          && !(i > 1 && is_identifier(bytes[i - 2]));
+        skip_quoted(bytes, i, escapes)
+      },
+      b'"' => skip_quoted(bytes, i, false),
+      b'-' if bytes.get(i + 1) == Some(&b'-') =>
+        bytes[i..].iter().position(|&b| b == b'\n')
+        .map_or(bytes.len(), |end| i + end + 1),
+      b'/' if bytes.get(i + 1) == Some(&b'*') => skip_comment(bytes, i),
+      b'$' if !follows_identifier => {
+        let digits = bytes[i + 1..].iter()
+          .take_while(|b| b.is_ascii_digit())
+          .count();
+        if digits > 0 {
+          let end = i + 1 + digits;
+          if let Ok(index) = query[i + 1..end].parse() {
+            parameters.push((i..end, Parameter::Positional(index)));
+          }
+          end
+        } else {
+          skip_dollar_quoted(bytes, i)
+        }
+      },
+      b':' | b'@' if starts_named_parameter(bytes, i) => {
+        let length = bytes[i + 1..].iter()
+          .take_while(|&&b| is_identifier(b) && b != b'$')
+          .count();
+        let end = i + 1 + length;
+        let name = query[i + 1..end].to_string();
+        parameters.push((i..end, Parameter::Named(name)));
+        end
+      },
+      _ => i + 1,
+    };
+  }
+  parameters
+}
+
+/// Replace each parameter in a query by the result of `replace`.
+pub fn rewrite<F>(query: &str, mut replace: F) -> String
+  where F: FnMut(&Parameter) -> String {
+  let mut result = String::new();
+  let mut offset = 0;
+  for (range, parameter) in parameters(query) {
+    result.push_str(&query[offset..range.start]);
+    result.push_str(&replace(&parameter));
+    offset = range.end;
+  }
+  result.push_str(&query[offset..]);
+  result
+}
+
+/// Return whether a named parameter starts at `i`. Casts (`a::b`) and array
+/// slices (`a[b:c]`, `a[:c]`) do not start named parameters, and neither does
+/// an `@` that follows an operator character, since PostgreSQL reads it as
+/// part of an operator such as `<@` or `@@`.
+fn starts_named_parameter(bytes: &[u8], i: usize) -> bool {
+  let preceded = i > 0 && match bytes[i - 1] {
+    b':' | b'[' => true,
+    previous =>
+      is_identifier(previous) || (bytes[i] == b'@' && is_operator(previous)),
+  };
+  !preceded && bytes.get(i + 1).map_or(false, |&b| is_name_start(b))
+}
+
+fn is_operator(byte: u8) -> bool {
+  b"+-*/<>=~!@#%^&|`?".contains(&byte)
+}
+
+fn is_name_start(byte: u8) -> bool {
+  byte.is_ascii_alphabetic() || byte == b'_' || byte >= 0x80
+}
+
+fn is_identifier(byte: u8) -> bool {
+  byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' || byte >= 0x80
+}
+
+/// Skip a string literal or quoted identifier starting at `start`, in which
+/// the quote is escaped by doubling it, or by a backslash if `escapes` is set.
+fn skip_quoted(bytes: &[u8], start: usize, escapes: bool) -> usize {
+  let quote = bytes[start];
+  let mut i = start + 1;
+  while i < bytes.len() {
+    if escapes && bytes[i] == b'\\' {
+      i += 2;
+    } else if bytes[i] == quote {
+      if bytes.get(i + 1) == Some(&quote) {
+        i += 2;
+      } else {
+        return i + 1;
+      }
+    } else {
+      i += 1;
+    }
+  }
+  bytes.len()
+}
+
+/// Skip a block comment starting at `start`. Block comments nest.
+fn skip_comment(bytes: &[u8], start: usize) -> usize {
+  let mut depth = 0;
+  let mut i = start;
+  while i < bytes.len() {
+    if bytes[i..].starts_with(b"/*") {
+      depth += 1;
+      i += 2;
+    } else if bytes[i..].starts_with(b"*/") {
+      depth -= 1;
+      i += 2;
+      if depth == 0 {
+        return i;
+      }
+    } else {
+      i += 1;
+    }
+  }
+  bytes.len()
+}
+
+/// Skip a dollar-quoted string starting at `start`. If there is no valid tag
+/// at `start`, skip only the dollar sign.
+fn skip_dollar_quoted(bytes: &[u8], start: usize) -> usize {
+  let tag_length = bytes[start + 1..].iter()
+    .take_while(|&&b| is_identifier(b) && b != b'$')
+    .count();
+  let tag_end = start + 1 + tag_length;
+  if bytes.get(tag_end) != Some(&b'$') {
+    return start + 1;
+  }
+  let tag = &bytes[start..tag_end + 1];
+  bytes[tag_end + 1..].windows(tag.len())
+  .position(|window| window == tag)
+  .map_or(bytes.len(), |end| tag_end + 1 + end + tag.len())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_parameters() {
+    let query = "SELECT $1, '$2', \"$3\", $$ $4 $$, $x$ $5 $x$, a$6 -- $7\n\
+                 /* /* $8 */ $9 */ + $10";
+    let parameters = parameters(query).into_iter()
+      .map(|(_, parameter)| parameter)
+      .collect::<Vec<_>>();
+    assert_eq!(parameters, vec![Parameter::Positional(1), Parameter::Positional(10)]);
+  }
+
+  #[test]
+  fn test_named_parameters() {
+    let query = "SELECT :a :: int, @b, ':c', x::d, e:f, g <@h, f(i := 1)";
+    let parameters = parameters(query).into_iter()
+      .map(|(_, parameter)| parameter)
+      .collect::<Vec<_>>();
+    assert_eq!(parameters, vec![
+      Parameter::Named("a".to_string()),
+      Parameter::Named("b".to_string()),
+    ]);
+  }
+
+  #[test]
+  fn test_operators_and_slices() {
+    let query = concat!("SELECT tsv @@ to_tsquery(:q), a[:n], a[1:m], ",
+                        "b @> @c, d=@e, f = :g, h=:i, @@j, k#@l");
+    let parameters = parameters(query).into_iter()
+      .map(|(_, parameter)| parameter)
+      .collect::<Vec<_>>();
+    assert_eq!(parameters, vec![
+      Parameter::Named("q".to_string()),
+      Parameter::Named("c".to_string()),
+      Parameter::Named("g".to_string()),
+      Parameter::Named("i".to_string()),
+    ]);
+  }
+
+  #[test]
+  fn test_rewrite() {
+    let query = "SELECT E'\\'$1', $2 :: int";
+    let rewritten = rewrite(query, |parameter| match *parameter {
+      Parameter::Positional(index) => format!("(NULL :: int{})", index * 2),
+      Parameter::Named(_) => unreachable!(),
+    });
+    assert_eq!(rewritten, "SELECT E'\\'$1', (NULL :: int4) :: int");
+  }
+}
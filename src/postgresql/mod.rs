@@ -0,0 +1,932 @@
+//! This module implements the PostgreSQL query source.
+
+use Schema;
+use error::{Error, Result};
+use escape;
+use postgres::Connection;
+use postgres::stmt::Column;
+use postgres::types::{Kind, Type};
+use source::call::{ECMAScript, ECMAScriptConvention, Rust, rust_hint,
+                   rust_type};
+use source::HasSchema;
+use std::{i16, i32, i64, u32};
+use std::collections::BTreeSet;
+use std::io;
+use std::rc::Rc;
+
+pub mod cache;
+pub mod file;
+
+mod ecmascript;
+mod lexer;
+mod nullability;
+mod rust;
+
+use self::cache::{Cache, Description};
+use self::ecmascript::TypeReference;
+use self::lexer::Parameter;
+
+mod error {
+  pub static SCALAR: &'static str =
+    concat!("The expected shape of the query result was scalar, but the ",
+            "query returns zero or more than one column.");
+
+  pub static DUPLICATE_COLUMN: &'static str =
+    concat!("The expected shape of the query result was a row or a table, ",
+            "but the query returns multiple columns with the same name.");
+
+  pub static SCALAR_INPUT: &'static str =
+    concat!("The expected shape of the query parameters was scalar, but the ",
+            "query has zero or more than one parameter.");
+
+  pub static MIXED_PARAMETERS: &'static str =
+    "A query cannot have both positional and named parameters.";
+
+  pub static NO_DATABASE: &'static str =
+    "A query cannot be described without a database connection or a cache.";
+
+  pub static NO_ROWS: &'static str = "The query returned no rows.";
+
+  pub static NO_COLUMN: &'static str =
+    "The query result has fewer columns than described.";
+}
+
+/// A SQL query source.
+pub struct Query<'a> {
+  /// The database connection. Without a connection, the query is looked up
+  /// in the cache.
+  pub connection: Option<&'a Connection>,
+
+  /// The schema cache. Queries described using the connection are stored in
+  /// the cache, and described only once.
+  pub cache: Option<&'a Cache>,
+
+  /// The (optionally parameterized) well-typed SQL query. Parameters are
+  /// either positional (`$1`) or named (`:name` or `@name`), but not both.
+  pub query: String,
+
+  /// The expected shape of the query parameters.
+  pub input_shape: InputShape,
+
+  /// The expected shape of the query result.
+  pub output_shape: OutputShape,
+}
+
+/// The expected shape of the parameters of a query.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InputShape {
+  /// A single row with any number of columns.
+  Row,
+
+  /// A single row with a single column.
+  Scalar,
+}
+
+/// The expected shape of a query result.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutputShape {
+  /// Any number of rows with any number of columns.
+  Table,
+
+  /// A single row with any number of columns.
+  Row,
+
+  /// A single row with a single column.
+  Scalar,
+}
+
+impl<'a> Query<'a> {
+  /// Describe the query using the connection or the cache. Return the
+  /// description, the query with positional parameters and the names of the
+  /// parameters in order of position.
+  fn describe(&self) -> Result<(Description, String, Vec<String>)> {
+    let (query, names) = rewrite(&self.query)?;
+    let description = match (self.connection, self.cache) {
+      (Some(connection), Some(cache)) =>
+        match cache.get_fresh(&self.query) {
+          Some(description) => description,
+          None => {
+            let description = describe(connection, &self.query, &query)?;
+            cache.insert(description.clone());
+            description
+          },
+        },
+      (Some(connection), None) => describe(connection, &self.query, &query)?,
+      (None, Some(cache)) =>
+        cache.get(&self.query).ok_or_else(|| Error::Cache(
+          format!("The schema cache has no entry for the query {}.",
+                  self.query)))?,
+      (None, None) =>
+        return Err(Error::NoDatabase(error::NO_DATABASE.to_string())),
+    };
+    Ok((description, query, names))
+  }
+}
+
+impl<'a> HasSchema for Query<'a> {
+  fn schema(&self) -> Result<(Rc<Schema>, Rc<Schema>)> {
+    let (description, _, names) = self.describe()?;
+    let input_schema = input_schema(self.input_shape, &description.parameters,
+                                    &names)?;
+    let output_schema = output_schema(self.output_shape,
+                                      &description.columns)?;
+    Ok((Rc::new(input_schema), Rc::new(output_schema)))
+  }
+}
+
+impl<'a> ECMAScript for Query<'a> {
+  fn ecmascript_call(&self, write: &mut io::Write) -> Result<()> {
+    let (description, query, names) = self.describe()?;
+    let mut types = BTreeSet::new();
+    description.types.collect(&mut types);
+    for &(ref type_, _) in &description.parameters {
+      type_.collect(&mut types);
+    }
+
+    // The OIDs of types referred to by name are looked up once per client.
+    if types.is_empty() {
+      write!(write, "(function(client, input, onSuccess, onError) {{\n")?;
+    } else {
+      write!(write, "(function() {{\n")?;
+      write!(write, "var names = [")?;
+      for name in &types {
+        write!(write, "{}, ", escape::ecmascript_string(name))?;
+      }
+      write!(write, "];\n")?;
+      write!(write, "var resolved = new WeakMap();\n")?;
+      write!(write, "var call = function(oids, client, input, onSuccess, ")?;
+      write!(write, "onError) {{\n")?;
+    }
+    description.types.declare(write)?;
+    write!(write, "var parameters = [")?;
+    for &(ref type_, _) in &description.parameters {
+      write!(write, "{}, ", type_.ecmascript())?;
+    }
+    write!(write, "];\n")?;
+    write!(write, "var values = ")?;
+    match self.input_shape {
+      InputShape::Row if !names.is_empty() => {
+        write!(write, "[")?;
+        for name in &names {
+          write!(write, "input[{}], ", escape::ecmascript_string(name))?;
+        }
+        write!(write, "]")?;
+      },
+      InputShape::Row => write!(write, "input")?,
+      InputShape::Scalar => write!(write, "[input]")?,
+    }
+    write!(write, ".map(function(value, index) {{\n")?;
+    write!(write, "return encode(parameters[index], value);\n")?;
+    write!(write, "}});\n")?;
+    write!(write, "client.query({{text: {}, values: values",
+           escape::ecmascript_string(&query))?;
+    if self.output_shape == OutputShape::Scalar {
+      write!(write, ", rowMode: 'array'")?;
+    }
+    write!(write, ", types: {{getTypeParser: getTypeParser}}")?;
+    write!(write, "}}, function(err, result) {{\n")?;
+    write!(write, "if (err !== null) {{\n")?;
+    write!(write, "onError(err);\n")?;
+    write!(write, "return;\n")?;
+    write!(write, "}}\nonSuccess(")?;
+    match self.output_shape {
+      OutputShape::Table => write!(write, "result.rows")?,
+      OutputShape::Row => write!(write, "result.rows[0]")?,
+      OutputShape::Scalar => write!(write, "result.rows[0][0]")?,
+    }
+    write!(write, ");\n")?;
+    write!(write, "}});\n")?;
+    write!(write, "}}")?;
+    if types.is_empty() {
+      write!(write, ")")?;
+    } else {
+      write!(write, ";\n")?;
+      write!(write, "return function(client, input, onSuccess, onError) {{\n")?;
+      write!(write, "if (resolved.has(client)) {{\n")?;
+      write!(write, "call(resolved.get(client), client, input, onSuccess, ")?;
+      write!(write, "onError);\n")?;
+      write!(write, "return;\n")?;
+      write!(write, "}}\n")?;
+      write!(write, "client.query({{text: {}, values: [names], ",
+             escape::ecmascript_string(
+               "SELECT $1 :: text[] :: regtype[] :: oid[] :: text"))?;
+      write!(write, "rowMode: 'array'}}, function(err, result) {{\n")?;
+      write!(write, "if (err !== null) {{\n")?;
+      write!(write, "onError(err);\n")?;
+      write!(write, "return;\n")?;
+      write!(write, "}}\n")?;
+      write!(write, "var oids = {{}};\n")?;
+      write!(write, "result.rows[0][0].slice(1, -1).split(',')")?;
+      write!(write, ".forEach(function(oid, index) {{\n")?;
+      write!(write, "oids[names[index]] = Number(oid);\n")?;
+      write!(write, "}});\n")?;
+      write!(write, "resolved.set(client, oids);\n")?;
+      write!(write, "call(oids, client, input, onSuccess, onError);\n")?;
+      write!(write, "}});\n")?;
+      write!(write, "}};\n")?;
+      write!(write, "}})()")?;
+    }
+    Ok(())
+  }
+
+  fn ecmascript_convention(&self) -> Result<ECMAScriptConvention> {
+    Ok(ECMAScriptConvention::Asynchronous)
+  }
+
+  fn ecmascript_imports(&self) -> Vec<(&'static str, &'static str)> {
+    vec![(ecmascript::PG, "pg")]
+  }
+}
+
+impl<'a> Rust for Query<'a> {
+  fn rust_context(&self) -> Result<String> {
+    Ok("&dyn postgres::GenericConnection".to_string())
+  }
+
+  fn rust_support(&self) -> Result<Vec<&'static str>> {
+    let (description, _, _) = self.describe()?;
+    let mut support = vec![];
+    for &(_, ref schema) in &description.parameters {
+      rust::support(schema, &mut support);
+    }
+    for &(_, ref schema) in &description.columns {
+      rust::support(schema, &mut support);
+    }
+    Ok(support)
+  }
+
+  fn rust_call(&self, write: &mut io::Write, name: &str) -> Result<()> {
+    let (description, query, names) = self.describe()?;
+    let output_schema = output_schema(self.output_shape,
+                                      &description.columns)?;
+    let query = lexer::rewrite(&query, |parameter| match *parameter {
+      Parameter::Positional(index) =>
+        description.parameters.get(index - 1)
+        .and_then(|&(_, ref schema)| rust::parameter_cast(index, schema))
+        .unwrap_or_else(|| format!("${}", index)),
+      Parameter::Named(ref name) => format!(":{}", name),
+    });
+    let mut parameters = vec![];
+    for (index, &(_, ref schema)) in description.parameters.iter().enumerate() {
+      rust::postgres_type(schema)?;
+      let value = match self.input_shape {
+        InputShape::Row if !names.is_empty() =>
+          format!("input.{}", escape::rust_value_name(&names[index])?),
+        InputShape::Row => format!("input.{}", index),
+        InputShape::Scalar => "input".to_string(),
+      };
+      write!(write, "    let p{} = {};\n", index,
+             rust::to_postgres(schema, &value))?;
+      parameters.push(format!("&p{}", index));
+    }
+    write!(write, "    let rows = context.query({}, &[{}])?;\n",
+           escape::rust_string(&query), parameters.join(", "))?;
+    let hint = rust_hint(name, "output");
+    match self.output_shape {
+      OutputShape::Table => {
+        let element = match output_schema {
+          Schema::ArrayOf(ref element) => element,
+          _ => unreachable!(),
+        };
+        write!(write, "    let mut output = Vec::with_capacity(rows.len());\n")?;
+        write!(write, "    for row in rows.iter() {{\n")?;
+        write!(write, "        output.push(")?;
+        rust_row(write, element, &description.columns,
+                 &rust_hint(&hint, "element"), "        ")?;
+        write!(write, ");\n")?;
+        write!(write, "    }}\n")?;
+        write!(write, "    Ok(output)\n")?;
+      },
+      OutputShape::Row => {
+        write!(write, "    let row = rows.iter().next().ok_or({})?;\n",
+               escape::rust_string(error::NO_ROWS))?;
+        write!(write, "    Ok(")?;
+        rust_row(write, &output_schema, &description.columns, &hint, "    ")?;
+        write!(write, ")\n")?;
+      },
+      OutputShape::Scalar => {
+        write!(write, "    let row = rows.iter().next().ok_or({})?;\n",
+               escape::rust_string(error::NO_ROWS))?;
+        write!(write, "    Ok({})\n",
+               rust_column(0, &description.columns[0].1)?)?;
+      },
+    }
+    Ok(())
+  }
+}
+
+/// Generate a Rust expression that builds the struct of a row from the
+/// columns of `row`.
+fn rust_row(write: &mut io::Write, schema: &Schema,
+            columns: &[(String, Schema)], hint: &str, indentation: &str)
+  -> Result<()> {
+  rust_type(write, schema, hint)?;
+  write!(write, " {{\n")?;
+  for (index, &(ref name, ref schema)) in columns.iter().enumerate() {
+    write!(write, "{}    {}: {},\n", indentation,
+           escape::rust_value_name(name)?, rust_column(index, schema)?)?;
+  }
+  write!(write, "{}}}", indentation)?;
+  Ok(())
+}
+
+/// Return a Rust expression that gets a column of `row`.
+fn rust_column(index: usize, schema: &Schema) -> Result<String> {
+  let column = format!("row.get_opt::<_, {}>({}).ok_or({})??",
+                       rust::postgres_type(schema)?, index,
+                       escape::rust_string(error::NO_COLUMN));
+  Ok(rust::from_postgres(schema, &column))
+}
+
+/// Rewrite the named parameters of a query into positional parameters.
+/// Return the rewritten query and the names of the parameters in order of
+/// position, which are absent if the query has positional parameters.
+fn rewrite(query: &str) -> Result<(String, Vec<String>)> {
+  let mut names = vec![];
+  let mut positional = false;
+  let query = lexer::rewrite(query, |parameter| match *parameter {
+    Parameter::Positional(index) => {
+      positional = true;
+      format!("${}", index)
+    },
+    Parameter::Named(ref name) => {
+      let index = match names.iter().position(|other| other == name) {
+        Some(index) => index,
+        None => {
+          names.push(name.clone());
+          names.len() - 1
+        },
+      };
+      format!("${}", index + 1)
+    },
+  });
+  if positional && !names.is_empty() {
+    return Err(Error::Unsupported(error::MIXED_PARAMETERS.to_string()));
+  }
+  Ok((query, names))
+}
+
+/// Prepare a query, given with positional parameters as `rewritten`, and
+/// describe its parameters and result columns.
+fn describe(connection: &Connection, query: &str, rewritten: &str)
+  -> Result<Description> {
+  let statement = connection.prepare(rewritten)?;
+  let parameters = statement.param_types().iter()
+    .map(|type_| Ok((TypeReference::new(type_), type_to_schema(type_)?)))
+    .collect::<Result<_>>()?;
+  let non_null = nullability::non_null_columns(connection, rewritten,
+                                               &statement)?;
+  let columns = statement.columns().iter().zip(non_null)
+    .map(|(column, non_null)| {
+      Ok((column.name().to_string(), column_schema(column, non_null)?))
+    })
+    .collect::<Result<_>>()?;
+  Ok(Description{
+    query: query.to_string(),
+    parameters: parameters,
+    columns: columns,
+    types: ecmascript::Types::new(&statement),
+  })
+}
+
+fn input_schema(shape: InputShape, parameters: &[(TypeReference, Schema)],
+                names: &[String]) -> Result<Schema> {
+  match shape {
+    InputShape::Row if !names.is_empty() =>
+      Ok(Schema::Record(
+        names.iter().zip(parameters)
+        .map(|(name, &(_, ref schema))| (name.clone(), schema.clone()))
+        .collect()
+      )),
+    InputShape::Row =>
+      Ok(Schema::AllOf(
+        parameters.iter().map(|&(_, ref schema)| schema.clone()).collect()
+      )),
+    InputShape::Scalar =>
+      if parameters.len() != 1 {
+        Err(Error::ShapeMismatch(error::SCALAR_INPUT.to_string()))
+      } else {
+        Ok(parameters[0].1.clone())
+      },
+  }
+}
+
+fn output_schema(shape: OutputShape, columns: &[(String, Schema)])
+  -> Result<Schema> {
+  match shape {
+    OutputShape::Table =>
+      row_schema(columns).map(|row| Schema::ArrayOf(Box::new(row))),
+    OutputShape::Row => row_schema(columns),
+    OutputShape::Scalar =>
+      if columns.len() != 1 {
+        Err(Error::ShapeMismatch(error::SCALAR.to_string()))
+      } else {
+        Ok(columns[0].1.clone())
+      },
+  }
+}
+
+fn row_schema(columns: &[(String, Schema)]) -> Result<Schema> {
+  let mut fields: Vec<(String, Schema)> = vec![];
+  for &(ref name, ref schema) in columns {
+    if fields.iter().any(|&(ref other, _)| other == name) {
+      return Err(Error::ShapeMismatch(error::DUPLICATE_COLUMN.to_string()));
+    }
+    fields.push((name.clone(), schema.clone()));
+  }
+  Ok(Schema::Record(fields))
+}
+
+fn column_schema(column: &Column, non_null: bool) -> Result<Schema> {
+  let schema = type_to_schema(column.type_())?;
+  if non_null {
+    Ok(schema)
+  } else {
+    Ok(Schema::Optional(Box::new(schema)))
+  }
+}
+
+/// Return the schema that corresponds to a PostgreSQL type. The elements of
+/// arrays and the fields of composite types may be null. Enumerations,
+/// domains and composite types are named after the PostgreSQL type.
+pub fn type_to_schema(type_: &Type) -> Result<Schema> {
+  match *type_.kind() {
+    Kind::Array(ref element) => {
+      let element = type_to_schema(element)?;
+      return Ok(Schema::ArrayOf(Box::new(Schema::Optional(Box::new(element)))));
+    },
+    Kind::Enum(ref labels) =>
+      return Ok(named(type_, Schema::Enumeration(labels.clone()))),
+    Kind::Domain(ref base) =>
+      return Ok(named(type_, type_to_schema(base)?)),
+    Kind::Composite(ref fields) => {
+      let mut record = vec![];
+      for field in fields {
+        let schema = type_to_schema(field.type_())?;
+        record.push((field.name().to_string(),
+                     Schema::Optional(Box::new(schema))));
+      }
+      return Ok(named(type_, Schema::Record(record)));
+    },
+    _ => (),
+  }
+  match *type_ {
+    Type::Bool => Ok(Schema::Boolean),
+    Type::Int2 => Ok(Schema::SignedInteger(i16::MIN as i32, i16::MAX as i32)),
+    Type::Int4 => Ok(Schema::SignedInteger(i32::MIN, i32::MAX)),
+    Type::Int8 => Ok(Schema::SignedInteger64(i64::MIN, i64::MAX)),
+    Type::Oid => Ok(Schema::SignedInteger64(0, u32::MAX as i64)),
+    Type::Numeric => Ok(Schema::Decimal),
+    Type::Float4 => Ok(Schema::SinglePrecision),
+    Type::Float8 => Ok(Schema::DoublePrecision),
+    Type::Bytea => Ok(Schema::ByteString),
+    Type::Text | Type::Varchar | Type::Bpchar => Ok(Schema::String),
+    Type::Uuid => Ok(Schema::Uuid),
+    Type::Date => Ok(Schema::Date),
+    Type::Timestamp => Ok(Schema::Timestamp),
+    Type::Timestamptz => Ok(Schema::TimestampWithTimeZone),
+    Type::Interval => Ok(Schema::Interval),
+    Type::Json | Type::Jsonb => Ok(Schema::Json),
+    _ => Err(Error::UnsupportedType(type_.name().to_string())),
+  }
+}
+
+/// Name the schema of a user-defined type. The name is qualified with the
+/// PostgreSQL schema of the type, such as `billing.status`, so that types of
+/// the same name in different schemas stay apart, except for types in the
+/// `public` schema and in temporary schemas, whose names vary by session.
+fn named(type_: &Type, schema: Schema) -> Schema {
+  let name = match type_.schema() {
+    "public" => type_.name().to_string(),
+    namespace if namespace.starts_with("pg_temp") => type_.name().to_string(),
+    namespace => format!("{}.{}", namespace, type_.name()),
+  };
+  Schema::Named(name, Box::new(schema))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use postgres::TlsMode;
+  use serde_json;
+  use source::call::{Asynchrony, ECMAScriptModule, TypeScriptOutput};
+  use std::collections::BTreeMap;
+  use std::env;
+
+  fn with_connection<F>(body: F) where F: Fn(&Connection) {
+    match env::var("TYPEFUNNEL_TEST_POSTGRESQL") {
+      Ok(connection_str) =>
+        match Connection::connect(connection_str, TlsMode::None) {
+          Ok(connection) => body(&connection),
+          _ => println!("Skipping test: could not connect to PostgreSQL"),
+        },
+      _ =>
+        println!("Skipping test: no PostgreSQL connection information given"),
+    }
+  }
+
+  #[test]
+  fn test_scalar_text() {
+    with_connection(|connection| {
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: "SELECT '' :: text".to_string(),
+        input_shape: InputShape::Row,
+        output_shape: OutputShape::Scalar,
+      };
+      let schema = source.schema().map_err(|e| e.to_string());
+      let expected = Schema::Optional(Box::new(Schema::String));
+      assert_eq!(schema, Ok((Rc::new(Schema::AllOf(vec![])), Rc::new(expected))));
+    });
+  }
+
+  #[test]
+  fn test_row_text() {
+    with_connection(|connection| {
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: "SELECT 0 :: int, '' :: text".to_string(),
+        input_shape: InputShape::Row,
+        output_shape: OutputShape::Row,
+      };
+      let schema = source.schema().map_err(|e| e.to_string());
+      let expected = Schema::Record(vec![
+        ("int4".to_string(), Schema::Optional(Box::new(
+          Schema::SignedInteger(i32::MIN, i32::MAX),
+        ))),
+        ("text".to_string(), Schema::Optional(Box::new(Schema::String))),
+      ]);
+      assert_eq!(schema, Ok((Rc::new(Schema::AllOf(vec![])), Rc::new(expected))));
+    });
+  }
+
+  #[test]
+  fn test_row_duplicate_column() {
+    with_connection(|connection| {
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: "SELECT 0 AS a, 1 AS a".to_string(),
+        input_shape: InputShape::Row,
+        output_shape: OutputShape::Row,
+      };
+      let schema = source.schema().map_err(|e| e.to_string());
+      assert_eq!(schema, Err(error::DUPLICATE_COLUMN.to_string()));
+    });
+  }
+
+  #[test]
+  fn test_table() {
+    with_connection(|connection| {
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: "SELECT n :: int FROM generate_series(1, 3) AS n".to_string(),
+        input_shape: InputShape::Row,
+        output_shape: OutputShape::Table,
+      };
+      let schema = source.schema().map_err(|e| e.to_string());
+      let expected = Schema::ArrayOf(Box::new(Schema::Record(vec![
+        ("n".to_string(), Schema::Optional(Box::new(
+          Schema::SignedInteger(i32::MIN, i32::MAX),
+        ))),
+      ])));
+      assert_eq!(schema, Ok((Rc::new(Schema::AllOf(vec![])), Rc::new(expected))));
+    });
+  }
+
+  #[test]
+  fn test_not_null_columns() {
+    with_connection(|connection| {
+      connection.batch_execute(
+        "CREATE TEMPORARY TABLE t (a int NOT NULL, b int)",
+      ).unwrap();
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: "SELECT a, b FROM t WHERE a = $1".to_string(),
+        input_shape: InputShape::Row,
+        output_shape: OutputShape::Row,
+      };
+      let schema = source.schema().map_err(|e| e.to_string());
+      let int4 = Schema::SignedInteger(i32::MIN, i32::MAX);
+      let expected = Schema::Record(vec![
+        ("a".to_string(), int4.clone()),
+        ("b".to_string(), Schema::Optional(Box::new(int4.clone()))),
+      ]);
+      assert_eq!(schema, Ok((Rc::new(Schema::AllOf(vec![int4])), Rc::new(expected))));
+    });
+  }
+
+  #[test]
+  fn test_not_null_columns_outer_join() {
+    with_connection(|connection| {
+      connection.batch_execute(
+        "CREATE TEMPORARY TABLE t (a int NOT NULL)",
+      ).unwrap();
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: "SELECT u.a FROM t LEFT JOIN t AS u ON false".to_string(),
+        input_shape: InputShape::Row,
+        output_shape: OutputShape::Scalar,
+      };
+      let schema = source.schema().map_err(|e| e.to_string());
+      let expected = Schema::Optional(Box::new(
+        Schema::SignedInteger(i32::MIN, i32::MAX),
+      ));
+      assert_eq!(schema, Ok((Rc::new(Schema::AllOf(vec![])), Rc::new(expected))));
+    });
+  }
+
+  #[test]
+  fn test_not_null_columns_union() {
+    with_connection(|connection| {
+      connection.batch_execute(
+        "CREATE TEMPORARY TABLE t (a int NOT NULL)",
+      ).unwrap();
+      for query in &["SELECT a FROM t UNION ALL SELECT NULL",
+                     "SELECT a FROM (SELECT a FROM t EXCEPT SELECT 1) AS u"] {
+        let source = Query{
+          connection: Some(connection),
+          cache: None,
+          query: query.to_string(),
+          input_shape: InputShape::Row,
+          output_shape: OutputShape::Scalar,
+        };
+        let schema = source.schema().map_err(|e| e.to_string());
+        let expected = Schema::Optional(Box::new(
+          Schema::SignedInteger(i32::MIN, i32::MAX),
+        ));
+        assert_eq!(schema, Ok((Rc::new(Schema::AllOf(vec![])), Rc::new(expected))));
+      }
+    });
+  }
+
+  #[test]
+  fn test_scalar_types() {
+    with_connection(|connection| {
+      let int4 = Schema::SignedInteger(i32::MIN, i32::MAX);
+      let cases = vec![
+        ("true", Schema::Boolean),
+        ("0 :: int2", Schema::SignedInteger(-32768, 32767)),
+        ("0 :: int8", Schema::SignedInteger64(i64::MIN, i64::MAX)),
+        ("0 :: oid", Schema::SignedInteger64(0, 4294967295)),
+        ("0 :: numeric", Schema::Decimal),
+        ("'' :: varchar", Schema::String),
+        ("'' :: char(1)", Schema::String),
+        ("'' :: bytea", Schema::ByteString),
+        ("gen_random_uuid()", Schema::Uuid),
+        ("now() :: date", Schema::Date),
+        ("now() :: timestamp", Schema::Timestamp),
+        ("now()", Schema::TimestampWithTimeZone),
+        ("'1 day' :: interval", Schema::Interval),
+        ("'{}' :: json", Schema::Json),
+        ("'{}' :: jsonb", Schema::Json),
+        ("ARRAY[0]", Schema::ArrayOf(Box::new(Schema::Optional(Box::new(int4))))),
+      ];
+      for (expression, expected) in cases {
+        let source = Query{
+          connection: Some(connection),
+        cache: None,
+          query: format!("SELECT {}", expression),
+          input_shape: InputShape::Row,
+          output_shape: OutputShape::Scalar,
+        };
+        let schema = source.schema().map_err(|e| e.to_string());
+        let expected = Schema::Optional(Box::new(expected));
+        assert_eq!(schema, Ok((Rc::new(Schema::AllOf(vec![])), Rc::new(expected))));
+      }
+    });
+  }
+
+  #[test]
+  fn test_user_defined_types() {
+    with_connection(|connection| {
+      connection.batch_execute(concat!(
+        "CREATE TYPE pg_temp.mood AS ENUM ('sad', 'happy');",
+        "CREATE DOMAIN pg_temp.email AS text CHECK (VALUE LIKE '%@%');",
+        "CREATE TYPE pg_temp.person AS (email pg_temp.email, mood pg_temp.mood);",
+      )).unwrap();
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: "SELECT $1 :: pg_temp.person".to_string(),
+        input_shape: InputShape::Row,
+        output_shape: OutputShape::Scalar,
+      };
+      let schema = source.schema().map_err(|e| e.to_string());
+      let person = Schema::Named("person".to_string(), Box::new(Schema::Record(vec![
+        ("email".to_string(), Schema::Optional(Box::new(Schema::Named(
+          "email".to_string(),
+          Box::new(Schema::String),
+        )))),
+        ("mood".to_string(), Schema::Optional(Box::new(Schema::Named(
+          "mood".to_string(),
+          Box::new(Schema::Enumeration(vec!["sad".to_string(), "happy".to_string()])),
+        )))),
+      ])));
+      let input = Schema::AllOf(vec![person.clone()]);
+      let output = Schema::Optional(Box::new(person));
+      assert_eq!(schema, Ok((Rc::new(input), Rc::new(output))));
+    });
+  }
+
+  #[test]
+  fn test_type_references() {
+    with_connection(|connection| {
+      connection.batch_execute(concat!(
+        "CREATE DOMAIN pg_temp.address AS text;",
+        "CREATE TYPE pg_temp.contact AS (address pg_temp.address, ids int4[]);",
+      )).unwrap();
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: "SELECT $1 :: pg_temp.contact, $2 :: int4".to_string(),
+        input_shape: InputShape::Row,
+        output_shape: OutputShape::Row,
+      };
+      let (description, _, _) = source.describe().unwrap();
+      assert_eq!(description.parameters.iter()
+                 .map(|&(ref type_, _)| type_.clone())
+                 .collect::<Vec<_>>(),
+                 vec![TypeReference::Name("\"pg_temp\".\"contact\"".to_string()),
+                      TypeReference::Oid(23)]);
+
+      let mut output = vec![];
+      source.ecmascript_call(&mut output).unwrap();
+      let output = String::from_utf8(output).unwrap();
+      assert!(output.starts_with(concat!(
+        "(function() {\n",
+        "var names = ['\"pg_temp\".\"address\"', '\"pg_temp\".\"contact\"', ];\n")));
+      assert!(output.contains("arrays[1007] = 23;\n"));
+      assert!(output.contains(concat!(
+        "composites[oids['\"pg_temp\".\"contact\"']] = ",
+        "[['address', oids['\"pg_temp\".\"address\"']], ['ids', 1007], ];\n")));
+      assert!(output.contains(concat!(
+        "var parameters = [oids['\"pg_temp\".\"contact\"'], 23, ];\n")));
+      assert!(output.ends_with("})()"));
+    });
+  }
+
+  #[test]
+  fn test_qualified_names() {
+    with_connection(|connection| {
+      let transaction = connection.transaction().unwrap();
+      transaction.batch_execute(concat!(
+        "CREATE SCHEMA typefunnel_a;",
+        "CREATE SCHEMA typefunnel_b;",
+        "CREATE TYPE typefunnel_a.status AS ENUM ('on');",
+        "CREATE TYPE typefunnel_b.status AS ENUM ('off');",
+      )).unwrap();
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: concat!("SELECT 'on' :: typefunnel_a.status AS a, ",
+                       "'off' :: typefunnel_b.status AS b").to_string(),
+        input_shape: InputShape::Row,
+        output_shape: OutputShape::Row,
+      };
+      let schema = source.schema().map_err(|e| e.to_string());
+      let status = |name: &str, label: &str| Schema::Optional(Box::new(
+        Schema::Named(name.to_string(), Box::new(
+          Schema::Enumeration(vec![label.to_string()])))));
+      let output = Schema::Record(vec![
+        ("a".to_string(), status("typefunnel_a.status", "on")),
+        ("b".to_string(), status("typefunnel_b.status", "off")),
+      ]);
+      assert_eq!(schema, Ok((Rc::new(Schema::AllOf(vec![])), Rc::new(output))));
+    });
+  }
+
+  #[test]
+  fn test_scalar_input() {
+    with_connection(|connection| {
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: "SELECT $1 :: text".to_string(),
+        input_shape: InputShape::Scalar,
+        output_shape: OutputShape::Scalar,
+      };
+      let schema = source.schema().map_err(|e| e.to_string());
+      let expected = Schema::Optional(Box::new(Schema::String));
+      assert_eq!(schema, Ok((Rc::new(Schema::String), Rc::new(expected))));
+    });
+  }
+
+  #[test]
+  fn test_scalar_input_without_parameters() {
+    with_connection(|connection| {
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: "SELECT 1".to_string(),
+        input_shape: InputShape::Scalar,
+        output_shape: OutputShape::Scalar,
+      };
+      let schema = source.schema().map_err(|e| e.to_string());
+      assert_eq!(schema, Err(error::SCALAR_INPUT.to_string()));
+    });
+  }
+
+  #[test]
+  fn test_named_parameters() {
+    with_connection(|connection| {
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: "SELECT :a :: int + @b :: int4 - :a".to_string(),
+        input_shape: InputShape::Row,
+        output_shape: OutputShape::Scalar,
+      };
+      let schema = source.schema().map_err(|e| e.to_string());
+      let int4 = Schema::SignedInteger(i32::MIN, i32::MAX);
+      let input = Schema::Record(vec![
+        ("a".to_string(), int4.clone()),
+        ("b".to_string(), int4.clone()),
+      ]);
+      let output = Schema::Optional(Box::new(int4));
+      assert_eq!(schema, Ok((Rc::new(input), Rc::new(output))));
+    });
+  }
+
+  #[test]
+  fn test_mixed_parameters() {
+    with_connection(|connection| {
+      let source = Query{
+        connection: Some(connection),
+        cache: None,
+        query: "SELECT $1 :: int + :b :: int".to_string(),
+        input_shape: InputShape::Row,
+        output_shape: OutputShape::Scalar,
+      };
+      let schema = source.schema().map_err(|e| e.to_string());
+      assert_eq!(schema, Err(error::MIXED_PARAMETERS.to_string()));
+    });
+  }
+
+  #[test]
+  fn test_no_database() {
+    let source = Query{
+      connection: None,
+      cache: None,
+      query: "SELECT 1".to_string(),
+      input_shape: InputShape::Row,
+      output_shape: OutputShape::Scalar,
+    };
+    match source.schema() {
+      Err(Error::NoDatabase(message)) =>
+        assert_eq!(message, error::NO_DATABASE),
+      result => panic!("Unexpected result {:?}", result),
+    }
+  }
+
+  #[test]
+  fn test_module_imports() {
+    let cache = Cache::new();
+    let int4 = Schema::SignedInteger(i32::MIN, i32::MAX);
+    cache.insert(Description{
+      query: "SELECT :a :: int AS b".to_string(),
+      parameters: vec![(TypeReference::Oid(23), int4.clone())],
+      columns: vec![("b".to_string(), Schema::Optional(Box::new(int4)))],
+      types: serde_json::from_str(
+        r#"{"arrays": [], "domains": [], "composites": []}"#).unwrap(),
+    });
+    let query = Query{
+      connection: None,
+      cache: Some(&cache),
+      query: "SELECT :a :: int AS b".to_string(),
+      input_shape: InputShape::Row,
+      output_shape: OutputShape::Row,
+    };
+    let mut calls = BTreeMap::new();
+    calls.insert("select".to_string(),
+                 (&query as &HasSchema, &query as &ECMAScript));
+    let module = ECMAScriptModule{calls, asynchrony: Asynchrony::Promises};
+
+    let mut ecmascript = vec![];
+    module.ecmascript(&mut ecmascript).unwrap();
+    let ecmascript = String::from_utf8(ecmascript).unwrap();
+    assert!(ecmascript.starts_with("var $pg = require('pg');\n"));
+    // OIDs are represented by strings, like other 64-bit integers.
+    assert!(ecmascript.contains(
+      "if ([26, 1082, 1114, 1184, 1186].indexOf(oid) !== -1) {\nreturn String;\n"));
+
+    let mut typescript = vec![];
+    module.typescript(&mut typescript, TypeScriptOutput::Module).unwrap();
+    let typescript = String::from_utf8(typescript).unwrap();
+    assert!(typescript.starts_with(
+      "// @ts-ignore\nimport $pg from 'pg';\nexport const "));
+    assert!(!typescript.contains("@ts-nocheck"));
+    assert!(!typescript.contains("require("));
+
+    let mut foreign = vec![];
+    module.purescript_foreign(&mut foreign).unwrap();
+    let foreign = String::from_utf8(foreign).unwrap();
+    assert!(foreign.starts_with("import $pg from 'pg';\n"));
+    assert!(!foreign.contains("require("));
+  }
+}
@@ -0,0 +1,234 @@
+//! Inference of the nullability of query result columns.
+//!
+//! PostgreSQL does not report whether a result column can be null. It does
+//! track, for each column of a query, the table column it was taken from, if
+//! any. This module recovers that information by creating a temporary view
+//! for the query and inspecting the view's rewrite rule, and then looks up
+//! the `NOT NULL` constraints of the originating table columns.
+
+use error::Result;
+use postgres::Connection;
+use postgres::stmt::Statement;
+use postgres::types::Oid;
+use postgresql::lexer::{self, Parameter};
+
+/// Return for each result column of a prepared query with positional
+/// parameters whether it is known to never be null. Columns whose
+/// nullability cannot be inferred are assumed to be nullable.
+pub fn non_null_columns(connection: &Connection, query: &str,
+                        statement: &Statement) -> Result<Vec<bool>> {
+  let unknown = vec![false; statement.columns().len()];
+  if statement.columns().is_empty() || !connection.is_active() {
+    return Ok(unknown);
+  }
+
+  // Views cannot have parameters, so replace them by typed nulls.
+  let param_types = statement.param_types();
+  let view_query = lexer::rewrite(query, |parameter| {
+    let type_ = match *parameter {
+      Parameter::Positional(index) => param_types.get(index.wrapping_sub(1)),
+      Parameter::Named(_) => None,
+    };
+    match type_ {
+      Some(type_) => format!("(NULL :: {}.{})",
+                             quote_identifier(type_.schema()),
+                             quote_identifier(type_.name())),
+      None => "NULL".to_string(),
+    }
+  });
+  let view_query = view_query.trim_end_matches(|c: char| {
+    c == ';' || c.is_whitespace()
+  });
+
+  // The transaction is never committed, so the view does not outlive it.
+  let transaction = connection.transaction()?;
+  let create_view = format!("CREATE TEMPORARY VIEW typefunnel_nullability AS {}",
+                            view_query);
+  if transaction.batch_execute(&create_view).is_err() {
+    return Ok(unknown);
+  }
+  let rows = transaction.query(
+    concat!("SELECT ev_action :: text FROM pg_catalog.pg_rewrite ",
+            "WHERE ev_class = 'pg_temp.typefunnel_nullability' :: regclass"),
+    &[],
+  )?;
+  let tree: String = match rows.iter().next() {
+    Some(row) => row.get(0),
+    None => return Ok(unknown),
+  };
+  let origins = match target_origins(&tree) {
+    Some(ref origins) if origins.len() == unknown.len() => origins.clone(),
+    _ => return Ok(unknown),
+  };
+
+  let attnotnull = transaction.prepare(
+    concat!("SELECT attnotnull FROM pg_catalog.pg_attribute ",
+            "WHERE attrelid = $1 AND attnum = $2"),
+  )?;
+  let mut non_null = vec![];
+  for origin in origins {
+    non_null.push(match origin {
+      Some((table, column)) =>
+        attnotnull.query(&[&table, &column])?
+        .iter().next()
+        .map_or(false, |row| row.get(0)),
+      None => false,
+    });
+  }
+  Ok(non_null)
+}
+
+/// Quote an identifier for inclusion in SQL.
+pub fn quote_identifier(identifier: &str) -> String {
+  format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+  Open,
+  Close,
+  BraceOpen,
+  BraceClose,
+  Atom(String),
+}
+
+/// Split a serialized PostgreSQL node tree into tokens.
+fn tokenize(tree: &str) -> Vec<Token> {
+  let mut tokens = vec![];
+  let mut atom = String::new();
+  let mut chars = tree.chars();
+  while let Some(c) = chars.next() {
+    let token = match c {
+      '\\' => {
+        atom.extend(chars.next());
+        continue;
+      },
+      '(' => Token::Open,
+      ')' => Token::Close,
+      '{' => Token::BraceOpen,
+      '}' => Token::BraceClose,
+      c if c.is_whitespace() => {
+        if !atom.is_empty() {
+          tokens.push(Token::Atom(atom.split_off(0)));
+        }
+        continue;
+      },
+      c => {
+        atom.push(c);
+        continue;
+      },
+    };
+    if !atom.is_empty() {
+      tokens.push(Token::Atom(atom.split_off(0)));
+    }
+    tokens.push(token);
+  }
+  if !atom.is_empty() {
+    tokens.push(Token::Atom(atom));
+  }
+  tokens
+}
+
+/// Return for each result column of the query in a serialized rewrite rule
+/// the table and column it was taken from, if any. Return `None` if the
+/// query contains outer joins, which may introduce nulls into any column, or
+/// set operations such as `UNION`, for which the origins are those of the
+/// leftmost query only.
+fn target_origins(tree: &str) -> Option<Vec<Option<(Oid, i16)>>> {
+  let tokens = tokenize(tree);
+  let atom = |index: usize| match tokens.get(index) {
+    Some(&Token::Atom(ref atom)) => Some(atom.as_str()),
+    _ => None,
+  };
+
+  for index in 0..tokens.len() {
+    if atom(index) == Some(":jointype") && atom(index + 1) != Some("0") {
+      return None;
+    }
+    if atom(index) == Some(":setOperations") && atom(index + 1) != Some("<>") {
+      return None;
+    }
+  }
+
+  // The rule action is a list holding a single query node. Find the target
+  // list of that query node, skipping those of any nested queries.
+  let mut depth = 0;
+  let mut start = None;
+  for (index, token) in tokens.iter().enumerate() {
+    match *token {
+      Token::BraceOpen => depth += 1,
+      Token::BraceClose => depth -= 1,
+      Token::Atom(ref atom) if depth == 1 && atom == ":targetList" => {
+        start = Some(index + 1);
+        break;
+      },
+      _ => (),
+    }
+  }
+  let start = start?;
+  if atom(start) == Some("<>") {
+    return Some(vec![]);
+  }
+  if tokens.get(start) != Some(&Token::Open) {
+    return None;
+  }
+
+  // Each target entry is a node directly inside the target list.
+  let mut origins = vec![];
+  let (mut parens, mut braces) = (0, 0);
+  let (mut table, mut column, mut junk) = (0, 0, false);
+  for index in start..tokens.len() {
+    match tokens[index] {
+      Token::Open => parens += 1,
+      Token::Close => {
+        parens -= 1;
+        if parens == 0 {
+          return Some(origins);
+        }
+      },
+      Token::BraceOpen => {
+        braces += 1;
+        if braces == 1 {
+          table = 0;
+          column = 0;
+          junk = false;
+        }
+      },
+      Token::BraceClose => {
+        braces -= 1;
+        if braces == 0 && !junk {
+          origins.push(if table == 0 { None } else { Some((table, column)) });
+        }
+      },
+      Token::Atom(ref key) if braces == 1 => {
+        let value = atom(index + 1);
+        match key.as_str() {
+          ":resorigtbl" => table = value?.parse().ok()?,
+          ":resorigcol" => column = value?.parse().ok()?,
+          ":resjunk" => junk = value == Some("true"),
+          _ => (),
+        }
+      },
+      Token::Atom(_) => (),
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_target_origins() {
+    let tree = concat!(
+      "({QUERY :commandType 1 :setOperations <> :targetList ",
+      "({TARGETENTRY :resno 1 :resorigtbl 16384 :resorigcol 2 :resjunk false} ",
+      "{TARGETENTRY :resno 2 :resorigtbl 0 :resorigcol 0 :resjunk false} ",
+      "{TARGETENTRY :resno 3 :resorigtbl 16384 :resorigcol 1 :resjunk true})})");
+    assert_eq!(target_origins(tree), Some(vec![Some((16384, 2)), None]));
+    let union = tree.replace(":setOperations <>",
+                             ":setOperations {SETOPERATIONSTMT :op 1 :all true}");
+    assert_eq!(target_origins(&union), None);
+  }
+}
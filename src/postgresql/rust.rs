@@ -0,0 +1,512 @@
+//! Generation of Rust code that converts between the Rust representation of
+//! PostgreSQL values in the `postgres` crate and the types given by
+//! `source::call::rust_type`.
+//!
+//! 64-bit integers and byte strings are wrapped in `Int64` and `Bytes`, and
+//! other values are passed as they are, except for those that the `postgres`
+//! crate has no conversions for. 64-bit integers are converted by
+//! `PostgresInt64`, which also takes values of the type `oid`. Results of the types represented by strings,
+//! such as decimal numbers and temporal values, are received as
+//! `PostgresText`, which formats the binary representation of PostgreSQL as
+//! text, and parameters of these types are passed as text and cast in the
+//! query. JSON values are wrapped in `PostgresJson`, and the types generated
+//! for enumerations in `PostgresEnum`. Domains and composite types are not
+//! supported, since the `postgres` crate does not convert domains as their
+//! base types.
+
+use Schema;
+use error::{Error, Result};
+use escape;
+use source::call::rust_integer_type;
+
+mod error {
+  pub static UNSUPPORTED: &'static str =
+    "Rust calls cannot yet be generated for queries with domains or composite types.";
+}
+
+/// The declaration of `PostgresText` in generated Rust modules.
+pub const TEXT: &'static str = r#"
+/// A value of a type that is represented by a string, such as a decimal
+/// number or a temporal value, in the textual representation that PostgreSQL
+/// outputs by default. Values with a time zone are in UTC.
+#[derive(Debug)]
+pub struct PostgresText(pub String);
+
+impl postgres::types::FromSql for PostgresText {
+    fn from_sql(type_: &postgres::types::Type, raw: &[u8]) -> Result<Self, Error> {
+        use postgres::types::Type;
+        let text = match *type_ {
+            Type::Numeric => PostgresText::numeric(raw)?,
+            Type::Uuid => {
+                let hex = raw.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+                if hex.len() != 32 {
+                    return Err("invalid uuid".into());
+                }
+                format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+            },
+            Type::Date => match PostgresText::integer(raw, 0, 4)? {
+                days if days == i64::from(i32::MAX) => "infinity".to_string(),
+                days if days == i64::from(i32::MIN) => "-infinity".to_string(),
+                days => {
+                    let (date, era) = PostgresText::date(days);
+                    format!("{}{}", date, era)
+                },
+            },
+            Type::Timestamp | Type::Timestamptz => match PostgresText::integer(raw, 0, 8)? {
+                i64::MAX => "infinity".to_string(),
+                i64::MIN => "-infinity".to_string(),
+                time => {
+                    let (date, era) = PostgresText::date(time.div_euclid(86_400_000_000));
+                    let zone = if *type_ == Type::Timestamptz { "+00" } else { "" };
+                    format!("{} {}{}{}", date, PostgresText::time(time.rem_euclid(86_400_000_000)),
+                            zone, era)
+                },
+            },
+            Type::Interval => PostgresText::interval(raw)?,
+            _ => return Err(format!("unexpected type {}", type_.name()).into()),
+        };
+        Ok(PostgresText(text))
+    }
+
+    fn accepts(type_: &postgres::types::Type) -> bool {
+        use postgres::types::Type;
+        match *type_ {
+            Type::Numeric | Type::Uuid | Type::Date | Type::Timestamp | Type::Timestamptz
+            | Type::Interval => true,
+            _ => false,
+        }
+    }
+}
+
+impl PostgresText {
+    /// Read a big-endian signed integer of `length` bytes at `start`.
+    fn integer(raw: &[u8], start: usize, length: usize) -> Result<i64, Error> {
+        let bytes = raw.get(start..start + length).ok_or("unexpected end of value")?;
+        let initial = if bytes[0] & 0x80 == 0 { 0 } else { -1 };
+        Ok(bytes.iter().fold(initial, |value, &byte| value << 8 | i64::from(byte)))
+    }
+
+    fn numeric(raw: &[u8]) -> Result<String, Error> {
+        let count = PostgresText::integer(raw, 0, 2)?;
+        let weight = PostgresText::integer(raw, 2, 2)?;
+        let sign = PostgresText::integer(raw, 4, 2)? & 0xffff;
+        let scale = PostgresText::integer(raw, 6, 2)? & 0xffff;
+        let digits = (0..count as usize)
+            .map(|index| PostgresText::integer(raw, 8 + 2 * index, 2))
+            .collect::<Result<Vec<_>, _>>()?;
+        let digit = |index: i64| if index >= 0 && index < count { digits[index as usize] } else { 0 };
+        let mut text = match sign {
+            0xc000 => return Ok("NaN".to_string()),
+            0xd000 => return Ok("Infinity".to_string()),
+            0xf000 => return Ok("-Infinity".to_string()),
+            0x4000 => "-".to_string(),
+            _ => String::new(),
+        };
+        if weight < 0 {
+            text.push('0');
+        }
+        for index in 0..weight + 1 {
+            if index == 0 {
+                text += &digit(index).to_string();
+            } else {
+                text += &format!("{:04}", digit(index));
+            }
+        }
+        if scale > 0 {
+            let mut fraction = String::new();
+            let mut index = weight + 1;
+            while (fraction.len() as i64) < scale {
+                fraction += &format!("{:04}", digit(index));
+                index += 1;
+            }
+            fraction.truncate(scale as usize);
+            text = text + "." + &fraction;
+        }
+        Ok(text)
+    }
+
+    /// Format a number of days since 2000-01-01, and return the era to append
+    /// to the value.
+    fn date(days: i64) -> (String, &'static str) {
+        // The conversion from Julian day numbers of PostgreSQL.
+        let mut julian = days + 2_451_545 + 32_044;
+        let mut quad = julian / 146_097;
+        let extra = (julian - quad * 146_097) * 4 + 3;
+        julian += 60 + quad * 3 + extra / 146_097;
+        quad = julian / 1461;
+        julian -= quad * 1461;
+        let mut year = julian * 4 / 1461;
+        julian = if year != 0 { (julian + 305) % 365 } else { (julian + 306) % 366 } + 123;
+        year += quad * 4 - 4800;
+        quad = julian * 2141 / 65536;
+        let day = julian - 7834 * quad / 256;
+        let month = (quad + 10) % 12 + 1;
+        if year > 0 {
+            (format!("{:04}-{:02}-{:02}", year, month, day), "")
+        } else {
+            (format!("{:04}-{:02}-{:02}", 1 - year, month, day), " BC")
+        }
+    }
+
+    /// Format a nonnegative number of microseconds as hours, minutes and
+    /// seconds.
+    fn time(time: i64) -> String {
+        let mut text = format!("{:02}:{:02}:{:02}", time / 3_600_000_000,
+                               time / 60_000_000 % 60, time / 1_000_000 % 60);
+        if time % 1_000_000 != 0 {
+            text += format!(".{:06}", time % 1_000_000).trim_end_matches('0');
+        }
+        text
+    }
+
+    fn interval(raw: &[u8]) -> Result<String, Error> {
+        let time = PostgresText::integer(raw, 0, 8)?;
+        let days = PostgresText::integer(raw, 8, 4)?;
+        let months = PostgresText::integer(raw, 12, 4)?;
+        let mut text = String::new();
+        let mut negative = false;
+        for &(value, unit) in &[(months / 12, "year"), (months % 12, "mon"), (days, "day")] {
+            if value != 0 {
+                let separator = if text.is_empty() { "" } else { " " };
+                let sign = if negative && value > 0 { "+" } else { "" };
+                let plural = if value == 1 { "" } else { "s" };
+                text += &format!("{}{}{} {}{}", separator, sign, value, unit, plural);
+                negative = value < 0;
+            }
+        }
+        if text.is_empty() || time != 0 {
+            let separator = if text.is_empty() { "" } else { " " };
+            let sign = if time < 0 { "-" } else if negative { "+" } else { "" };
+            let time = PostgresText::time(time.checked_abs().ok_or("interval out of range")?);
+            text += &format!("{}{}{}", separator, sign, time);
+        }
+        Ok(text)
+    }
+}
+"#;
+
+/// The declaration of `PostgresInt64` in generated Rust modules.
+pub const INT64: &'static str = r#"
+/// A 64-bit integer of the type `int8`, or of the unsigned 32-bit type `oid`.
+#[derive(Debug)]
+pub struct PostgresInt64(pub i64);
+
+impl postgres::types::FromSql for PostgresInt64 {
+    fn from_sql(type_: &postgres::types::Type, raw: &[u8]) -> Result<Self, Error> {
+        use postgres::types::Type;
+        match *type_ {
+            Type::Oid => Ok(PostgresInt64(i64::from(u32::from_sql(type_, raw)?))),
+            _ => Ok(PostgresInt64(i64::from_sql(type_, raw)?)),
+        }
+    }
+
+    fn accepts(type_: &postgres::types::Type) -> bool {
+        match *type_ {
+            postgres::types::Type::Int8 | postgres::types::Type::Oid => true,
+            _ => false,
+        }
+    }
+}
+
+impl postgres::types::ToSql for PostgresInt64 {
+    fn to_sql(&self, type_: &postgres::types::Type, out: &mut Vec<u8>)
+        -> Result<postgres::types::IsNull, Error> {
+        use postgres::types::Type;
+        match *type_ {
+            Type::Oid if self.0 < 0 || self.0 > i64::from(u32::MAX) =>
+                Err("oid out of range".into()),
+            Type::Oid => (self.0 as u32).to_sql(type_, out),
+            _ => self.0.to_sql(type_, out),
+        }
+    }
+
+    fn accepts(type_: &postgres::types::Type) -> bool {
+        <PostgresInt64 as postgres::types::FromSql>::accepts(type_)
+    }
+
+    postgres::to_sql_checked!();
+}
+"#;
+
+/// The declaration of `PostgresJson` in generated Rust modules.
+pub const JSON: &'static str = r#"
+/// A JSON value of the type `json` or `jsonb`.
+#[derive(Debug)]
+pub struct PostgresJson(pub serde_json::Value);
+
+impl postgres::types::FromSql for PostgresJson {
+    fn from_sql(type_: &postgres::types::Type, raw: &[u8]) -> Result<Self, Error> {
+        let raw = match (type_, raw.split_first()) {
+            (&postgres::types::Type::Jsonb, Some((&1, raw))) => raw,
+            (&postgres::types::Type::Jsonb, _) => return Err("unsupported jsonb version".into()),
+            _ => raw,
+        };
+        Ok(PostgresJson(serde_json::from_slice(raw)?))
+    }
+
+    fn accepts(type_: &postgres::types::Type) -> bool {
+        match *type_ {
+            postgres::types::Type::Json | postgres::types::Type::Jsonb => true,
+            _ => false,
+        }
+    }
+}
+
+impl postgres::types::ToSql for PostgresJson {
+    fn to_sql(&self, type_: &postgres::types::Type, out: &mut Vec<u8>)
+        -> Result<postgres::types::IsNull, Error> {
+        if *type_ == postgres::types::Type::Jsonb {
+            out.push(1);
+        }
+        serde_json::to_writer(out, &self.0)?;
+        Ok(postgres::types::IsNull::No)
+    }
+
+    fn accepts(type_: &postgres::types::Type) -> bool {
+        <PostgresJson as postgres::types::FromSql>::accepts(type_)
+    }
+
+    postgres::to_sql_checked!();
+}
+"#;
+
+/// The declaration of `PostgresEnum` in generated Rust modules.
+pub const ENUM: &'static str = r#"
+/// A value of a type declared for an enumeration, as a value of an enum type
+/// with the same labels.
+#[derive(Debug)]
+pub struct PostgresEnum<T>(pub T);
+
+impl<T: serde::de::DeserializeOwned> postgres::types::FromSql for PostgresEnum<T> {
+    fn from_sql(_: &postgres::types::Type, raw: &[u8]) -> Result<Self, Error> {
+        let label = std::str::from_utf8(raw)?.to_string();
+        Ok(PostgresEnum(serde_json::from_value(serde_json::Value::String(label))?))
+    }
+
+    fn accepts(type_: &postgres::types::Type) -> bool {
+        match *type_.kind() {
+            postgres::types::Kind::Enum(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T: serde::Serialize + std::fmt::Debug> postgres::types::ToSql for PostgresEnum<T> {
+    fn to_sql(&self, _: &postgres::types::Type, out: &mut Vec<u8>)
+        -> Result<postgres::types::IsNull, Error> {
+        match serde_json::to_value(&self.0)? {
+            serde_json::Value::String(label) => out.extend_from_slice(label.as_bytes()),
+            _ => return Err("expected an enumeration".into()),
+        }
+        Ok(postgres::types::IsNull::No)
+    }
+
+    fn accepts(type_: &postgres::types::Type) -> bool {
+        match *type_.kind() {
+            postgres::types::Kind::Enum(_) => true,
+            _ => false,
+        }
+    }
+
+    postgres::to_sql_checked!();
+}
+"#;
+
+/// The direction of a conversion between the types given by `rust_type` and
+/// `postgres_type`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Direction {
+  ToPostgres,
+  FromPostgres,
+}
+
+/// Return the Rust type that the `postgres` crate converts to and from the
+/// PostgreSQL values that conform to a schema. Parameters of the types
+/// represented by strings are strings instead, and cast by `parameter_cast`.
+pub fn postgres_type(schema: &Schema) -> Result<String> {
+  match *schema {
+    Schema::ArrayOf(ref element) =>
+      Ok(format!("Vec<{}>", postgres_type(element)?)),
+    Schema::Optional(ref element) =>
+      Ok(format!("Option<{}>", postgres_type(element)?)),
+    Schema::Named(ref name, ref element) => match **element {
+      Schema::Enumeration(_) =>
+        Ok(format!("PostgresEnum<{}>", escape::rust_type_name(name)?)),
+      _ => Err(Error::Unsupported(error::UNSUPPORTED.to_string())),
+    },
+    Schema::Boolean => Ok("bool".to_string()),
+    Schema::SignedInteger(minimum, maximum) =>
+      Ok(rust_integer_type(minimum, maximum).to_string()),
+    Schema::SignedInteger64(_, _) => Ok("PostgresInt64".to_string()),
+    Schema::SinglePrecision => Ok("f32".to_string()),
+    Schema::DoublePrecision => Ok("f64".to_string()),
+    Schema::ByteString => Ok("Vec<u8>".to_string()),
+    Schema::String => Ok("String".to_string()),
+    _ if text_type(schema).is_some() => Ok("PostgresText".to_string()),
+    Schema::Json => Ok("PostgresJson".to_string()),
+    _ => Err(Error::Unsupported(error::UNSUPPORTED.to_string())),
+  }
+}
+
+/// Return the declarations of the types that `postgres_type` gives for a
+/// schema, and which generated Rust modules must contain, if not already in
+/// `support`.
+pub fn support(schema: &Schema, support: &mut Vec<&'static str>) {
+  let declaration = match *schema {
+    Schema::ArrayOf(ref element) | Schema::Optional(ref element) =>
+      return self::support(element, support),
+    Schema::Named(_, ref element) => match **element {
+      Schema::Enumeration(_) => ENUM,
+      _ => return,
+    },
+    Schema::SignedInteger64(_, _) => INT64,
+    Schema::Json => JSON,
+    _ if text_type(schema).is_some() => TEXT,
+    _ => return,
+  };
+  if !support.contains(&declaration) {
+    support.push(declaration);
+  }
+}
+
+/// Return the cast of the positional parameter with the given index and
+/// schema, if it is passed as text, such as `($1 :: text :: numeric)`.
+pub fn parameter_cast(index: usize, schema: &Schema) -> Option<String> {
+  text_type(schema).map(|type_| {
+    let dimensions = type_.matches("[]").count();
+    format!("(${} :: text{} :: {})", index, "[]".repeat(dimensions), type_)
+  })
+}
+
+/// Return the PostgreSQL type of values conforming to a schema, if they are
+/// represented by strings.
+fn text_type(schema: &Schema) -> Option<String> {
+  match *schema {
+    Schema::ArrayOf(ref element) =>
+      text_type(element).map(|element| format!("{}[]", element)),
+    Schema::Optional(ref element) => text_type(element),
+    Schema::Decimal => Some("numeric".to_string()),
+    Schema::Uuid => Some("uuid".to_string()),
+    Schema::Date => Some("date".to_string()),
+    Schema::Timestamp => Some("timestamp".to_string()),
+    Schema::TimestampWithTimeZone => Some("timestamptz".to_string()),
+    Schema::Interval => Some("interval".to_string()),
+    _ => None,
+  }
+}
+
+/// Return a Rust expression that converts the value of `expression` to the
+/// type of a parameter, which is that given by `postgres_type` unless the
+/// parameter is passed as text.
+pub fn to_postgres(schema: &Schema, expression: &str) -> String {
+  match *schema {
+    Schema::ArrayOf(ref element)
+      if converts(element, Direction::ToPostgres) =>
+      format!("{}.into_iter().map(|value| {}).collect::<Vec<_>>()",
+              expression, to_postgres(element, "value")),
+    Schema::Optional(ref element)
+      if converts(element, Direction::ToPostgres) =>
+      format!("{}.map(|value| {})", expression, to_postgres(element, "value")),
+    Schema::Named(_, _) => format!("PostgresEnum({})", expression),
+    Schema::SignedInteger64(_, _) => format!("PostgresInt64({}.0)", expression),
+    Schema::ByteString => format!("{}.0", expression),
+    Schema::Json => format!("PostgresJson({})", expression),
+    _ => expression.to_string(),
+  }
+}
+
+/// Return a Rust expression that converts the value of `expression`, of the
+/// type given by `postgres_type`, to the type given by `rust_type`.
+pub fn from_postgres(schema: &Schema, expression: &str) -> String {
+  match *schema {
+    Schema::ArrayOf(ref element)
+      if converts(element, Direction::FromPostgres) =>
+      format!("{}.into_iter().map(|value| {}).collect()", expression,
+              from_postgres(element, "value")),
+    Schema::Optional(ref element)
+      if converts(element, Direction::FromPostgres) =>
+      format!("{}.map(|value| {})", expression,
+              from_postgres(element, "value")),
+    Schema::SignedInteger64(_, _) => format!("Int64({}.0)", expression),
+    Schema::ByteString => format!("Bytes({})", expression),
+    Schema::Named(_, _) | Schema::Json => format!("{}.0", expression),
+    _ if text_type(schema).is_some() => format!("{}.0", expression),
+    _ => expression.to_string(),
+  }
+}
+
+/// Whether values conforming to a schema must be converted in the given
+/// direction.
+fn converts(schema: &Schema, direction: Direction) -> bool {
+  match *schema {
+    Schema::ArrayOf(ref element) | Schema::Optional(ref element) =>
+      converts(element, direction),
+    Schema::Named(_, _)
+    | Schema::SignedInteger64(_, _)
+    | Schema::ByteString
+    | Schema::Json => true,
+    _ => direction == Direction::FromPostgres && text_type(schema).is_some(),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::i64;
+
+  #[test]
+  fn test_conversions() {
+    let schema = Schema::Optional(Box::new(Schema::ArrayOf(Box::new(
+      Schema::Optional(Box::new(
+        Schema::SignedInteger64(i64::MIN, i64::MAX)))))));
+    assert_eq!(postgres_type(&schema).map_err(|e| e.to_string()),
+               Ok("Option<Vec<Option<PostgresInt64>>>".to_string()));
+    assert_eq!(to_postgres(&schema, "input.ids"), concat!(
+      "input.ids.map(|value| value.into_iter().map(|value| ",
+      "value.map(|value| PostgresInt64(value.0))).collect::<Vec<_>>())"));
+    assert_eq!(from_postgres(&schema, "column"), concat!(
+      "column.map(|value| value.into_iter().map(|value| ",
+      "value.map(|value| Int64(value.0))).collect())"));
+    let mut declarations = vec![];
+    support(&schema, &mut declarations);
+    assert_eq!(declarations, vec![INT64]);
+    assert_eq!(from_postgres(&Schema::String, "column"), "column");
+    assert!(postgres_type(&Schema::Named("positive".to_string(), Box::new(
+      Schema::SignedInteger(1, i32::MAX)))).is_err());
+  }
+
+  #[test]
+  fn test_text_conversions() {
+    let schema = Schema::ArrayOf(Box::new(Schema::Optional(Box::new(
+      Schema::Decimal))));
+    assert_eq!(postgres_type(&schema).map_err(|e| e.to_string()),
+               Ok("Vec<Option<PostgresText>>".to_string()));
+    assert_eq!(to_postgres(&schema, "input"), "input");
+    assert_eq!(from_postgres(&schema, "column"),
+               "column.into_iter().map(|value| value.map(|value| value.0)).collect()");
+    assert_eq!(parameter_cast(2, &schema),
+               Some("($2 :: text[] :: numeric[])".to_string()));
+    assert_eq!(parameter_cast(1, &Schema::Json), None);
+
+    let mut declarations = vec![];
+    support(&schema, &mut declarations);
+    support(&Schema::TimestampWithTimeZone, &mut declarations);
+    assert_eq!(declarations, vec![TEXT]);
+  }
+
+  #[test]
+  fn test_enum_conversions() {
+    let schema = Schema::Optional(Box::new(Schema::Named(
+      "mood".to_string(),
+      Box::new(Schema::Enumeration(vec!["sad".to_string()])))));
+    assert_eq!(postgres_type(&schema).map_err(|e| e.to_string()),
+               Ok("Option<PostgresEnum<Mood>>".to_string()));
+    assert_eq!(to_postgres(&schema, "input.mood"),
+               "input.mood.map(|value| PostgresEnum(value))");
+    assert_eq!(from_postgres(&schema, "column"),
+               "column.map(|value| value.0)");
+    let mut declarations = vec![];
+    support(&schema, &mut declarations);
+    assert_eq!(declarations, vec![ENUM]);
+  }
+}
@@ -2,25 +2,385 @@
 //! different programming environments.
 
 /// Generate ECMAScript functions for serializing and deserializing data.
+///
+/// 64-bit integers and decimal numbers are represented as strings of decimal
+/// digits, so that they do not lose precision. Temporal values and UUIDs are
+/// represented as strings as well. Byte strings are `Uint8Array`s, and are
+/// serialized as base64 strings. Data conforming to one of many schemas is
+/// represented as an object `{tag: index, value: value}`, where `index` is
+/// the position of the schema among the alternatives, and is serialized as
+/// the value alone. When deserializing, the first alternative that the value
+/// conforms to is taken.
+///
+/// The generated functions check that the data conforms to the schema. If it
+/// does not, they throw an `Error` named `ValidationError`, whose `path`
+/// property is a JSON Pointer to the offending value.
 pub mod ecmascript {
   use Schema;
+  use error::Result;
+  use escape;
   use std::io;
 
+  /// The name of the errors thrown by the generated functions.
+  pub const VALIDATION_ERROR: &'static str = "ValidationError";
+
+  /// Whether a function converts to or from the serialized representation.
+  #[derive(Clone, Copy, PartialEq)]
+  enum Direction {
+    Serialize,
+    Deserialize,
+  }
+
   /// Generate an ECMAScript expression that evaluates to a function that
   /// serializes data conforming to the given schema.
-  pub fn serialize(write: &mut io::Write, _: &Schema) -> io::Result<()> {
-    write!(write, "(function(value) {{\n")?;
-    write!(write, "return value;\n")?;
-    write!(write, "}})")?;
-    Ok(())
+  pub fn serialize(write: &mut io::Write, schema: &Schema) -> Result<()> {
+    function(write, schema, Direction::Serialize)
   }
 
   /// Generate an ECMAScript expression that evaluates to a function that
   /// deserializes data conforming to the given schema.
-  pub fn deserialize(write: &mut io::Write, _: &Schema) -> io::Result<()> {
-    write!(write, "(function(value) {{\n")?;
-    write!(write, "return value;\n")?;
+  pub fn deserialize(write: &mut io::Write, schema: &Schema)
+    -> Result<()> {
+    function(write, schema, Direction::Deserialize)
+  }
+
+  fn function(write: &mut io::Write, schema: &Schema, direction: Direction)
+    -> Result<()> {
+    write!(write, "(function() {{\n")?;
+    write!(write, "var fail = function(path, problem) {{\n")?;
+    write!(write, "var error = new Error('The value' + (path === '' ? '' : ' at ' + path) + ' ' + problem + '.');\n")?;
+    write!(write, "error.name = '{}';\n", VALIDATION_ERROR)?;
+    write!(write, "error.path = path;\n")?;
+    write!(write, "throw error;\n")?;
+    write!(write, "}};\n")?;
+    write!(write, "var convert = ")?;
+    convert(write, schema, direction)?;
+    write!(write, ";\n")?;
+    write!(write, "return function(value) {{\n")?;
+    write!(write, "return convert(value, '');\n")?;
+    write!(write, "}};\n")?;
+    write!(write, "}})()")?;
+    Ok(())
+  }
+
+  /// Generate an ECMAScript expression that evaluates to a function that
+  /// checks and converts a value at a path. Records are read from and written
+  /// to objects keyed by field name.
+  fn convert(write: &mut io::Write, schema: &Schema, direction: Direction)
+    -> Result<()> {
+    if let Schema::Named(_, ref schema) = *schema {
+      return convert(write, schema, direction);
+    }
+    write!(write, "(function(value, path) {{\n")?;
+    match *schema {
+      Schema::AllOf(ref elements) => {
+        write!(write, "if (!Array.isArray(value) || value.length !== {}) {{\n",
+               elements.len())?;
+        write!(write, "fail(path, 'is not an array of {} elements');\n",
+               elements.len())?;
+        write!(write, "}}\n")?;
+        write!(write, "return [\n")?;
+        for (index, schema) in elements.iter().enumerate() {
+          convert(write, schema, direction)?;
+          write!(write, "(value[{0}], path + '/{0}'),\n", index)?;
+        }
+        write!(write, "];\n")?;
+      },
+      Schema::Record(ref fields) => {
+        write!(write, "if (typeof value !== 'object' || value === null || Array.isArray(value)) {{\n")?;
+        write!(write, "fail(path, 'is not an object');\n")?;
+        write!(write, "}}\n")?;
+        write!(write, "return {{\n")?;
+        for &(ref name, ref schema) in fields {
+          let key = escape::ecmascript_string(name);
+          let token = name.replace('~', "~0").replace('/', "~1");
+          write!(write, "{}: ", key)?;
+          convert(write, schema, direction)?;
+          write!(write, "(value[{}], path + {}),\n", key,
+                 escape::ecmascript_string(&format!("/{}", token)))?;
+        }
+        write!(write, "}};\n")?;
+      },
+      Schema::ArrayOf(ref schema) => {
+        write!(write, "if (!Array.isArray(value)) {{\n")?;
+        write!(write, "fail(path, 'is not an array');\n")?;
+        write!(write, "}}\n")?;
+        write!(write, "var element = ")?;
+        convert(write, schema, direction)?;
+        write!(write, ";\n")?;
+        write!(write, "return value.map(function(value, index) {{\n")?;
+        write!(write, "return element(value, path + '/' + index);\n")?;
+        write!(write, "}});\n")?;
+      },
+      Schema::Optional(ref schema) => {
+        write!(write, "if (value === null || value === undefined) {{\n")?;
+        write!(write, "return null;\n")?;
+        write!(write, "}}\n")?;
+        write!(write, "return ")?;
+        convert(write, schema, direction)?;
+        write!(write, "(value, path);\n")?;
+      },
+      Schema::OneOf(ref alternatives) => match direction {
+        Direction::Serialize => {
+          write!(write, "if (typeof value !== 'object' || value === null || typeof value.tag !== 'number') {{\n")?;
+          write!(write, "fail(path, 'is not a tagged alternative');\n")?;
+          write!(write, "}}\n")?;
+          write!(write, "switch (value.tag) {{\n")?;
+          for (tag, alternative) in alternatives.iter().enumerate() {
+            write!(write, "case {}: return ", tag)?;
+            convert(write, alternative, direction)?;
+            write!(write, "(value.value, path + '/value');\n")?;
+          }
+          write!(write, "}}\n")?;
+          write!(write, "fail(path, 'has an unknown tag');\n")?;
+        },
+        Direction::Deserialize => {
+          for (tag, alternative) in alternatives.iter().enumerate() {
+            write!(write, "try {{\n")?;
+            write!(write, "return {{tag: {}, value: ", tag)?;
+            convert(write, alternative, direction)?;
+            write!(write, "(value, path)}};\n")?;
+            write!(write, "}} catch (error) {{\n")?;
+            write!(write, "if (error.name !== '{}') {{\n", VALIDATION_ERROR)?;
+            write!(write, "throw error;\n")?;
+            write!(write, "}}\n")?;
+            write!(write, "}}\n")?;
+          }
+          write!(write, "fail(path, 'does not conform to any alternative');\n")?;
+        },
+      },
+      Schema::Named(_, _) => unreachable!(),
+      Schema::Enumeration(ref labels) => {
+        let labels = labels.iter()
+          .map(|label| escape::ecmascript_string(label))
+          .collect::<Vec<_>>();
+        write!(write, "if ([{}].indexOf(value) === -1) {{\n", labels.join(", "))?;
+        write!(write, "fail(path, 'is not one of the labels');\n")?;
+        write!(write, "}}\n")?;
+        write!(write, "return value;\n")?;
+      },
+      Schema::Boolean => check(write, "typeof value === 'boolean'", "a boolean")?,
+      Schema::SignedInteger(minimum, maximum) => {
+        check(write, "typeof value === 'number' && value % 1 === 0",
+              "an integer")?;
+        write!(write, "if (value < {} || value > {}) {{\n", minimum, maximum)?;
+        write!(write, "fail(path, 'is not between {} and {}');\n", minimum,
+               maximum)?;
+        write!(write, "}}\n")?;
+      },
+      Schema::SignedInteger64(_, _) =>
+        check(write, "typeof value === 'string' && /^-?[0-9]+$/.test(value)",
+              "an integer string")?,
+      Schema::Decimal =>
+        check(write, "typeof value === 'string'", "a decimal string")?,
+      Schema::SinglePrecision => {
+        check(write, "typeof value === 'number'", "a number")?;
+        write!(write, "if (Math.abs(value) > 3.4028234663852886e38) {{\n")?;
+        write!(write, "fail(path, 'is out of the single-precision range');\n")?;
+        write!(write, "}}\n")?;
+      },
+      Schema::DoublePrecision => check(write, "typeof value === 'number'", "a number")?,
+      Schema::ByteString => match direction {
+        Direction::Serialize => {
+          write!(write, "if (!(value instanceof Uint8Array)) {{\n")?;
+          write!(write, "fail(path, 'is not a Uint8Array');\n")?;
+          write!(write, "}}\n")?;
+          write!(write, "if (typeof Buffer !== 'undefined') {{\n")?;
+          write!(write, "return Buffer.from(value.buffer, value.byteOffset, value.byteLength).toString('base64');\n")?;
+          write!(write, "}}\n")?;
+          write!(write, "var binary = '';\n")?;
+          write!(write, "for (var i = 0; i < value.length; ++i) {{\n")?;
+          write!(write, "binary += String.fromCharCode(value[i]);\n")?;
+          write!(write, "}}\n")?;
+          write!(write, "return btoa(binary);\n")?;
+        },
+        Direction::Deserialize => {
+          write!(write, "if (typeof value !== 'string' || !/^[A-Za-z0-9+\\/]*={{0,2}}$/.test(value) || value.length % 4 !== 0) {{\n")?;
+          write!(write, "fail(path, 'is not a base64 string');\n")?;
+          write!(write, "}}\n")?;
+          write!(write, "if (typeof Buffer !== 'undefined') {{\n")?;
+          write!(write, "return new Uint8Array(Buffer.from(value, 'base64'));\n")?;
+          write!(write, "}}\n")?;
+          write!(write, "var binary = atob(value);\n")?;
+          write!(write, "var bytes = new Uint8Array(binary.length);\n")?;
+          write!(write, "for (var i = 0; i < binary.length; ++i) {{\n")?;
+          write!(write, "bytes[i] = binary.charCodeAt(i);\n")?;
+          write!(write, "}}\n")?;
+          write!(write, "return bytes;\n")?;
+        },
+      },
+      Schema::String
+      | Schema::Uuid
+      | Schema::Date
+      | Schema::Timestamp
+      | Schema::TimestampWithTimeZone
+      | Schema::Interval => check(write, "typeof value === 'string'", "a string")?,
+      Schema::Json => write!(write, "return value;\n")?,
+    }
+    match *schema {
+      Schema::AllOf(_) | Schema::Record(_) | Schema::ArrayOf(_)
+      | Schema::Optional(_) | Schema::OneOf(_) | Schema::Named(_, _)
+      | Schema::Enumeration(_) | Schema::ByteString | Schema::Json => (),
+      _ => write!(write, "return value;\n")?,
+    }
     write!(write, "}})")?;
     Ok(())
   }
+
+  /// Generate a statement that fails unless `condition` holds.
+  fn check(write: &mut io::Write, condition: &str, expected: &str)
+    -> Result<()> {
+    write!(write, "if (!({})) {{\n", condition)?;
+    write!(write, "fail(path, 'is not {}');\n", expected)?;
+    write!(write, "}}\n")?;
+    Ok(())
+  }
+
+  #[cfg(test)]
+  mod test {
+    use super::*;
+    use std::io::Write;
+    use std::process::Command;
+
+    /// Run an ECMAScript program with Node.js, after declaring `serialize`
+    /// and `deserialize` for a schema and `attempt`, which prints the JSON of
+    /// the result of a function or the message and path of the error it
+    /// throws. Return what the program prints, or nothing if Node.js cannot
+    /// be run.
+    fn run(schema: &Schema, program: &str) -> Option<String> {
+      let mut script = vec![];
+      write!(script, "var serialize = ").unwrap();
+      serialize(&mut script, schema).unwrap();
+      write!(script, ";\nvar deserialize = ").unwrap();
+      deserialize(&mut script, schema).unwrap();
+      write!(script, ";\n").unwrap();
+      write!(script, "var attempt = function(f) {{\n").unwrap();
+      write!(script, "try {{\n").unwrap();
+      write!(script, "console.log(JSON.stringify(f()));\n").unwrap();
+      write!(script, "}} catch (error) {{\n").unwrap();
+      write!(script, "console.log(error.name + ' ' + JSON.stringify(error.path) + ' ' + error.message);\n").unwrap();
+      write!(script, "}}\n").unwrap();
+      write!(script, "}};\n{}\n", program).unwrap();
+      let script = String::from_utf8(script).unwrap();
+      match Command::new("node").arg("-e").arg(&script).output() {
+        Ok(output) => {
+          assert!(output.status.success(), "{}",
+                  String::from_utf8_lossy(&output.stderr));
+          Some(String::from_utf8(output.stdout).unwrap())
+        },
+        Err(_) => {
+          println!("Skipping test: could not run Node.js");
+          None
+        },
+      }
+    }
+
+    #[test]
+    fn test_all_of_arity() {
+      let schema = Schema::AllOf(vec![Schema::Boolean, Schema::String]);
+      let output = run(&schema, concat!(
+        "attempt(function() { return deserialize([true, 'a']); });\n",
+        "attempt(function() { return deserialize([true]); });\n",
+        "attempt(function() { return serialize([true, 'a', 'b']); });\n",
+        "attempt(function() { return deserialize({0: true, 1: 'a'}); });"));
+      if let Some(output) = output {
+        assert_eq!(output, concat!(
+          "[true,\"a\"]\n",
+          "ValidationError \"\" The value is not an array of 2 elements.\n",
+          "ValidationError \"\" The value is not an array of 2 elements.\n",
+          "ValidationError \"\" The value is not an array of 2 elements.\n"));
+      }
+    }
+
+    #[test]
+    fn test_signed_integer() {
+      let schema = Schema::ArrayOf(Box::new(Schema::SignedInteger(-5, 5)));
+      let output = run(&schema, concat!(
+        "attempt(function() { return deserialize([-5, 0, 5]); });\n",
+        "attempt(function() { return deserialize([0, 6]); });\n",
+        "attempt(function() { return deserialize([-6]); });\n",
+        "attempt(function() { return serialize([1.5]); });\n",
+        "attempt(function() { return deserialize(['1']); });"));
+      if let Some(output) = output {
+        assert_eq!(output, concat!(
+          "[-5,0,5]\n",
+          "ValidationError \"/1\" The value at /1 is not between -5 and 5.\n",
+          "ValidationError \"/0\" The value at /0 is not between -5 and 5.\n",
+          "ValidationError \"/0\" The value at /0 is not an integer.\n",
+          "ValidationError \"/0\" The value at /0 is not an integer.\n"));
+      }
+    }
+
+    #[test]
+    fn test_base64() {
+      let program = concat!(
+        "attempt(function() { return serialize(new Uint8Array([])); });\n",
+        "attempt(function() { return serialize(new Uint8Array([0, 1, 254, 255])); });\n",
+        "attempt(function() { return serialize(new Uint8Array([0, 1, 254, 255]).subarray(1, 3)); });\n",
+        "attempt(function() { return Array.from(deserialize('AAH+/w==')); });\n",
+        "attempt(function() { return Array.from(deserialize('AAH+')); });\n",
+        "attempt(function() { return deserialize('AAH'); });\n",
+        "attempt(function() { return deserialize('AA=H'); });\n",
+        "attempt(function() { return serialize([0]); });");
+      let expected = concat!(
+        "\"\"\n",
+        "\"AAH+/w==\"\n",
+        "\"Af4=\"\n",
+        "[0,1,254,255]\n",
+        "[0,1,254]\n",
+        "ValidationError \"\" The value is not a base64 string.\n",
+        "ValidationError \"\" The value is not a base64 string.\n",
+        "ValidationError \"\" The value is not a Uint8Array.\n");
+      if let Some(output) = run(&Schema::ByteString, program) {
+        assert_eq!(output, expected);
+      }
+      // Without `Buffer`, as in browsers, `btoa` and `atob` are used.
+      let program = format!("(function(Buffer) {{\n{}\n}})(undefined);",
+                            program);
+      if let Some(output) = run(&Schema::ByteString, &program) {
+        assert_eq!(output, expected);
+      }
+    }
+
+    #[test]
+    fn test_path_escaping() {
+      let schema = Schema::Record(vec![
+        ("a/b~c".to_string(), Schema::Record(vec![
+          ("~1".to_string(), Schema::Boolean),
+        ])),
+      ]);
+      let mut code = vec![];
+      deserialize(&mut code, &schema).unwrap();
+      let code = String::from_utf8(code).unwrap();
+      assert!(code.contains("path + '/a~1b~0c'"));
+      assert!(code.contains("path + '/~01'"));
+      let output = run(&schema, concat!(
+        "attempt(function() { return deserialize({'a/b~c': {'~1': true}}); });\n",
+        "attempt(function() { return deserialize({'a/b~c': {'~1': 1}}); });"));
+      if let Some(output) = output {
+        assert_eq!(output, concat!(
+          "{\"a/b~c\":{\"~1\":true}}\n",
+          "ValidationError \"/a~1b~0c/~01\" The value at /a~1b~0c/~01 is not a boolean.\n"));
+      }
+    }
+
+    #[test]
+    fn test_record_not_object() {
+      let schema = Schema::ArrayOf(Box::new(Schema::Record(vec![
+        ("a".to_string(), Schema::Optional(Box::new(Schema::Boolean))),
+      ])));
+      let output = run(&schema, concat!(
+        "attempt(function() { return deserialize([{}, {a: true}]); });\n",
+        "attempt(function() { return deserialize([null]); });\n",
+        "attempt(function() { return deserialize([{}, []]); });\n",
+        "attempt(function() { return serialize(['a']); });"));
+      if let Some(output) = output {
+        assert_eq!(output, concat!(
+          "[{\"a\":null},{\"a\":true}]\n",
+          "ValidationError \"/0\" The value at /0 is not an object.\n",
+          "ValidationError \"/1\" The value at /1 is not an object.\n",
+          "ValidationError \"/0\" The value at /0 is not an object.\n"));
+      }
+    }
+  }
 }
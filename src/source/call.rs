@@ -2,75 +2,1899 @@
 //! traits for sources that can generate calls.
 
 use Schema;
+use error::{Error, Result};
+use escape;
+use json_schema;
+use serde_json;
 use source::HasSchema;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::{i16, i8};
 use std::io;
+use std::rc::Rc;
 
 /// Trait for sources that can generate ECMAScript calls.
 pub trait ECMAScript {
   /// Generate an ECMAScript expression that evaluates to a function that
   /// performs the call.
-  fn ecmascript_call(&self, &mut io::Write) -> io::Result<()>;
+  fn ecmascript_call(&self, &mut io::Write) -> Result<()>;
 
   /// Return the calling convention used for this call.
-  fn ecmascript_convention(&self) -> io::Result<ECMAScriptConvention>;
+  fn ecmascript_convention(&self) -> Result<ECMAScriptConvention>;
+
+  /// Return the modules that the generated expression refers to, as pairs of
+  /// a variable and the name of the module that must be bound to it. The
+  /// module that contains the call binds them with `require` or `import`.
+  fn ecmascript_imports(&self) -> Vec<(&'static str, &'static str)> {
+    vec![]
+  }
 }
 
+/// Trait for sources that can generate Rust calls.
+pub trait Rust {
+  /// Return the Rust type of the context that the generated function takes as
+  /// its first parameter, such as a database connection or a URL.
+  fn rust_context(&self) -> Result<String>;
+
+  /// Generate the body of a Rust function that performs the call. The
+  /// function has the parameters `context` and `input`, and returns the
+  /// output or an `Error`. The input and output have the types given by
+  /// `rust_type` for the hints `rust_hint(name, "input")` and
+  /// `rust_hint(name, "output")`, where `name` is the name of the call.
+  fn rust_call(&self, write: &mut io::Write, name: &str) -> Result<()>;
+
+  /// Return the declarations that the generated function relies on besides
+  /// the types of its schemas, such as conversions for a database client.
+  /// Generated modules contain each of them once.
+  fn rust_support(&self) -> Result<Vec<&'static str>> {
+    Ok(vec![])
+  }
+}
+
+/// Trait for sources that have schemas and can generate ECMAScript and Rust
+/// calls.
+pub trait Call: HasSchema + ECMAScript + Rust {}
+
+impl<T> Call for T where T: HasSchema + ECMAScript + Rust {}
+
 /// Calling convention for generated ECMAScript calls.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ECMAScriptConvention {
   /// The generated function returns the result or throws an exception.
   Synchronous,
 
   /// The generated function takes two callbacks.
   Asynchronous,
+
+  /// The generated function returns a Promise of the result.
+  Promise,
+}
+
+/// How the asynchronous calls of an ECMAScript module are exposed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Asynchrony {
+  /// The calls keep the asynchronous convention and take two callbacks.
+  Callbacks,
+
+  /// The calls are adapted with `Promised` and return Promises.
+  Promises,
+}
+
+/// A call that adapts a call with the asynchronous convention to the Promise
+/// convention. Calls with other conventions are left alone.
+pub struct Promised<'a, C>(pub &'a C) where C: 'a + ?Sized;
+
+impl<'a, C> HasSchema for Promised<'a, C> where C: HasSchema + ?Sized {
+  fn schema(&self) -> Result<(Rc<Schema>, Rc<Schema>)> {
+    self.0.schema()
+  }
+}
+
+impl<'a, C> ECMAScript for Promised<'a, C> where C: ECMAScript + ?Sized {
+  fn ecmascript_call(&self, write: &mut io::Write) -> Result<()> {
+    if self.0.ecmascript_convention()? != ECMAScriptConvention::Asynchronous {
+      return self.0.ecmascript_call(write);
+    }
+    write!(write, "(function(call) {{\n")?;
+    write!(write, "return function(context, input) {{\n")?;
+    write!(write, "return new Promise(function(resolve, reject) {{\n")?;
+    write!(write, "call(context, input, resolve, reject);\n")?;
+    write!(write, "}});\n")?;
+    write!(write, "}};\n")?;
+    write!(write, "}})(")?;
+    self.0.ecmascript_call(write)?;
+    write!(write, ")")?;
+    Ok(())
+  }
+
+  fn ecmascript_convention(&self) -> Result<ECMAScriptConvention> {
+    match self.0.ecmascript_convention()? {
+      ECMAScriptConvention::Asynchronous => Ok(ECMAScriptConvention::Promise),
+      convention => Ok(convention),
+    }
+  }
+
+  fn ecmascript_imports(&self) -> Vec<(&'static str, &'static str)> {
+    self.0.ecmascript_imports()
+  }
+}
+
+/// The kind of TypeScript file generated for an ECMAScript module.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TypeScriptOutput {
+  /// A declaration file (`.d.ts`) that describes the ECMAScript module.
+  Declarations,
+
+  /// A TypeScript module (`.ts`) that contains the calls themselves.
+  Module,
 }
 
 pub struct ECMAScriptModule<'a> {
-  pub calls: HashMap<String, (&'a HasSchema, &'a ECMAScript)>,
+  /// The calls, by name. They are generated in order of name, so that the
+  /// same calls always give the same code.
+  pub calls: BTreeMap<String, (&'a HasSchema, &'a ECMAScript)>,
+
+  /// How the asynchronous calls are exposed.
+  pub asynchrony: Asynchrony,
 }
 
 impl<'a> ECMAScriptModule<'a> {
-  pub fn ecmascript(&self, write: &mut io::Write) -> io::Result<()> {
-    for (name, &(_, ref call)) in &self.calls {
-      write!(write, "exports.{} = ", name)?;
-      call.ecmascript_call(write)?;
+  pub fn ecmascript(&self, write: &mut io::Write) -> Result<()> {
+    for (variable, module) in self.imports() {
+      write!(write, "var {} = require({});\n", variable,
+             escape::ecmascript_string(module))?;
+    }
+    for (name, &(_, call)) in &self.calls {
+      write!(write, "exports.{} = ", escape::ecmascript_identifier(name)?)?;
+      self.call(write, call)?;
+      write!(write, ";\n")?;
+    }
+    Ok(())
+  }
+
+  /// Return the modules that the calls refer to, without duplicates. See
+  /// `ECMAScript::ecmascript_imports`.
+  fn imports(&self) -> BTreeSet<(&'static str, &'static str)> {
+    self.calls.values()
+      .flat_map(|&(_, call)| call.ecmascript_imports())
+      .collect()
+  }
+
+  /// Generate ECMAScript module imports of the modules that the calls refer
+  /// to.
+  fn import(&self, write: &mut io::Write) -> Result<()> {
+    for (variable, module) in self.imports() {
+      write!(write, "import {} from {};\n", variable,
+             escape::ecmascript_string(module))?;
+    }
+    Ok(())
+  }
+
+  /// Generate a call as exposed by the module.
+  fn call(&self, write: &mut io::Write, call: &ECMAScript) -> Result<()> {
+    match self.asynchrony {
+      Asynchrony::Callbacks => call.ecmascript_call(write),
+      Asynchrony::Promises => Promised(call).ecmascript_call(write),
+    }
+  }
+
+  /// Return the calling convention of a call as exposed by the module.
+  fn convention(&self, call: &ECMAScript) -> Result<ECMAScriptConvention> {
+    match self.asynchrony {
+      Asynchrony::Callbacks => call.ecmascript_convention(),
+      Asynchrony::Promises => Promised(call).ecmascript_convention(),
+    }
+  }
+
+  /// Generate a JSON Schema document that bundles the input and output
+  /// schemas of the calls. See `json_schema::bundle`.
+  pub fn json_schema(&self, write: &mut io::Write) -> Result<()> {
+    let bundle = json_schema::bundle(
+      self.calls.iter().map(|(name, &(source, _))| (name.as_str(), source))
+    )?;
+    serde_json::to_writer_pretty(&mut *write, &bundle)
+      .map_err(|error| Error::Io(error.into()))?;
+    write!(write, "\n")?;
+    Ok(())
+  }
+
+  /// Generate TypeScript declarations for the calls, or a TypeScript module
+  /// that contains them. Named schemas are declared as exported type aliases.
+  /// The calls are functions whose first parameter is the context, such as a
+  /// database client or a URL, followed by the input and, for asynchronous
+  /// calls, the callbacks.
+  ///
+  /// In a module, the calls and the imports of the modules they refer to are
+  /// untyped ECMAScript, so type errors are ignored on their lines only, and
+  /// the calls are cast to their declared types.
+  pub fn typescript(&self, write: &mut io::Write, output: TypeScriptOutput)
+    -> Result<()> {
+    if output == TypeScriptOutput::Module {
+      let mut imports = vec![];
+      self.import(&mut imports)?;
+      typescript_untyped(write, &imports)?;
+    }
+
+    let mut named = vec![];
+    for &(ref has_schema, _) in self.calls.values() {
+      let (input_schema, output_schema) = has_schema.schema()?;
+      named_schemas(&input_schema, &mut named)?;
+      named_schemas(&output_schema, &mut named)?;
+    }
+    for &(ref name, ref schema) in &named {
+      write!(write, "export type {} = ", escape::ecmascript_type_name(name)?)?;
+      typescript_type(write, schema)?;
       write!(write, ";\n")?;
     }
+
+    for (name, &(has_schema, call)) in &self.calls {
+      let (input_schema, output_schema) = has_schema.schema()?;
+      let name = escape::ecmascript_identifier(name)?;
+      match output {
+        TypeScriptOutput::Declarations =>
+          write!(write, "export declare const {}: ", name)?,
+        TypeScriptOutput::Module => write!(write, "export const {}: ", name)?,
+      }
+      write!(write, "(context: any, input: ")?;
+      typescript_type(write, &input_schema)?;
+      match self.convention(call)? {
+        ECMAScriptConvention::Synchronous => {
+          write!(write, ") => ")?;
+          typescript_type(write, &output_schema)?;
+        },
+        ECMAScriptConvention::Asynchronous => {
+          write!(write, ", onSuccess: (output: ")?;
+          typescript_type(write, &output_schema)?;
+          write!(write, ") => void, onError: (error: any) => void) => void")?;
+        },
+        ECMAScriptConvention::Promise => {
+          write!(write, ") => Promise<")?;
+          typescript_type(write, &output_schema)?;
+          write!(write, ">")?;
+        },
+      }
+      if output == TypeScriptOutput::Module {
+        let mut code = b"(".to_vec();
+        self.call(&mut code, call)?;
+        code.extend_from_slice(b") as any;");
+        write!(write, " =\n")?;
+        typescript_untyped(write, &code)?;
+      } else {
+        write!(write, ";\n")?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Generate a PureScript module with the given name for the calls. Named
+  /// schemas are declared as types. Synchronous calls are effects, and
+  /// asynchronous calls are `Aff`s that wrap foreign imports taking
+  /// callbacks. The foreign imports are implemented by the module generated
+  /// by `purescript_foreign`, which must be placed next to this one with the
+  /// extension `.js`.
+  ///
+  /// Alternatives are nested `Either`s, or sum types if they are named, named
+  /// enumerations are sum types with a constructor for each label, and
+  /// optional data is `Maybe`. If there are any, the foreign imports take a
+  /// record of the constructors and eliminators of these types, so that the
+  /// foreign module can convert the tagged objects, labels and nulls that
+  /// represent them in ECMAScript.
+  pub fn purescript(&self, write: &mut io::Write, module: &str)
+    -> Result<()> {
+    let (named, usage) = self.purescript_usage()?;
+    let sums = named.iter()
+      .filter(|&&(_, ref schema)| is_sum(schema))
+      .collect::<Vec<_>>();
+    let runtime = usage.either || usage.maybe || !sums.is_empty();
+    let mut asynchronous = false;
+    for &(_, call) in self.calls.values() {
+      asynchronous |=
+        self.convention(call)? != ECMAScriptConvention::Synchronous;
+    }
+
+    write!(write, "module {} where\n\n",
+           escape::purescript_module_name(module)?)?;
+    write!(write, "import Prelude\n\n")?;
+    if usage.uint8_array {
+      write!(write, "import Data.ArrayBuffer.Types (Uint8Array)\n")?;
+    }
+    // The runtime record refers to Either and Maybe even if only one of them,
+    // or only named alternatives, which are sum types, are used.
+    if runtime {
+      write!(write, "import Data.Either (Either(..), either)\n")?;
+    } else if asynchronous {
+      write!(write, "import Data.Either (Either(..))\n")?;
+    }
+    if runtime {
+      write!(write, "import Data.Maybe (Maybe(..), maybe)\n")?;
+    }
+    if named.iter().any(|&(_, ref schema)| is_newtype(schema)) {
+      write!(write, "import Data.Newtype (class Newtype)\n")?;
+    }
+    if !self.calls.is_empty() {
+      write!(write, "import Effect (Effect)\n")?;
+    }
+    if asynchronous {
+      write!(write, "import Effect.Aff (Aff, makeAff, nonCanceler)\n")?;
+      write!(write, "import Effect.Exception (Error)\n")?;
+    }
+    if usage.foreign {
+      write!(write, "import Foreign (Foreign)\n")?;
+    }
+    write!(write, "\n")?;
+
+    // Tuples are represented as arrays, so they are foreign types with
+    // foreign functions for constructing and eliminating them.
+    for &arity in &usage.tuples {
+      let variables = (1..arity + 1)
+        .map(|index| format!("a{}", index))
+        .collect::<Vec<_>>();
+      let tuple = format!("Tuple{}{}", arity, variables.iter()
+                          .map(|variable| format!(" {}", variable))
+                          .collect::<String>());
+      write!(write, "foreign import data Tuple{} :: ", arity)?;
+      for _ in &variables {
+        write!(write, "Type -> ")?;
+      }
+      write!(write, "Type\n\n")?;
+      write!(write, "foreign import tuple{} :: ", arity)?;
+      if arity > 0 {
+        write!(write, "forall {}. ", variables.join(" "))?;
+      }
+      for variable in &variables {
+        write!(write, "{} -> ", variable)?;
+      }
+      write!(write, "{}\n\n", tuple)?;
+      write!(write, "foreign import fromTuple{} :: forall ", arity)?;
+      for variable in &variables {
+        write!(write, "{} ", variable)?;
+      }
+      if arity == 0 {
+        write!(write, "r. r -> {} -> r\n\n", tuple)?;
+      } else {
+        write!(write, "r. (")?;
+        for variable in &variables {
+          write!(write, "{} -> ", variable)?;
+        }
+        write!(write, "r) -> {} -> r\n\n", tuple)?;
+      }
+    }
+
+    for &(ref name, ref schema) in &named {
+      purescript_declaration(write, name, schema)?;
+    }
+
+    if runtime {
+      write!(write, "type Runtime =\n")?;
+      write!(write, "  {{ left :: forall a b. a -> Either a b\n")?;
+      write!(write, "  , right :: forall a b. b -> Either a b\n")?;
+      write!(write, "  , either :: forall a b c. (a -> c) -> (b -> c) -> ")?;
+      write!(write, "Either a b -> c\n")?;
+      write!(write, "  , nothing :: forall a. Maybe a\n")?;
+      write!(write, "  , just :: forall a. a -> Maybe a\n")?;
+      write!(write, "  , maybe :: forall a b. b -> (a -> b) -> Maybe a -> b\n")?;
+      for &&(ref name, ref schema) in &sums {
+        let type_name = escape::purescript_type_name(name)?;
+        write!(write, "  , {} :: {{ match :: ",
+               escape::purescript_string(&type_name))?;
+        purescript_match_type(write, &type_name, schema)?;
+        match *schema {
+          Schema::OneOf(ref alternatives) =>
+            for (tag, alternative) in alternatives.iter().enumerate() {
+              write!(write, ", \"{}\" :: ", tag)?;
+              purescript_type(write, alternative)?;
+              write!(write, " -> {}", type_name)?;
+            },
+          Schema::Enumeration(ref labels) =>
+            for tag in 0..labels.len() {
+              write!(write, ", \"{}\" :: {}", tag, type_name)?;
+            },
+          _ => unreachable!(),
+        }
+        write!(write, " }}\n")?;
+      }
+      write!(write, "  }}\n\n")?;
+      write!(write, "runtime :: Runtime\n")?;
+      write!(write, "runtime =\n")?;
+      write!(write, "  {{ left: Left\n")?;
+      write!(write, "  , right: Right\n")?;
+      write!(write, "  , either: either\n")?;
+      write!(write, "  , nothing: Nothing\n")?;
+      write!(write, "  , just: Just\n")?;
+      write!(write, "  , maybe: maybe\n")?;
+      for &&(ref name, ref schema) in &sums {
+        let type_name = escape::purescript_type_name(name)?;
+        write!(write, "  , {}: {{ match: {}",
+               escape::purescript_string(&type_name),
+               escape::purescript_value_name(&format!("match_{}", name))?)?;
+        for (tag, constructor) in purescript_constructors(name, schema)?
+                                  .iter().enumerate() {
+          write!(write, ", \"{}\": {}", tag, constructor)?;
+        }
+        write!(write, " }}\n")?;
+      }
+      write!(write, "  }}\n\n")?;
+    }
+
+    for (name, &(has_schema, call)) in &self.calls {
+      let (input_schema, output_schema) = has_schema.schema()?;
+      let name = escape::purescript_identifier(name)?;
+      let argument = if runtime { " runtime" } else { "" };
+      match self.convention(call)? {
+        ECMAScriptConvention::Synchronous if !runtime => {
+          write!(write, "foreign import {} :: forall context. context -> ",
+                 name)?;
+          purescript_type(write, &input_schema)?;
+          write!(write, " -> Effect ")?;
+          purescript_type(write, &output_schema)?;
+          write!(write, "\n\n")?;
+        },
+        ECMAScriptConvention::Synchronous => {
+          write!(write, "foreign import {}Impl :: forall context. Runtime -> ",
+                 name)?;
+          write!(write, "context -> ")?;
+          purescript_type(write, &input_schema)?;
+          write!(write, " -> Effect ")?;
+          purescript_type(write, &output_schema)?;
+          write!(write, "\n\n")?;
+          write!(write, "{} :: forall context. context -> ", name)?;
+          purescript_type(write, &input_schema)?;
+          write!(write, " -> Effect ")?;
+          purescript_type(write, &output_schema)?;
+          write!(write, "\n")?;
+          write!(write, "{0} = {0}Impl runtime\n\n", name)?;
+        },
+        ECMAScriptConvention::Asynchronous | ECMAScriptConvention::Promise => {
+          write!(write, "foreign import {}Impl :: forall context. ", name)?;
+          if runtime {
+            write!(write, "Runtime -> ")?;
+          }
+          write!(write, "context -> ")?;
+          purescript_type(write, &input_schema)?;
+          write!(write, " -> (")?;
+          purescript_type(write, &output_schema)?;
+          write!(write, " -> Effect Unit) -> (Error -> Effect Unit) -> ")?;
+          write!(write, "Effect Unit\n\n")?;
+          write!(write, "{} :: forall context. context -> ", name)?;
+          purescript_type(write, &input_schema)?;
+          write!(write, " -> Aff ")?;
+          purescript_type(write, &output_schema)?;
+          write!(write, "\n")?;
+          write!(write, "{} context input = makeAff \\callback -> do\n", name)?;
+          write!(write, "  {}Impl{} context input (callback <<< Right) ", name,
+                 argument)?;
+          write!(write, "(callback <<< Left)\n")?;
+          write!(write, "  pure nonCanceler\n\n")?;
+        },
+      }
+    }
     Ok(())
   }
 
-  pub fn purescript(&self, write: &mut io::Write) -> io::Result<()> {
-    for (name, &(ref has_schema, ref call)) in &self.calls {
+  /// Generate the foreign ECMAScript module that implements the foreign
+  /// imports of the module generated by `purescript`. It contains the calls
+  /// themselves, curried as PureScript expects, and converts their input and
+  /// output between the ECMAScript and PureScript representations.
+  pub fn purescript_foreign(&self, write: &mut io::Write) -> Result<()> {
+    let (named, usage) = self.purescript_usage()?;
+    let runtime = usage.either || usage.maybe
+      || named.iter().any(|&(_, ref schema)| is_sum(schema));
+    self.import(write)?;
+    for &arity in &usage.tuples {
+      let variables = (1..arity + 1)
+        .map(|index| format!("a{}", index))
+        .collect::<Vec<_>>();
+      write!(write, "export const tuple{} = ", arity)?;
+      for variable in &variables {
+        write!(write, "function({}) {{ return ", variable)?;
+      }
+      write!(write, "[{}]", variables.join(", "))?;
+      for _ in &variables {
+        write!(write, "; }}")?;
+      }
+      write!(write, ";\n")?;
+      write!(write, "export const fromTuple{} = function(f) {{ ", arity)?;
+      write!(write, "return function(tuple) {{ return f")?;
+      for index in 0..arity {
+        write!(write, "(tuple[{}])", index)?;
+      }
+      write!(write, "; }}; }};\n")?;
+    }
+
+    for (name, &(has_schema, call)) in &self.calls {
       let (input_schema, output_schema) = has_schema.schema()?;
-      write!(write, "foreign import {} :: ", name)?;
-      purescript_type(write, &input_schema)?;
-      write!(write, " -> {} ", match call.ecmascript_convention()? {
-        ECMAScriptConvention::Synchronous => "IOSync",
-        ECMAScriptConvention::Asynchronous => "IO",
-      })?;
-      purescript_type(write, &output_schema)?;
-      write!(write, "\n")?;
+      let name = escape::ecmascript_identifier(
+        &escape::purescript_identifier(name)?)?;
+      let convention = self.convention(call)?;
+      let convert_input =
+        needs_conversion(&input_schema, Conversion::FromPureScript);
+      let convert_output =
+        needs_conversion(&output_schema, Conversion::ToPureScript);
+      if convention == ECMAScriptConvention::Synchronous && !runtime {
+        write!(write, "export const {} = (function(call) {{\n", name)?;
+      } else {
+        write!(write, "export const {}Impl = (function(call) {{\n", name)?;
+      }
+      if runtime {
+        write!(write, "return function(runtime) {{\n")?;
+      }
+      if convert_input {
+        write!(write, "var fromPureScript = ")?;
+        purescript_conversion(write, &input_schema,
+                              Conversion::FromPureScript)?;
+        write!(write, ";\n")?;
+      }
+      if convert_output {
+        write!(write, "var toPureScript = ")?;
+        purescript_conversion(write, &output_schema,
+                              Conversion::ToPureScript)?;
+        write!(write, ";\n")?;
+      }
+      write!(write, "return function(context) {{\n")?;
+      write!(write, "return function(input) {{\n")?;
+      let output = if convert_output { "toPureScript(output)" } else { "output" };
+      match convention {
+        ECMAScriptConvention::Synchronous => {
+          write!(write, "return function() {{\n")?;
+          if convert_input {
+            write!(write, "input = fromPureScript(input);\n")?;
+          }
+          write!(write, "var output = call(context, input);\n")?;
+          write!(write, "return {};\n", output)?;
+          write!(write, "}};\n")?;
+        },
+        ECMAScriptConvention::Asynchronous | ECMAScriptConvention::Promise => {
+          let succeed =
+            format!("function(output) {{ onSuccess({})(); }}", output);
+          let fail = "function(error) { onError(error)(); }";
+          write!(write, "return function(onSuccess) {{\n")?;
+          write!(write, "return function(onError) {{\n")?;
+          write!(write, "return function() {{\n")?;
+          write!(write, "var converted;\n")?;
+          if convert_input {
+            write!(write, "try {{\n")?;
+            write!(write, "converted = fromPureScript(input);\n")?;
+            write!(write, "}} catch (error) {{\n")?;
+            write!(write, "onError(error)();\n")?;
+            write!(write, "return;\n")?;
+            write!(write, "}}\n")?;
+          } else {
+            write!(write, "converted = input;\n")?;
+          }
+          if convention == ECMAScriptConvention::Asynchronous {
+            write!(write, "call(context, converted, {}, {});\n", succeed,
+                   fail)?;
+          } else {
+            write!(write, "call(context, converted).then({}, {});\n", succeed,
+                   fail)?;
+          }
+          write!(write, "}};\n")?;
+          write!(write, "}};\n")?;
+          write!(write, "}};\n")?;
+        },
+      }
+      write!(write, "}};\n")?;
+      write!(write, "}};\n")?;
+      if runtime {
+        write!(write, "}};\n")?;
+      }
+      write!(write, "}})(")?;
+      self.call(write, call)?;
+      write!(write, ");\n")?;
     }
     Ok(())
   }
+
+  /// Return the named schemas of the calls, and what their PureScript types
+  /// refer to.
+  fn purescript_usage(&self)
+    -> Result<(Vec<(String, Schema)>, PureScriptUsage)> {
+    let mut named = vec![];
+    let mut usage = PureScriptUsage::default();
+    for &(ref has_schema, _) in self.calls.values() {
+      let (input_schema, output_schema) = has_schema.schema()?;
+      named_schemas(&input_schema, &mut named)?;
+      named_schemas(&output_schema, &mut named)?;
+      purescript_usage(&input_schema, &mut usage);
+      purescript_usage(&output_schema, &mut usage);
+    }
+    Ok((named, usage))
+  }
+}
+
+/// What the PureScript types of a module refer to, so that only the needed
+/// modules are imported and the needed tuple types are declared.
+#[derive(Default)]
+struct PureScriptUsage {
+  tuples: BTreeSet<usize>,
+  either: bool,
+  maybe: bool,
+  uint8_array: bool,
+  foreign: bool,
+}
+
+fn purescript_usage(schema: &Schema, usage: &mut PureScriptUsage) {
+  match *schema {
+    Schema::AllOf(ref elements) => {
+      usage.tuples.insert(elements.len());
+      for element in elements {
+        purescript_usage(element, usage);
+      }
+    },
+    Schema::OneOf(ref elements) => {
+      usage.either |= elements.len() > 1;
+      for element in elements {
+        purescript_usage(element, usage);
+      }
+    },
+    Schema::Record(ref fields) =>
+      for &(_, ref field) in fields {
+        purescript_usage(field, usage);
+      },
+    // Named alternatives are sum types rather than Eithers.
+    Schema::Named(_, ref element) => match **element {
+      Schema::OneOf(ref elements) =>
+        for element in elements {
+          purescript_usage(element, usage);
+        },
+      ref element => purescript_usage(element, usage),
+    },
+    Schema::ArrayOf(ref element) => purescript_usage(element, usage),
+    Schema::Optional(ref element) => {
+      usage.maybe = true;
+      purescript_usage(element, usage);
+    },
+    Schema::ByteString => usage.uint8_array = true,
+    Schema::Json => usage.foreign = true,
+    _ => (),
+  }
+}
+
+/// Whether a named schema is declared as a PureScript newtype.
+fn is_newtype(schema: &Schema) -> bool {
+  match *schema {
+    Schema::Record(_) => false,
+    _ => !is_sum(schema),
+  }
+}
+
+/// Whether a named schema is declared as a PureScript sum type.
+fn is_sum(schema: &Schema) -> bool {
+  match *schema {
+    Schema::OneOf(ref alternatives) => !alternatives.is_empty(),
+    Schema::Enumeration(ref labels) => !labels.is_empty(),
+    _ => false,
+  }
+}
+
+/// Return the names of the constructors of the sum type declared for a named
+/// schema, in order of tag or label.
+fn purescript_constructors(name: &str, schema: &Schema)
+  -> Result<Vec<String>> {
+  let type_name = escape::purescript_type_name(name)?;
+  let constructors = match *schema {
+    Schema::OneOf(ref alternatives) => (0..alternatives.len())
+      .map(|tag| format!("{}{}", type_name, tag))
+      .collect::<Vec<_>>(),
+    Schema::Enumeration(ref labels) => labels.iter()
+      .map(|label| escape::purescript_type_name(&format!("{}_{}", name, label)))
+      .collect::<Result<Vec<_>>>()?,
+    _ => vec![],
+  };
+  for (index, constructor) in constructors.iter().enumerate() {
+    if constructors[..index].contains(constructor) {
+      return Err(Error::InvalidName(format!(
+        "The PureScript type {} would have more than one constructor named {}.",
+        type_name, constructor)));
+    }
+  }
+  Ok(constructors)
 }
 
-fn purescript_type(write: &mut io::Write, schema: &Schema) -> io::Result<()> {
+/// Collect the named schemas occurring in a schema, each name once. It is an
+/// error for a name to be used for different schemas.
+fn named_schemas(schema: &Schema, named: &mut Vec<(String, Schema)>)
+  -> Result<()> {
+  match *schema {
+    Schema::AllOf(ref elements) | Schema::OneOf(ref elements) =>
+      for element in elements {
+        named_schemas(element, named)?;
+      },
+    Schema::Record(ref fields) =>
+      for &(_, ref field) in fields {
+        named_schemas(field, named)?;
+      },
+    Schema::ArrayOf(ref element) | Schema::Optional(ref element) =>
+      named_schemas(element, named)?,
+    Schema::Named(ref name, ref schema) => {
+      named_schemas(schema, named)?;
+      match named.iter().find(|&&(ref other, _)| other == name) {
+        None => named.push((name.clone(), (**schema).clone())),
+        Some(&(_, ref other)) if other == &**schema => (),
+        Some(_) => return Err(Error::InvalidName(format!(
+          "The name {} is used for different schemas.", name))),
+      }
+    },
+    _ => (),
+  }
+  Ok(())
+}
+
+/// Generate a PureScript declaration for a named schema. Records are declared
+/// as type synonyms, alternatives as sum types with a constructor for each
+/// alternative and an eliminator, enumerations as sum types with a nullary
+/// constructor for each label, an eliminator and `Eq` and `Ord` instances, and
+/// all other schemas as newtypes with a `Newtype` instance, which share their
+/// runtime representation with the wrapped type.
+fn purescript_declaration(write: &mut io::Write, name: &str, schema: &Schema)
+  -> Result<()> {
+  let type_name = escape::purescript_type_name(name)?;
+  if !is_sum(schema) {
+    if let Schema::Record(_) = *schema {
+      write!(write, "type {} = ", type_name)?;
+      purescript_type(write, schema)?;
+      write!(write, "\n\n")?;
+    } else {
+      write!(write, "newtype {0} = {0} ", type_name)?;
+      purescript_type(write, schema)?;
+      write!(write, "\n\n")?;
+      write!(write, "derive instance newtype{0} :: Newtype {0} _\n\n",
+             type_name)?;
+    }
+    return Ok(());
+  }
+
+  let constructors = purescript_constructors(name, schema)?;
+  write!(write, "data {}\n", type_name)?;
+  for (tag, constructor) in constructors.iter().enumerate() {
+    write!(write, "  {} {}", if tag == 0 { "=" } else { "|" }, constructor)?;
+    if let Schema::OneOf(ref alternatives) = *schema {
+      write!(write, " ")?;
+      purescript_type(write, &alternatives[tag])?;
+    }
+    write!(write, "\n")?;
+  }
+  write!(write, "\n")?;
+  if let Schema::Enumeration(_) = *schema {
+    write!(write, "derive instance eq{0} :: Eq {0}\n", type_name)?;
+    write!(write, "derive instance ord{0} :: Ord {0}\n\n", type_name)?;
+  }
+  let eliminator = escape::purescript_value_name(&format!("match_{}", name))?;
+  write!(write, "{} :: ", eliminator)?;
+  purescript_match_type(write, &type_name, schema)?;
+  write!(write, "\n")?;
+  for (tag, constructor) in constructors.iter().enumerate() {
+    write!(write, "{}", eliminator)?;
+    for other in 0..constructors.len() {
+      if other == tag {
+        write!(write, " f")?;
+      } else {
+        write!(write, " _")?;
+      }
+    }
+    if let Schema::OneOf(_) = *schema {
+      write!(write, " ({} value) = f value\n", constructor)?;
+    } else {
+      write!(write, " {} = f\n", constructor)?;
+    }
+  }
+  write!(write, "\n")?;
+  Ok(())
+}
+
+/// Generate the type of the eliminator of a sum type, which takes a function
+/// for each alternative, or a value for each label of an enumeration.
+fn purescript_match_type(write: &mut io::Write, type_name: &str,
+                         schema: &Schema) -> Result<()> {
+  write!(write, "forall r. ")?;
+  match *schema {
+    Schema::OneOf(ref alternatives) =>
+      for alternative in alternatives {
+        write!(write, "(")?;
+        purescript_type(write, alternative)?;
+        write!(write, " -> r) -> ")?;
+      },
+    Schema::Enumeration(ref labels) =>
+      for _ in labels {
+        write!(write, "r -> ")?;
+      },
+    _ => unreachable!(),
+  }
+  write!(write, "{} -> r", type_name)?;
+  Ok(())
+}
+
+fn purescript_type(write: &mut io::Write, schema: &Schema) -> Result<()> {
   match *schema {
     Schema::AllOf(ref elements) => {
       write!(write, "(Tuple{}", elements.len())?;
       for element in elements {
         write!(write, " ")?;
-        purescript_type(write, element);
+        purescript_type(write, element)?;
+      }
+      write!(write, ")")?;
+    },
+    Schema::Record(ref fields) => {
+      write!(write, "{{")?;
+      for (index, &(ref name, ref field)) in fields.iter().enumerate() {
+        write!(write, "{} {} :: ", if index == 0 { "" } else { "," },
+               escape::purescript_label(name))?;
+        purescript_type(write, field)?;
+      }
+      write!(write, " }}")?;
+    },
+    Schema::ArrayOf(ref element) => {
+      write!(write, "(Array ")?;
+      purescript_type(write, element)?;
+      write!(write, ")")?;
+    },
+    Schema::Optional(ref element) => {
+      write!(write, "(Maybe ")?;
+      purescript_type(write, element)?;
+      write!(write, ")")?;
+    },
+    Schema::OneOf(ref alternatives) => match alternatives.split_first() {
+      None => write!(write, "Void")?,
+      Some((alternative, &[])) => purescript_type(write, alternative)?,
+      Some((alternative, rest)) => {
+        write!(write, "(Either ")?;
+        purescript_type(write, alternative)?;
+        write!(write, " ")?;
+        purescript_type(write, &Schema::OneOf(rest.to_vec()))?;
+        write!(write, ")")?;
+      },
+    },
+    Schema::Named(ref name, _) =>
+      write!(write, "{}", escape::purescript_type_name(name)?)?,
+    Schema::Enumeration(_) => write!(write, "String")?,
+    Schema::Boolean => write!(write, "Boolean")?,
+    Schema::SignedInteger(_, _) => write!(write, "Int")?, // FIXME: Bounds.
+    Schema::SignedInteger64(_, _) => write!(write, "String")?,
+    Schema::Decimal => write!(write, "String")?,
+    Schema::SinglePrecision | Schema::DoublePrecision =>
+      write!(write, "Number")?,
+    Schema::ByteString => write!(write, "Uint8Array")?,
+    Schema::String | Schema::Uuid => write!(write, "String")?,
+    Schema::Date
+    | Schema::Timestamp
+    | Schema::TimestampWithTimeZone
+    | Schema::Interval => write!(write, "String")?,
+    Schema::Json => write!(write, "Foreign")?,
+  }
+  Ok(())
+}
+
+/// Whether a conversion goes from the ECMAScript representation of data to
+/// the PureScript one, or back.
+#[derive(Clone, Copy, PartialEq)]
+enum Conversion {
+  ToPureScript,
+  FromPureScript,
+}
+
+/// Whether data conforming to a schema must be converted between its
+/// ECMAScript and PureScript representations. Alternatives are tagged objects,
+/// named enumerations are labels and optional data is nullable in ECMAScript,
+/// and single-precision numbers are checked to be in range on their way out
+/// of PureScript, whose numbers are double-precision.
+fn needs_conversion(schema: &Schema, conversion: Conversion) -> bool {
+  match *schema {
+    Schema::AllOf(ref elements) =>
+      elements.iter().any(|element| needs_conversion(element, conversion)),
+    Schema::Record(ref fields) =>
+      fields.iter().any(|&(_, ref field)| needs_conversion(field, conversion)),
+    Schema::Named(_, ref element) if is_sum(element) => true,
+    Schema::Optional(_) => true,
+    Schema::ArrayOf(ref element)
+    | Schema::Named(_, ref element) => needs_conversion(element, conversion),
+    Schema::OneOf(ref alternatives) => !alternatives.is_empty(),
+    Schema::SinglePrecision => conversion == Conversion::FromPureScript,
+    _ => false,
+  }
+}
+
+/// Generate an ECMAScript expression that evaluates to a function that
+/// converts data conforming to a schema between its ECMAScript and PureScript
+/// representations, using the constructors and eliminators in `runtime`.
+fn purescript_conversion(write: &mut io::Write, schema: &Schema,
+                         conversion: Conversion) -> Result<()> {
+  if !needs_conversion(schema, conversion) {
+    write!(write, "(function(value) {{ return value; }})")?;
+    return Ok(());
+  }
+  write!(write, "(function(value) {{\n")?;
+  match *schema {
+    Schema::AllOf(ref elements) => {
+      write!(write, "return [\n")?;
+      for (index, element) in elements.iter().enumerate() {
+        purescript_conversion(write, element, conversion)?;
+        write!(write, "(value[{}]),\n", index)?;
+      }
+      write!(write, "];\n")?;
+    },
+    Schema::Record(ref fields) => {
+      write!(write, "return {{\n")?;
+      for &(ref name, ref field) in fields {
+        let key = escape::ecmascript_string(name);
+        write!(write, "{}: ", key)?;
+        purescript_conversion(write, field, conversion)?;
+        write!(write, "(value[{}]),\n", key)?;
+      }
+      write!(write, "}};\n")?;
+    },
+    Schema::ArrayOf(ref element) => {
+      write!(write, "var element = ")?;
+      purescript_conversion(write, element, conversion)?;
+      write!(write, ";\n")?;
+      write!(write, "return value.map(function(value) {{\n")?;
+      write!(write, "return element(value);\n")?;
+      write!(write, "}});\n")?;
+    },
+    Schema::Optional(ref element) => match conversion {
+      Conversion::ToPureScript => {
+        write!(write, "if (value === null || value === undefined) {{\n")?;
+        write!(write, "return runtime.nothing;\n")?;
+        write!(write, "}}\n")?;
+        write!(write, "return runtime.just(")?;
+        purescript_conversion(write, element, conversion)?;
+        write!(write, "(value));\n")?;
+      },
+      Conversion::FromPureScript => {
+        write!(write, "return runtime.maybe(null)(")?;
+        purescript_conversion(write, element, conversion)?;
+        write!(write, ")(value);\n")?;
+      },
+    },
+    Schema::Named(ref name, ref element) => match **element {
+      Schema::OneOf(ref alternatives) if !alternatives.is_empty() => {
+        let runtime = format!("runtime[{}]", escape::ecmascript_string(
+          &escape::purescript_type_name(name)?));
+        match conversion {
+          Conversion::ToPureScript => {
+            write!(write, "switch (value.tag) {{\n")?;
+            for (tag, alternative) in alternatives.iter().enumerate() {
+              write!(write, "case {0}: return {1}[{0}](", tag, runtime)?;
+              purescript_conversion(write, alternative, conversion)?;
+              write!(write, "(value.value));\n")?;
+            }
+            write!(write, "}}\n")?;
+          },
+          Conversion::FromPureScript => {
+            write!(write, "return {}.match", runtime)?;
+            for (tag, alternative) in alternatives.iter().enumerate() {
+              write!(write, "(function(value) {{ return {{tag: {}, value: ",
+                     tag)?;
+              purescript_conversion(write, alternative, conversion)?;
+              write!(write, "(value)}}; }})")?;
+            }
+            write!(write, "(value);\n")?;
+          },
+        }
+      },
+      Schema::Enumeration(ref labels) if !labels.is_empty() => {
+        let runtime = format!("runtime[{}]", escape::ecmascript_string(
+          &escape::purescript_type_name(name)?));
+        match conversion {
+          Conversion::ToPureScript => {
+            write!(write, "switch (value) {{\n")?;
+            for (tag, label) in labels.iter().enumerate() {
+              write!(write, "case {}: return {}[{}];\n",
+                     escape::ecmascript_string(label), runtime, tag)?;
+            }
+            write!(write, "}}\n")?;
+            write!(write, "throw new RangeError({} + value);\n",
+                   escape::ecmascript_string(
+                     &format!("Unexpected label of {}: ", name)))?;
+          },
+          Conversion::FromPureScript => {
+            write!(write, "return {}.match", runtime)?;
+            for label in labels {
+              write!(write, "({})", escape::ecmascript_string(label))?;
+            }
+            write!(write, "(value);\n")?;
+          },
+        }
+      },
+      ref element => {
+        write!(write, "return ")?;
+        purescript_conversion(write, element, conversion)?;
+        write!(write, "(value);\n")?;
+      },
+    },
+    // Alternatives are nested Eithers: the last alternative is a Right in as
+    // many Rights as there are alternatives before it, and each other
+    // alternative is a Left in as many Rights as there are alternatives
+    // before it.
+    Schema::OneOf(ref alternatives) => match conversion {
+      Conversion::ToPureScript => {
+        write!(write, "switch (value.tag) {{\n")?;
+        for (tag, alternative) in alternatives.iter().enumerate() {
+          let last = tag + 1 == alternatives.len();
+          write!(write, "case {}: return ", tag)?;
+          for _ in 0..tag {
+            write!(write, "runtime.right(")?;
+          }
+          if !last {
+            write!(write, "runtime.left(")?;
+          }
+          purescript_conversion(write, alternative, conversion)?;
+          write!(write, "(value.value)")?;
+          for _ in 0..if last { tag } else { tag + 1 } {
+            write!(write, ")")?;
+          }
+          write!(write, ";\n")?;
+        }
+        write!(write, "}}\n")?;
+      },
+      Conversion::FromPureScript => {
+        write!(write, "return ")?;
+        for (tag, alternative) in alternatives.iter().enumerate() {
+          if tag + 1 < alternatives.len() {
+            write!(write, "runtime.either(")?;
+          }
+          write!(write, "function(value) {{ return {{tag: {}, value: ", tag)?;
+          purescript_conversion(write, alternative, conversion)?;
+          write!(write, "(value)}}; }}")?;
+          if tag + 1 < alternatives.len() {
+            write!(write, ")(")?;
+          }
+        }
+        for _ in 1..alternatives.len() {
+          write!(write, ")")?;
+        }
+        write!(write, "(value);\n")?;
+      },
+    },
+    Schema::SinglePrecision => {
+      write!(write, "if (Math.abs(value) > 3.4028234663852886e38) {{\n")?;
+      write!(write, "throw new RangeError('The number ' + value + ")?;
+      write!(write, "' is out of the single-precision range.');\n")?;
+      write!(write, "}}\n")?;
+      write!(write, "return value;\n")?;
+    },
+    _ => unreachable!(),
+  }
+  write!(write, "}})")?;
+  Ok(())
+}
+
+/// Write untyped ECMAScript code to a TypeScript module, ignoring type errors
+/// on each of its lines.
+fn typescript_untyped(write: &mut io::Write, code: &[u8]) -> Result<()> {
+  for line in String::from_utf8_lossy(code).lines() {
+    write!(write, "// @ts-ignore\n{}\n", line)?;
+  }
+  Ok(())
+}
+
+/// Generate the TypeScript type of the ECMAScript representation of data
+/// conforming to a schema.
+fn typescript_type(write: &mut io::Write, schema: &Schema) -> Result<()> {
+  match *schema {
+    Schema::AllOf(ref elements) => {
+      write!(write, "[")?;
+      for (index, element) in elements.iter().enumerate() {
+        write!(write, "{}", if index == 0 { "" } else { ", " })?;
+        typescript_type(write, element)?;
+      }
+      write!(write, "]")?;
+    },
+    Schema::Record(ref fields) => {
+      write!(write, "{{")?;
+      for &(ref name, ref field) in fields {
+        write!(write, " {}: ", escape::ecmascript_property(name))?;
+        typescript_type(write, field)?;
+        write!(write, ";")?;
+      }
+      write!(write, " }}")?;
+    },
+    Schema::ArrayOf(ref element) => {
+      write!(write, "Array<")?;
+      typescript_type(write, element)?;
+      write!(write, ">")?;
+    },
+    Schema::Optional(ref element) => {
+      write!(write, "(")?;
+      typescript_type(write, element)?;
+      write!(write, " | null)")?;
+    },
+    Schema::OneOf(ref alternatives) if alternatives.is_empty() =>
+      write!(write, "never")?,
+    Schema::OneOf(ref alternatives) => {
+      write!(write, "(")?;
+      for (tag, alternative) in alternatives.iter().enumerate() {
+        write!(write, "{}{{ tag: {}; value: ", if tag == 0 { "" } else { " | " },
+               tag)?;
+        typescript_type(write, alternative)?;
+        write!(write, "; }}")?;
       }
       write!(write, ")")?;
+    },
+    Schema::Named(ref name, _) =>
+      write!(write, "{}", escape::ecmascript_type_name(name)?)?,
+    Schema::Enumeration(ref labels) if labels.is_empty() =>
+      write!(write, "never")?,
+    Schema::Enumeration(ref labels) => {
+      write!(write, "(")?;
+      for (index, label) in labels.iter().enumerate() {
+        write!(write, "{}{}", if index == 0 { "" } else { " | " },
+               escape::ecmascript_string(label))?;
+      }
+      write!(write, ")")?;
+    },
+    Schema::Boolean => write!(write, "boolean")?,
+    Schema::SignedInteger(_, _) => write!(write, "number")?,
+    Schema::SignedInteger64(_, _) => write!(write, "string")?,
+    Schema::Decimal => write!(write, "string")?,
+    Schema::SinglePrecision | Schema::DoublePrecision => write!(write, "number")?,
+    Schema::ByteString => write!(write, "Uint8Array")?,
+    Schema::String | Schema::Uuid => write!(write, "string")?,
+    Schema::Date
+    | Schema::Timestamp
+    | Schema::TimestampWithTimeZone
+    | Schema::Interval => write!(write, "string")?,
+    Schema::Json => write!(write, "unknown")?,
+  }
+  Ok(())
+}
+
+/// Names that generated Rust modules use themselves, and which cannot be
+/// declared as types.
+const RUST_TAKEN: &'static [&'static str] = &[
+  "Box", "Bytes", "Deserialize", "Error", "Int64", "Option", "PostgresEnum",
+  "PostgresInt64", "PostgresJson", "PostgresText", "Result", "Serialize",
+  "String", "Vec",
+];
+
+/// The declaration of the type of 64-bit integers in generated Rust modules.
+const RUST_INT64: &'static str = r#"
+/// A 64-bit integer, serialized as a string of decimal digits.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Int64(pub i64);
+
+impl Serialize for Int64 {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Int64 {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map(Int64).map_err(serde::de::Error::custom)
+    }
+}
+"#;
+
+/// The declaration of the type of byte strings in generated Rust modules.
+const RUST_BYTES: &'static str = r#"
+/// A byte string, serialized as a base64 string.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Bytes(pub Vec<u8>);
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+impl Serialize for Bytes {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut text = String::with_capacity((self.0.len() + 2) / 3 * 4);
+        for chunk in self.0.chunks(3) {
+            let mut bits = 0u32;
+            for (index, &byte) in chunk.iter().enumerate() {
+                bits |= u32::from(byte) << (16 - 8 * index);
+            }
+            for index in 0..4 {
+                if index <= chunk.len() {
+                    text.push(BASE64[(bits >> (18 - 6 * index) & 63) as usize] as char);
+                } else {
+                    text.push('=');
+                }
+            }
+        }
+        serializer.serialize_str(&text)
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let invalid = || <D::Error as serde::de::Error>::custom("invalid base64 string");
+        if text.len() % 4 != 0 {
+            return Err(invalid());
+        }
+        let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+        for (index, chunk) in text.as_bytes().chunks(4).enumerate() {
+            let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+            if padding > 2 || padding > 0 && (index + 1) * 4 < text.len() {
+                return Err(invalid());
+            }
+            let mut bits = 0u32;
+            for (index, &c) in chunk[..4 - padding].iter().enumerate() {
+                let value = BASE64.iter().position(|&d| d == c).ok_or_else(invalid)?;
+                bits |= (value as u32) << (18 - 6 * index);
+            }
+            bytes.extend_from_slice(&bits.to_be_bytes()[1..4 - padding]);
+        }
+        Ok(Bytes(bytes))
+    }
+}
+"#;
+
+/// A Rust module with calls.
+pub struct RustModule<'a> {
+  /// The calls, by name. They are generated in order of name, so that the
+  /// same calls always give the same code.
+  pub calls: BTreeMap<String, (&'a HasSchema, &'a Rust)>,
+}
+
+impl<'a> RustModule<'a> {
+  /// Generate a Rust module for the calls, which are public functions named
+  /// after the calls in snake case. Their first parameter is the context,
+  /// such as a database connection or a URL, followed by the input.
+  ///
+  /// The records, alternatives and enumerations in the schemas of the calls,
+  /// and the named schemas, are declared as types that Serde serializes as
+  /// the ECMAScript serialization functions do. Records are structs,
+  /// alternatives are untagged enums, so that the first alternative that data
+  /// conforms to is taken when deserializing, and integers are the narrowest
+  /// of `i8`, `i16` and `i32` that their bounds fit in. 64-bit integers and
+  /// byte strings are the declared types `Int64` and `Bytes`.
+  ///
+  /// The generated module depends on the crates `serde`, with derive, and
+  /// `serde_json`. Queries depend on `postgres` 0.14, and web services on
+  /// `reqwest` 0.9.
+  pub fn rust(&self, write: &mut io::Write) -> Result<()> {
+    let mut declarations = vec![];
+    let mut usage = RustUsage::default();
+    let mut functions = BTreeSet::new();
+    let mut support = vec![];
+    for (name, &(has_schema, call)) in &self.calls {
+      let (input_schema, output_schema) = has_schema.schema()?;
+      rust_declarations(&input_schema, &rust_hint(name, "input"),
+                        &mut declarations, &mut usage)?;
+      rust_declarations(&output_schema, &rust_hint(name, "output"),
+                        &mut declarations, &mut usage)?;
+      if !functions.insert(escape::rust_value_name(name)?) {
+        return Err(Error::InvalidName(format!(
+          "The Rust function {} is generated for more than one call.",
+          escape::rust_value_name(name)?)));
+      }
+      for declaration in call.rust_support()? {
+        if !support.contains(&declaration) {
+          support.push(declaration);
+        }
+      }
+    }
+
+    if !declarations.is_empty() || usage.int64 || usage.bytes {
+      write!(write, "use serde::{{Deserialize, Serialize}};\n\n")?;
+    }
+    write!(write, "/// The error of a failed call.\n")?;
+    write!(write, "pub type Error = Box<dyn std::error::Error + Send + Sync>;\n")?;
+    if usage.int64 {
+      write!(write, "{}", RUST_INT64)?;
+    }
+    if usage.bytes {
+      write!(write, "{}", RUST_BYTES)?;
+    }
+    for declaration in support {
+      write!(write, "{}", declaration)?;
+    }
+    for &(ref type_name, ref hint, ref schema) in &declarations {
+      rust_declaration(write, type_name, hint, schema)?;
+    }
+
+    for (name, &(has_schema, call)) in &self.calls {
+      let (input_schema, output_schema) = has_schema.schema()?;
+      write!(write, "\n#[allow(unused_variables)]\n")?;
+      write!(write, "pub fn {}(context: {}, input: ",
+             escape::rust_value_name(name)?, call.rust_context()?)?;
+      rust_type(write, &input_schema, &rust_hint(name, "input"))?;
+      write!(write, ") -> Result<")?;
+      rust_type(write, &output_schema, &rust_hint(name, "output"))?;
+      write!(write, ", Error> {{\n")?;
+      call.rust_call(write, name)?;
+      write!(write, "}}\n")?;
+    }
+    Ok(())
+  }
+}
+
+/// Which of the declared types of generated Rust modules are used.
+#[derive(Default)]
+struct RustUsage {
+  int64: bool,
+  bytes: bool,
+}
+
+/// Collect the Rust types to declare for a schema with the given hint, each
+/// name once, paired with their hints. The same name cannot be declared for
+/// different schemas.
+fn rust_declarations(schema: &Schema, hint: &str,
+                     declarations: &mut Vec<(String, String, Schema)>,
+                     usage: &mut RustUsage) -> Result<()> {
+  match *schema {
+    Schema::AllOf(ref elements) =>
+      for (index, element) in elements.iter().enumerate() {
+        rust_declarations(element, &rust_hint(hint, &index.to_string()),
+                          declarations, usage)?;
+      },
+    Schema::Record(ref fields) => {
+      declare(hint, schema, declarations)?;
+      for &(ref name, ref field) in fields {
+        rust_declarations(field, &rust_hint(hint, name), declarations, usage)?;
+      }
+    },
+    Schema::OneOf(ref alternatives) => {
+      declare(hint, schema, declarations)?;
+      for (tag, alternative) in alternatives.iter().enumerate() {
+        rust_declarations(alternative, &rust_hint(hint, &tag.to_string()),
+                          declarations, usage)?;
+      }
+    },
+    Schema::Enumeration(_) => declare(hint, schema, declarations)?,
+    Schema::ArrayOf(ref element) =>
+      rust_declarations(element, &rust_hint(hint, "element"), declarations,
+                        usage)?,
+    Schema::Optional(ref element) =>
+      rust_declarations(element, hint, declarations, usage)?,
+    Schema::Named(ref name, ref element) => {
+      match **element {
+        Schema::Record(_) | Schema::OneOf(_) | Schema::Enumeration(_) => (),
+        _ => declare(name, schema, declarations)?,
+      }
+      rust_declarations(element, name, declarations, usage)?;
+    },
+    Schema::SignedInteger64(_, _) => usage.int64 = true,
+    Schema::ByteString => usage.bytes = true,
+    _ => (),
+  }
+  Ok(())
+}
+
+fn declare(hint: &str, schema: &Schema,
+           declarations: &mut Vec<(String, String, Schema)>) -> Result<()> {
+  let type_name = escape::rust_type_name(hint)?;
+  let taken = RUST_TAKEN.contains(&type_name.as_str());
+  match declarations.iter().find(|&&(ref other, _, _)| *other == type_name) {
+    Some(&(_, _, ref other)) if other == schema => Ok(()),
+    Some(_) => Err(taken_name(&type_name)),
+    None if taken => Err(taken_name(&type_name)),
+    None => {
+      declarations.push((type_name, hint.to_string(), schema.clone()));
       Ok(())
     },
-    Schema::OneOf(_) => unimplemented!(),
-    Schema::SignedInteger(_, _) => write!(write, "Int"), // FIXME: Bounds.
-    Schema::SinglePrecision => unimplemented!(),
-    Schema::DoublePrecision => write!(write, "Number"),
-    Schema::ByteString => write!(write, "ByteString"),
-    Schema::String => write!(write, "String"),
+  }
+}
+
+fn taken_name(type_name: &str) -> Error {
+  Error::InvalidName(format!(
+    "The Rust type {} would be declared for more than one schema.",
+    type_name))
+}
+
+/// Generate the declaration of a Rust type with the given name and hint.
+/// Records are structs, alternatives are enums with a variant for each
+/// alternative, enumerations are enums with a variant for each label, and
+/// other named schemas are type aliases.
+fn rust_declaration(write: &mut io::Write, type_name: &str, hint: &str,
+                    schema: &Schema) -> Result<()> {
+  match *schema {
+    Schema::Record(ref fields) => {
+      write!(write, "\n#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]\n")?;
+      write!(write, "pub struct {} {{\n", type_name)?;
+      let mut field_names = BTreeSet::new();
+      for &(ref name, ref field) in fields {
+        let field_name = escape::rust_value_name(name)?;
+        if !field_names.insert(field_name.clone()) {
+          return Err(Error::InvalidName(format!(
+            "The Rust type {} would have more than one field named {}.",
+            type_name, field_name)));
+        }
+        if field_name != *name {
+          write!(write, "    #[serde(rename = {})]\n", escape::rust_string(name))?;
+        }
+        write!(write, "    pub {}: ", field_name)?;
+        rust_type(write, field, &rust_hint(hint, name))?;
+        write!(write, ",\n")?;
+      }
+      write!(write, "}}\n")?;
+    },
+    Schema::OneOf(ref alternatives) => {
+      write!(write, "\n#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]\n")?;
+      write!(write, "#[serde(untagged)]\n")?;
+      write!(write, "pub enum {} {{\n", type_name)?;
+      for (tag, alternative) in alternatives.iter().enumerate() {
+        write!(write, "    Alternative{}(", tag)?;
+        rust_type(write, alternative, &rust_hint(hint, &tag.to_string()))?;
+        write!(write, "),\n")?;
+      }
+      write!(write, "}}\n")?;
+    },
+    Schema::Enumeration(ref labels) => {
+      write!(write, "\n#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ")?;
+      write!(write, "Serialize, Deserialize)]\n")?;
+      write!(write, "pub enum {} {{\n", type_name)?;
+      let mut variants = BTreeSet::new();
+      for label in labels {
+        let variant = escape::rust_type_name(label)?;
+        if !variants.insert(variant.clone()) {
+          return Err(Error::InvalidName(format!(
+            "The Rust type {} would have more than one variant named {}.",
+            type_name, variant)));
+        }
+        write!(write, "    #[serde(rename = {})]\n", escape::rust_string(label))?;
+        write!(write, "    {},\n", variant)?;
+      }
+      write!(write, "}}\n")?;
+    },
+    Schema::Named(_, ref element) => {
+      write!(write, "\npub type {} = ", type_name)?;
+      rust_type(write, element, hint)?;
+      write!(write, ";\n")?;
+    },
+    _ => unreachable!(),
+  }
+  Ok(())
+}
+
+/// Return the hint for a part of a schema with the given hint, such as a
+/// field or an element. Rust types are named after the hints of anonymous
+/// records, alternatives and enumerations.
+pub fn rust_hint(hint: &str, part: &str) -> String {
+  format!("{}_{}", hint, part)
+}
+
+/// Return the narrowest Rust integer type that fits the given bounds.
+pub fn rust_integer_type(minimum: i32, maximum: i32) -> &'static str {
+  if minimum >= i8::MIN as i32 && maximum <= i8::MAX as i32 {
+    "i8"
+  } else if minimum >= i16::MIN as i32 && maximum <= i16::MAX as i32 {
+    "i16"
+  } else {
+    "i32"
+  }
+}
+
+/// Generate the Rust type of data conforming to a schema with the given hint,
+/// as declared by `RustModule::rust`. Tuples are Rust tuples.
+pub fn rust_type(write: &mut io::Write, schema: &Schema, hint: &str)
+  -> Result<()> {
+  match *schema {
+    Schema::AllOf(ref elements) => {
+      write!(write, "(")?;
+      for (index, element) in elements.iter().enumerate() {
+        write!(write, "{}", if index == 0 { "" } else { ", " })?;
+        rust_type(write, element, &rust_hint(hint, &index.to_string()))?;
+      }
+      write!(write, "{})", if elements.len() == 1 { "," } else { "" })?;
+    },
+    Schema::Record(_) | Schema::OneOf(_) | Schema::Enumeration(_) =>
+      write!(write, "{}", escape::rust_type_name(hint)?)?,
+    Schema::ArrayOf(ref element) => {
+      write!(write, "Vec<")?;
+      rust_type(write, element, &rust_hint(hint, "element"))?;
+      write!(write, ">")?;
+    },
+    Schema::Optional(ref element) => {
+      write!(write, "Option<")?;
+      rust_type(write, element, hint)?;
+      write!(write, ">")?;
+    },
+    Schema::Named(ref name, _) =>
+      write!(write, "{}", escape::rust_type_name(name)?)?,
+    Schema::Boolean => write!(write, "bool")?,
+    Schema::SignedInteger(minimum, maximum) =>
+      write!(write, "{}", rust_integer_type(minimum, maximum))?,
+    Schema::SignedInteger64(_, _) => write!(write, "Int64")?,
+    Schema::SinglePrecision => write!(write, "f32")?,
+    Schema::DoublePrecision => write!(write, "f64")?,
+    Schema::ByteString => write!(write, "Bytes")?,
+    Schema::Decimal
+    | Schema::String
+    | Schema::Uuid
+    | Schema::Date
+    | Schema::Timestamp
+    | Schema::TimestampWithTimeZone
+    | Schema::Interval => write!(write, "String")?,
+    Schema::Json => write!(write, "serde_json::Value")?,
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use constant::Constant;
+  use std::i32;
+  use web_service::WebService;
+
+  /// A synchronous call with the given output schema and no input.
+  struct Fixed(Schema);
+
+  impl HasSchema for Fixed {
+    fn schema(&self) -> Result<(Rc<Schema>, Rc<Schema>)> {
+      Ok((Rc::new(Schema::AllOf(vec![])), Rc::new(self.0.clone())))
+    }
+  }
+
+  impl ECMAScript for Fixed {
+    fn ecmascript_call(&self, write: &mut io::Write) -> Result<()> {
+      write!(write, "(function() {{ return null; }})")?;
+      Ok(())
+    }
+
+    fn ecmascript_convention(&self) -> Result<ECMAScriptConvention> {
+      Ok(ECMAScriptConvention::Synchronous)
+    }
+  }
+
+  #[test]
+  fn test_purescript_named_alternatives() {
+    let score = Fixed(Schema::Named("score".to_string(), Box::new(
+      Schema::OneOf(vec![Schema::Boolean, Schema::String]))));
+    let mut calls = BTreeMap::new();
+    calls.insert("score".to_string(),
+                 (&score as &HasSchema, &score as &ECMAScript));
+    let module = ECMAScriptModule{calls: calls, asynchrony: Asynchrony::Callbacks};
+
+    let mut output = vec![];
+    module.purescript(&mut output, "Client").unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with(concat!(
+      "module Client where\n\n",
+      "import Prelude\n\n",
+      "import Data.Either (Either(..), either)\n",
+      "import Data.Maybe (Maybe(..), maybe)\n",
+      "import Effect (Effect)\n\n")));
+    assert!(output.contains("  { left :: forall a b. a -> Either a b\n"));
+    assert!(output.contains("foreign import scoreImpl :: forall context. "));
+
+    let service = WebService{name: "remote".to_string(), source: &score};
+    let mut calls = BTreeMap::new();
+    calls.insert("remote".to_string(),
+                 (&service as &HasSchema, &service as &ECMAScript));
+    let module = ECMAScriptModule{calls: calls, asynchrony: Asynchrony::Callbacks};
+
+    let mut output = vec![];
+    module.purescript(&mut output, "Client").unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with(concat!(
+      "module Client where\n\n",
+      "import Prelude\n\n",
+      "import Data.Either (Either(..), either)\n",
+      "import Data.Maybe (Maybe(..), maybe)\n",
+      "import Effect (Effect)\n",
+      "import Effect.Aff (Aff, makeAff, nonCanceler)\n",
+      "import Effect.Exception (Error)\n\n")));
+  }
+
+  #[test]
+  fn test_promises() {
+    let constant = Constant::Boolean(true);
+    let service = WebService{name: "flag".to_string(), source: &constant};
+    let mut calls = BTreeMap::new();
+    calls.insert("constant".to_string(),
+                 (&constant as &HasSchema, &constant as &ECMAScript));
+    calls.insert("flag".to_string(),
+                 (&service as &HasSchema, &service as &ECMAScript));
+    let module = ECMAScriptModule{calls: calls, asynchrony: Asynchrony::Promises};
+
+    let mut output = vec![];
+    module.typescript(&mut output, TypeScriptOutput::Declarations).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(
+      "export declare const constant: (context: any, input: []) => boolean;\n"));
+    assert!(output.contains(
+      "export declare const flag: (context: any, input: []) => Promise<boolean>;\n"));
+
+    let mut output = vec![];
+    module.ecmascript(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.matches("return new Promise(").count(), 1);
+
+    let mut output = vec![];
+    module.typescript(&mut output, TypeScriptOutput::Module).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(!output.contains("@ts-nocheck"));
+    assert!(output.starts_with(concat!(
+      "export const constant: (context: any, input: []) => boolean =\n",
+      "// @ts-ignore\n((function() {\n",
+      "// @ts-ignore\nreturn true;\n",
+      "// @ts-ignore\n})) as any;\n",
+      "export const flag: (context: any, input: []) => Promise<boolean> =\n")));
+    // Only the lines of the calls themselves are ignored.
+    let lines = output.lines().collect::<Vec<_>>();
+    for (index, line) in lines.iter().enumerate() {
+      if !line.starts_with("export const ") && *line != "// @ts-ignore" {
+        assert_eq!(lines[index - 1], "// @ts-ignore");
+      }
+    }
+  }
+
+  #[test]
+  fn test_purescript() {
+    let constant = Constant::Record(vec![
+      ("pair".to_string(), Constant::AllOf(vec![
+        Constant::Boolean(true),
+        Constant::String("a".to_string()),
+      ])),
+    ]);
+    let service = WebService{name: "remote".to_string(), source: &constant};
+    let mut calls = BTreeMap::new();
+    calls.insert("local".to_string(),
+                 (&constant as &HasSchema, &constant as &ECMAScript));
+    calls.insert("remote".to_string(),
+                 (&service as &HasSchema, &service as &ECMAScript));
+    let module = ECMAScriptModule{calls: calls, asynchrony: Asynchrony::Callbacks};
+
+    let mut output = vec![];
+    module.purescript(&mut output, "App.Client").unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with("module App.Client where\n\nimport Prelude\n"));
+    assert!(output.contains("import Effect.Aff (Aff, makeAff, nonCanceler)\n"));
+    assert!(output.contains(concat!(
+      "foreign import tuple2 :: forall a1 a2. a1 -> a2 -> Tuple2 a1 a2\n")));
+    assert!(output.contains(concat!(
+      "foreign import local :: forall context. context -> (Tuple0) -> ",
+      "Effect { pair :: (Tuple2 Boolean String) }\n")));
+    assert!(output.contains(concat!(
+      "remote :: forall context. context -> (Tuple0) -> ",
+      "Aff { pair :: (Tuple2 Boolean String) }\n",
+      "remote context input = makeAff \\callback -> do\n")));
+
+    let mut output = vec![];
+    module.purescript_foreign(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(concat!(
+      "export const tuple2 = function(a1) { return function(a2) { ",
+      "return [a1, a2]; }; };\n")));
+    assert!(output.contains("export const local = (function(call) {\n"));
+    assert!(output.contains("export const remoteImpl = (function(call) {\n"));
+
+    assert!(module.purescript(&mut vec![], "client").is_err());
+  }
+
+  #[test]
+  fn test_purescript_alternatives() {
+    let schema = Schema::Record(vec![
+      ("score".to_string(), Schema::Named("score".to_string(), Box::new(
+        Schema::OneOf(vec![Schema::SinglePrecision, Schema::String])))),
+      ("either".to_string(), Schema::OneOf(vec![
+        Schema::Boolean, Schema::String, Schema::SinglePrecision])),
+    ]);
+
+    let mut output = vec![];
+    purescript_type(&mut output, &schema).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), concat!(
+      "{ score :: Score, either :: (Either Boolean (Either String Number)) }"));
+
+    let mut output = vec![];
+    purescript_declaration(&mut output, "score", match schema {
+      Schema::Record(ref fields) => match fields[0].1 {
+        Schema::Named(_, ref schema) => schema,
+        _ => unreachable!(),
+      },
+      _ => unreachable!(),
+    }).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), concat!(
+      "data Score\n  = Score0 Number\n  | Score1 String\n\n",
+      "matchScore :: forall r. (Number -> r) -> (String -> r) -> Score -> r\n",
+      "matchScore f _ (Score0 value) = f value\n",
+      "matchScore _ f (Score1 value) = f value\n\n"));
+
+    let mut output = vec![];
+    purescript_conversion(&mut output, &schema, Conversion::ToPureScript)
+      .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("case 1: return runtime['Score'][1]("));
+    assert!(output.contains(concat!(
+      "case 2: return runtime.right(runtime.right(",
+      "(function(value) { return value; })(value.value)));")));
+    assert!(!output.contains("RangeError"));
+
+    let mut output = vec![];
+    purescript_conversion(&mut output, &schema, Conversion::FromPureScript)
+      .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(
+      "return runtime['Score'].match(function(value) {"));
+    assert!(output.contains(
+      "return runtime.either(function(value) { return {tag: 0, value: "));
+    assert!(output.contains("RangeError"));
+  }
+
+  #[test]
+  fn test_purescript_enumeration() {
+    let mood = Fixed(Schema::Named("mood".to_string(), Box::new(
+      Schema::Enumeration(vec!["sad".to_string(), "very-happy".to_string()]))));
+    let mut calls = BTreeMap::new();
+    calls.insert("mood".to_string(),
+                 (&mood as &HasSchema, &mood as &ECMAScript));
+    let module = ECMAScriptModule{calls: calls, asynchrony: Asynchrony::Callbacks};
+
+    let mut output = vec![];
+    module.purescript(&mut output, "Client").unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(!output.contains("import Data.Newtype"));
+    assert!(output.contains(concat!(
+      "data Mood\n  = MoodSad\n  | MoodVeryHappy\n\n",
+      "derive instance eqMood :: Eq Mood\n",
+      "derive instance ordMood :: Ord Mood\n\n",
+      "matchMood :: forall r. r -> r -> Mood -> r\n",
+      "matchMood f _ MoodSad = f\n",
+      "matchMood _ f MoodVeryHappy = f\n\n")));
+    assert!(output.contains(concat!(
+      "  , \"Mood\" :: { match :: forall r. r -> r -> Mood -> r, ",
+      "\"0\" :: Mood, \"1\" :: Mood }\n")));
+    assert!(output.contains(concat!(
+      "  , \"Mood\": { match: matchMood, ",
+      "\"0\": MoodSad, \"1\": MoodVeryHappy }\n")));
+
+    let mut output = vec![];
+    module.purescript_foreign(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("case 'very-happy': return runtime['Mood'][1];\n"));
+    assert!(output.contains(
+      "throw new RangeError('Unexpected label of mood: ' + value);\n"));
+
+    let mut output = vec![];
+    purescript_conversion(&mut output, &mood.0, Conversion::FromPureScript)
+      .unwrap();
+    assert!(String::from_utf8(output).unwrap().contains(
+      "return runtime['Mood'].match('sad')('very-happy')(value);\n"));
+
+    let clash = Schema::Enumeration(vec!["a-b".to_string(), "a_b".to_string()]);
+    assert!(purescript_declaration(&mut vec![], "clash", &clash).is_err());
+  }
+
+  #[test]
+  fn test_purescript_optional() {
+    let person = Fixed(Schema::Record(vec![
+      ("email".to_string(), Schema::Optional(Box::new(Schema::String))),
+    ]));
+    let mut calls = BTreeMap::new();
+    calls.insert("person".to_string(),
+                 (&person as &HasSchema, &person as &ECMAScript));
+    let module = ECMAScriptModule{calls: calls, asynchrony: Asynchrony::Callbacks};
+
+    let mut output = vec![];
+    module.purescript(&mut output, "Client").unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("import Data.Maybe (Maybe(..), maybe)\n"));
+    assert!(!output.contains("Nullable"));
+    assert!(output.contains("  , maybe :: forall a b. b -> (a -> b) -> Maybe a -> b\n"));
+    assert!(output.contains("  , nothing: Nothing\n  , just: Just\n"));
+    assert!(output.contains(concat!(
+      "person :: forall context. context -> (Tuple0) -> ",
+      "Effect { email :: (Maybe String) }\n")));
+
+    let mut output = vec![];
+    module.purescript_foreign(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(concat!(
+      "if (value === null || value === undefined) {\n",
+      "return runtime.nothing;\n}\n",
+      "return runtime.just((function(value) { return value; })(value));\n")));
+
+    let mut output = vec![];
+    purescript_conversion(&mut output, &person.0, Conversion::FromPureScript)
+      .unwrap();
+    assert!(String::from_utf8(output).unwrap().contains(concat!(
+      "return runtime.maybe(null)((function(value) { return value; }))",
+      "(value);\n")));
+  }
+
+  #[test]
+  fn test_named_schema_conflict() {
+    let a = Fixed(Schema::Named("status".to_string(), Box::new(
+      Schema::Enumeration(vec!["on".to_string()]))));
+    let b = Fixed(Schema::Named("status".to_string(), Box::new(
+      Schema::Enumeration(vec!["off".to_string()]))));
+    let mut calls = BTreeMap::new();
+    calls.insert("a".to_string(), (&a as &HasSchema, &a as &ECMAScript));
+    calls.insert("b".to_string(), (&b as &HasSchema, &b as &ECMAScript));
+    let module = ECMAScriptModule{calls: calls, asynchrony: Asynchrony::Callbacks};
+
+    let result = module.typescript(&mut vec![], TypeScriptOutput::Declarations)
+      .map_err(|e| e.to_string());
+    assert_eq!(result,
+               Err("The name status is used for different schemas.".to_string()));
+    assert!(module.purescript(&mut vec![], "Client").is_err());
+  }
+
+  #[test]
+  fn test_rust() {
+    let constant = Constant::Record(vec![
+      ("type".to_string(), Constant::ArrayOf(vec![
+        Constant::SignedInteger(1),
+        Constant::String("a".to_string()),
+      ])),
+    ]);
+    let service = WebService{name: "remote".to_string(), source: &constant};
+    let mut calls = BTreeMap::new();
+    calls.insert("localSettings".to_string(),
+                 (&constant as &HasSchema, &constant as &Rust));
+    calls.insert("remote".to_string(),
+                 (&service as &HasSchema, &service as &Rust));
+    let module = RustModule{calls: calls};
+
+    let mut output = vec![];
+    module.rust(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with("use serde::{Deserialize, Serialize};\n"));
+    assert!(!output.contains("pub struct Int64"));
+    assert!(output.contains(concat!(
+      "pub struct LocalSettingsOutput {\n",
+      "    #[serde(rename = \"type\")]\n",
+      "    pub type_: Vec<LocalSettingsOutputTypeElement>,\n}\n")));
+    assert!(output.contains(concat!(
+      "#[serde(untagged)]\n",
+      "pub enum LocalSettingsOutputTypeElement {\n",
+      "    Alternative0(i8),\n    Alternative1(String),\n}\n")));
+    assert!(output.contains(concat!(
+      "pub fn local_settings(context: (), input: ()) -> ",
+      "Result<LocalSettingsOutput, Error> {\n",
+      "    Ok(LocalSettingsOutput { type_: vec![",
+      "LocalSettingsOutputTypeElement::Alternative0(1), ",
+      "LocalSettingsOutputTypeElement::Alternative1(\"a\".to_string())], })\n")));
+    assert!(output.contains(concat!(
+      "pub fn remote(context: &str, input: ()) -> ",
+      "Result<RemoteOutput, Error> {\n",
+      "    let mut response = reqwest::Client::new()\n",
+      "        .post(&format!(\"{}/remote\", context))\n",
+      "        .json(&[(); 0])\n")));
+  }
+
+  #[test]
+  fn test_rust_type() {
+    let schema = Schema::AllOf(vec![
+      Schema::Optional(Box::new(Schema::SignedInteger(0, 1000))),
+      Schema::ArrayOf(Box::new(Schema::Record(vec![]))),
+      Schema::Named("user_id".to_string(),
+                    Box::new(Schema::SignedInteger64(0, 1))),
+      Schema::AllOf(vec![Schema::ByteString]),
+    ]);
+    let mut output = vec![];
+    rust_type(&mut output, &schema, "get_user_input").unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), concat!(
+      "(Option<i16>, Vec<GetUserInput1Element>, UserId, (Bytes,))"));
+
+    let mut declarations = vec![];
+    let mut usage = RustUsage::default();
+    rust_declarations(&schema, "get_user_input", &mut declarations, &mut usage)
+      .unwrap();
+    let names = declarations.iter()
+      .map(|&(ref name, _, _)| name.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(names, vec!["GetUserInput1Element", "UserId"]);
+    assert!(usage.int64 && usage.bytes);
+
+    let taken = Schema::Named("string".to_string(), Box::new(Schema::String));
+    assert!(rust_declarations(&taken, "", &mut vec![], &mut usage).is_err());
+  }
+
+  #[test]
+  fn test_typescript_type() {
+    let schema = Schema::Record(vec![
+      ("id".to_string(), Schema::SignedInteger(0, i32::MAX)),
+      ("pair".to_string(), Schema::AllOf(vec![Schema::String,
+                                              Schema::SignedInteger64(0, 1)])),
+      ("mood".to_string(), Schema::Optional(Box::new(
+        Schema::Enumeration(vec!["sad".to_string(), "happy".to_string()])))),
+      ("either".to_string(), Schema::OneOf(vec![
+        Schema::Boolean,
+        Schema::ArrayOf(Box::new(Schema::Named("user_status".to_string(),
+                                               Box::new(Schema::String)))),
+      ])),
+      ("first-name".to_string(), Schema::ByteString),
+    ]);
+    let mut output = vec![];
+    typescript_type(&mut output, &schema).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), concat!(
+      "{ id: number; pair: [string, string]; ",
+      "mood: (('sad' | 'happy') | null); ",
+      "either: ({ tag: 0; value: boolean; } | ",
+      "{ tag: 1; value: Array<UserStatus>; }); ",
+      "'first-name': Uint8Array; }"
+    ));
   }
 }
@@ -2,7 +2,7 @@
 //! sources.
 
 use Schema;
-use std::io;
+use error::Result;
 use std::rc::Rc;
 
 pub mod call;
@@ -11,5 +11,5 @@ pub mod call;
 pub trait HasSchema {
   /// Return the input and output schemas of this source. May have side-effects
   /// to retrieve the schemas.
-  fn schema(&self) -> io::Result<(Rc<Schema>, Rc<Schema>)>;
+  fn schema(&self) -> Result<(Rc<Schema>, Rc<Schema>)>;
 }
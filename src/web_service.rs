@@ -1,45 +1,71 @@
 //! This module implements the web service source.
 
 use Schema;
-use serialization::ecmascript::{deserialize, serialize};
+use error::Result;
+use escape;
+use serialization::ecmascript::{VALIDATION_ERROR, deserialize, serialize};
 use source::HasSchema;
-use source::call::{ECMAScript, ECMAScriptConvention};
+use source::call::{ECMAScript, ECMAScriptConvention, Rust};
 use std::io;
 use std::rc::Rc;
 
-pub struct WebService<'a, Source> where Source: 'a {
+pub struct WebService<'a, Source> where Source: 'a + ?Sized {
   pub name: String,
   pub source: &'a Source,
 }
 
-impl<'a, Source> HasSchema for WebService<'a, Source> where Source: HasSchema {
-  fn schema(&self) -> io::Result<(Rc<Schema>, Rc<Schema>)> {
+impl<'a, Source> HasSchema for WebService<'a, Source>
+  where Source: HasSchema + ?Sized {
+  fn schema(&self) -> Result<(Rc<Schema>, Rc<Schema>)> {
     self.source.schema()
   }
 }
 
-impl<'a, Source> ECMAScript for WebService<'a, Source>  where Source: HasSchema {
-  fn ecmascript_call(&self, write: &mut io::Write) -> io::Result<()> {
+impl<'a, Source> ECMAScript for WebService<'a, Source>
+  where Source: HasSchema + ?Sized {
+  fn ecmascript_call(&self, write: &mut io::Write) -> Result<()> {
     let (input_schema, output_schema) = self.schema()?;
 
     write!(write, "((function() {{\n")?;
 
-    write!(write, "var deserialize = ")?;
-    deserialize(write, &input_schema)?;
+    write!(write, "var serialize = ")?;
+    serialize(write, &input_schema)?;
     write!(write, ";\n")?;
 
-    write!(write, "var serialize = ")?;
-    serialize(write, &output_schema)?;
+    write!(write, "var deserialize = ")?;
+    deserialize(write, &output_schema)?;
     write!(write, ";\n")?;
 
     write!(write, "return function(url, input, onSuccess, onError) {{\n")?;
+    write!(write, "var body;\n")?;
+    write!(write, "try {{\n")?;
+    write!(write, "body = JSON.stringify(serialize(input));\n")?;
+    write!(write, "}} catch (error) {{\n")?;
+    write!(write, "onError(error);\n")?;
+    write!(write, "return;\n")?;
+    write!(write, "}}\n")?;
     write!(write, "var xhr = new XMLHttpRequest();\n")?;
     write!(write, "xhr.addEventListener('load', function() {{\n")?;
-    write!(write, "var output = deserialize(JSON.parse(xhr.responseText));\n")?;
+    write!(write, "if (xhr.status !== 200) {{\n")?;
+    write!(write, "onError(new Error('The web service responded with status ' + xhr.status + '.'));\n")?;
+    write!(write, "return;\n")?;
+    write!(write, "}}\n")?;
+    write!(write, "var output;\n")?;
+    write!(write, "try {{\n")?;
+    write!(write, "output = deserialize(JSON.parse(xhr.responseText));\n")?;
+    write!(write, "}} catch (error) {{\n")?;
+    write!(write, "onError(error);\n")?;
+    write!(write, "return;\n")?;
+    write!(write, "}}\n")?;
     write!(write, "onSuccess(output);\n")?;
     write!(write, "}});\n")?;
-    write!(write, "xhr.open('POST', url + '/{}');\n", self.name)?;
-    write!(write, "xhr.send(JSON.stringify(serialize(input)));\n")?;
+    write!(write, "xhr.addEventListener('error', function() {{\n")?;
+    write!(write, "onError(new Error('The HTTP request failed.'));\n")?;
+    write!(write, "}});\n")?;
+    write!(write, "xhr.open('POST', url + {});\n",
+           escape::ecmascript_string(&format!("/{}",
+                                              escape::url_path_segment(&self.name))))?;
+    write!(write, "xhr.send(body);\n")?;
     write!(write, "}};\n")?;
 
     write!(write, "}})())")?;
@@ -47,19 +73,52 @@ impl<'a, Source> ECMAScript for WebService<'a, Source>  where Source: HasSchema
     Ok(())
   }
 
-  fn ecmascript_convention(&self) -> io::Result<ECMAScriptConvention> {
+  fn ecmascript_convention(&self) -> Result<ECMAScriptConvention> {
     Ok(ECMAScriptConvention::Asynchronous)
   }
 }
 
+impl<'a, Source> Rust for WebService<'a, Source>
+  where Source: HasSchema + ?Sized {
+  fn rust_context(&self) -> Result<String> {
+    Ok("&str".to_string())
+  }
+
+  fn rust_call(&self, write: &mut io::Write, _: &str) -> Result<()> {
+    let (input_schema, output_schema) = self.schema()?;
+    // Serde serializes the empty tuple as null, rather than as an empty array.
+    let empty = Schema::AllOf(vec![]);
+
+    write!(write, "    let mut response = reqwest::Client::new()\n")?;
+    write!(write, "        .post(&format!(\"{{}}/{}\", context))\n",
+           escape::url_path_segment(&self.name))?;
+    if *input_schema == empty {
+      write!(write, "        .json(&[(); 0])\n")?;
+    } else {
+      write!(write, "        .json(&input)\n")?;
+    }
+    write!(write, "        .send()?;\n")?;
+    write!(write, "    if response.status().as_u16() != 200 {{\n")?;
+    write!(write, "        return Err(format!(\"The web service responded with status {{}}.\", response.status().as_u16()).into());\n")?;
+    write!(write, "    }}\n")?;
+    if *output_schema == empty {
+      write!(write, "    response.json::<[(); 0]>()?;\n")?;
+      write!(write, "    Ok(())\n")?;
+    } else {
+      write!(write, "    Ok(response.json()?)\n")?;
+    }
+    Ok(())
+  }
+}
+
 /// Generate ECMAScript functions for serving calls over HTTP.
 pub mod ecmascript {
   use super::*;
 
   /// Generate an ECMAScript statement that sets up an Express application. It
   /// does not call `listen` on the application.
-  pub fn serve<F>(write: &mut io::Write, f: F) -> io::Result<()>
-    where F: FnOnce(&mut io::Write) -> io::Result<()> {
+  pub fn serve<F>(write: &mut io::Write, f: F) -> Result<()>
+    where F: FnOnce(&mut io::Write) -> Result<()> {
     write!(write, "var express = require('express');\n")?;
     write!(write, "var bodyParser = require('body-parser');\n")?;
     write!(write, "var app = express();\n")?;
@@ -74,11 +133,16 @@ pub mod ecmascript {
     write: &mut io::Write,
     service: &WebService<Source>,
     context: &str
-  ) -> io::Result<()> where Source: HasSchema + ECMAScript {
+  ) -> Result<()> where Source: HasSchema + ECMAScript + ?Sized {
     let (input_schema, output_schema) = service.source.schema()?;
 
     write!(write, "((function() {{\n")?;
 
+    for (variable, module) in service.source.ecmascript_imports() {
+      write!(write, "var {} = require({});\n", variable,
+             escape::ecmascript_string(module))?;
+    }
+
     write!(write, "var deserialize = ")?;
     deserialize(write, &input_schema)?;
     write!(write, ";\n")?;
@@ -91,9 +155,23 @@ pub mod ecmascript {
     service.source.ecmascript_call(write)?;
     write!(write, ";\n")?;
 
-    write!(write, "app.post('/{}', function(req, res) {{\n", service.name)?;
-    write!(write, "var input = deserialize(req.body);\n")?;
-    match service.source.ecmascript_convention()? {
+    let convention = service.source.ecmascript_convention()?;
+    let route = format!("/{}", escape::url_path_segment(&service.name));
+    write!(write, "app.post({}, {}function(req, res) {{\n",
+           escape::ecmascript_string(&route),
+           if convention == ECMAScriptConvention::Promise { "async " } else { "" })?;
+    write!(write, "var input;\n")?;
+    write!(write, "try {{\n")?;
+    write!(write, "input = deserialize(req.body);\n")?;
+    write!(write, "}} catch (error) {{\n")?;
+    write!(write, "if (error.name !== '{}') {{\n", VALIDATION_ERROR)?;
+    write!(write, "res.sendStatus(500);\n")?;
+    write!(write, "return;\n")?;
+    write!(write, "}}\n")?;
+    write!(write, "res.status(400).json({{error: error.message, path: error.path}});\n")?;
+    write!(write, "return;\n")?;
+    write!(write, "}}\n")?;
+    match convention {
       ECMAScriptConvention::Synchronous => {
         write!(write, "var output = call({}, input);\n", context)?;
         continuation(write)?;
@@ -102,9 +180,19 @@ pub mod ecmascript {
         write!(write, "call({}, input, function(output) {{\n", context)?;
         continuation(write)?;
         write!(write, "}}, function(error) {{\n")?;
-        write!(write, "res.status(500);\n")?;
+        write!(write, "res.sendStatus(500);\n")?;
         write!(write, "}});\n")?;
       },
+      ECMAScriptConvention::Promise => {
+        write!(write, "var output;\n")?;
+        write!(write, "try {{\n")?;
+        write!(write, "output = await call({}, input);\n", context)?;
+        write!(write, "}} catch (error) {{\n")?;
+        write!(write, "res.sendStatus(500);\n")?;
+        write!(write, "return;\n")?;
+        write!(write, "}}\n")?;
+        continuation(write)?;
+      },
     }
     write!(write, "}});\n")?;
 
@@ -113,8 +201,185 @@ pub mod ecmascript {
     Ok(())
   }
 
-  fn continuation(write: &mut io::Write) -> io::Result<()> {
-    write!(write, "res.json(serialize(output));\n")?;
+  fn continuation(write: &mut io::Write) -> Result<()> {
+    write!(write, "var body;\n")?;
+    write!(write, "try {{\n")?;
+    write!(write, "body = serialize(output);\n")?;
+    write!(write, "}} catch (error) {{\n")?;
+    write!(write, "res.sendStatus(500);\n")?;
+    write!(write, "return;\n")?;
+    write!(write, "}}\n")?;
+    write!(write, "res.json(body);\n")?;
     Ok(())
   }
+
+  #[cfg(test)]
+  mod test {
+    use super::*;
+    use constant::Constant;
+    use source::call::Promised;
+
+    #[test]
+    fn test_handle_promise() {
+      let constant = Constant::Boolean(true);
+      let remote = WebService{name: "remote".to_string(), source: &constant};
+      let promised = Promised(&remote);
+      let service = WebService{name: "flag".to_string(), source: &promised};
+      let mut output = vec![];
+      handle(&mut output, &service, "null").unwrap();
+      let output = String::from_utf8(output).unwrap();
+      assert!(output.contains("app.post('/flag', async function(req, res) {\n"));
+      assert!(output.contains("output = await call(null, input);\n"));
+      assert!(output.contains(
+        "res.status(400).json({error: error.message, path: error.path});\n"));
+      // Throwing in an async handler would reject a Promise that no one
+      // handles, instead of responding.
+      assert!(output.contains(concat!(
+        "if (error.name !== 'ValidationError') {\n",
+        "res.sendStatus(500);\nreturn;\n}\n")));
+    }
+  }
+}
+
+/// Generate OpenAPI documents that describe web services.
+pub mod openapi {
+  use super::*;
+  use json_schema;
+  use serde_json::{Map, Value};
+  use std::collections::BTreeMap;
+
+  /// The OpenAPI version of generated documents.
+  pub const VERSION: &'static str = "3.1.0";
+
+  /// Return an OpenAPI document that describes the HTTP interface served by
+  /// `ecmascript::handle`. Each web service is a POST path that takes the
+  /// serialized input as its JSON request body and responds with the
+  /// serialized output, with status 400 and a validation error if the input
+  /// does not conform to its schema, or with status 500 if the call fails.
+  /// Named schemas are placed in the schema components.
+  pub fn document<'a, 'b, I, Source>(title: &str, version: &str, services: I)
+    -> Result<Value>
+    where I: IntoIterator<Item = &'b WebService<'a, Source>>,
+          Source: 'a + HasSchema + ?Sized, 'a: 'b {
+    let mut schemas = BTreeMap::new();
+    let mut paths = Map::new();
+    for service in services {
+      let (input_schema, output_schema) = service.schema()?;
+      let input = json_schema::convert(&input_schema, SCHEMAS, &mut schemas);
+      let output = json_schema::convert(&output_schema, SCHEMAS, &mut schemas);
+      paths.insert(format!("/{}", escape::url_path_segment(&service.name)), json!({
+        "post": {
+          "operationId": service.name,
+          "requestBody": {
+            "required": true,
+            "content": {"application/json": {"schema": input}},
+          },
+          "responses": {
+            "200": {
+              "description": "The output of the call.",
+              "content": {"application/json": {"schema": output}},
+            },
+            "400": {
+              "description": "The request body does not conform to the schema.",
+              "content": {"application/json": {"schema": {
+                "type": "object",
+                "properties": {
+                  "error": {"type": "string"},
+                  "path": {"type": "string", "format": "json-pointer"},
+                },
+                "required": ["error", "path"],
+              }}},
+            },
+            "500": {
+              "description": "The call failed.",
+            },
+          },
+        },
+      }));
+    }
+
+    let mut document = json!({
+      "openapi": VERSION,
+      "info": {"title": title, "version": version},
+      "jsonSchemaDialect": json_schema::DIALECT,
+      "paths": paths,
+    });
+    if !schemas.is_empty() {
+      document["components"] = json!({"schemas": schemas});
+    }
+    Ok(document)
+  }
+
+  /// The base of the references to schema components.
+  const SCHEMAS: &'static str = "#/components/schemas/";
+
+  #[cfg(test)]
+  mod test {
+    use super::*;
+    use interchange::Document;
+    use std::i32;
+
+    #[test]
+    fn test_document() {
+      let schema = Document{
+        input: Rc::new(Schema::Named("Id".to_string(),
+                                     Box::new(Schema::SignedInteger(0, i32::MAX)))),
+        output: Rc::new(Schema::ArrayOf(Box::new(Schema::String))),
+      };
+      let service = WebService{name: "names".to_string(), source: &schema};
+      let document = document("Names", "1.0", vec![&service])
+        .map_err(|e| e.to_string());
+      assert_eq!(document, Ok(json!({
+        "openapi": "3.1.0",
+        "info": {"title": "Names", "version": "1.0"},
+        "jsonSchemaDialect": "https://json-schema.org/draft/2020-12/schema",
+        "paths": {
+          "/names": {
+            "post": {
+              "operationId": "names",
+              "requestBody": {
+                "required": true,
+                "content": {
+                  "application/json": {
+                    "schema": {"$ref": "#/components/schemas/Id"},
+                  },
+                },
+              },
+              "responses": {
+                "200": {
+                  "description": "The output of the call.",
+                  "content": {
+                    "application/json": {
+                      "schema": {"type": "array", "items": {"type": "string"}},
+                    },
+                  },
+                },
+                "400": {
+                  "description": "The request body does not conform to the schema.",
+                  "content": {
+                    "application/json": {
+                      "schema": {
+                        "type": "object",
+                        "properties": {
+                          "error": {"type": "string"},
+                          "path": {"type": "string", "format": "json-pointer"},
+                        },
+                        "required": ["error", "path"],
+                      },
+                    },
+                  },
+                },
+                "500": {"description": "The call failed."},
+              },
+            },
+          },
+        },
+        "components": {
+          "schemas": {
+            "Id": {"type": "integer", "minimum": 0, "maximum": i32::MAX},
+          },
+        },
+      })));
+    }
+  }
 }
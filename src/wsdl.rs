@@ -0,0 +1,670 @@
+//! This module implements the WSDL source. It reads WSDL 1.1 documents with
+//! SOAP 1.1 bindings and inline XML Schema types, and yields an operation for
+//! each operation of the first SOAP binding. Both document/literal and
+//! rpc/literal bindings are supported.
+//!
+//! Values are represented in ECMAScript as they would be by the other
+//! sources: complex types become objects, repeated elements become arrays,
+//! and optional and nillable elements may be null. The XML is built and
+//! parsed by the generated call, using the input and output schemas in the
+//! encoding of the `interchange` module.
+
+use Schema;
+use error::{Error, Result};
+use escape;
+use interchange;
+use roxmltree::{Document, Node};
+use source::HasSchema;
+use source::call::{ECMAScript, ECMAScriptConvention, Rust};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::rc::Rc;
+use std::{i16, i32, i64, i8, u16, u32, u8};
+
+const WSDL: &'static str = "http://schemas.xmlsoap.org/wsdl/";
+const SOAP: &'static str = "http://schemas.xmlsoap.org/wsdl/soap/";
+const XSD: &'static str = "http://www.w3.org/2001/XMLSchema";
+
+mod error {
+  pub static DEFINITIONS: &'static str =
+    "The WSDL document does not have a definitions element.";
+
+  pub static BINDING: &'static str = "The WSDL document has no SOAP binding.";
+
+  pub static RUST: &'static str =
+    "Rust calls cannot yet be generated for WSDL operations.";
+}
+
+/// An operation of a WSDL document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Operation {
+  /// The name of the operation.
+  pub name: String,
+
+  /// The address of the SOAP endpoint, if the document gives one.
+  pub endpoint: Option<String>,
+
+  /// The value of the `SOAPAction` header.
+  pub action: String,
+
+  /// The request message.
+  pub input: Message,
+
+  /// The response message.
+  pub output: Message,
+}
+
+/// A SOAP message, which is a single element in the SOAP body.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+  /// The local name of the element.
+  pub name: String,
+
+  /// The namespace of the element.
+  pub namespace: String,
+
+  /// Whether the child elements are in the namespace of the element.
+  pub qualified: bool,
+
+  /// The schema of the content of the element.
+  pub schema: Rc<Schema>,
+}
+
+/// Read the operations from a WSDL file.
+pub fn read<P>(path: P) -> Result<Vec<Operation>> where P: AsRef<Path> {
+  let mut text = String::new();
+  File::open(path)?.read_to_string(&mut text)?;
+  parse(&text)
+}
+
+/// Parse the operations in the text of a WSDL document.
+pub fn parse(text: &str) -> Result<Vec<Operation>> {
+  let document = Document::parse(text)
+    .map_err(|error| Error::Syntax(error.to_string()))?;
+  let definitions = document.root_element();
+  if !definitions.has_tag_name((WSDL, "definitions")) {
+    return Err(Error::Syntax(error::DEFINITIONS.to_string()));
+  }
+  let target_namespace = definitions.attribute("targetNamespace").unwrap_or("");
+  let schemas = children(definitions, WSDL, "types")
+    .flat_map(|types| children(types, XSD, "schema"))
+    .collect::<Vec<_>>();
+
+  let binding = children(definitions, WSDL, "binding")
+    .find(|binding| children(*binding, SOAP, "binding").next().is_some())
+    .ok_or_else(|| Error::Syntax(error::BINDING.to_string()))?;
+  let binding_name = required_attribute(binding, "name")?;
+  let style = children(binding, SOAP, "binding")
+    .filter_map(|soap| soap.attribute("style"))
+    .next()
+    .unwrap_or("document");
+  let port_type = find_global(definitions, WSDL, "portType",
+                              &qname(binding, required_attribute(binding, "type")?)?)?;
+  let endpoint = children(definitions, WSDL, "service")
+    .flat_map(|service| children(service, WSDL, "port"))
+    .find(|port| {
+      port.attribute("binding")
+      .and_then(|binding| qname(*port, binding).ok())
+      .map_or(false, |(_, name)| name == binding_name)
+    })
+    .and_then(|port| children(port, SOAP, "address").next())
+    .and_then(|address| address.attribute("location"))
+    .map(str::to_string);
+
+  let mut converter = Converter{schemas: schemas, stack: vec![]};
+  let mut operations = vec![];
+  for operation in children(binding, WSDL, "operation") {
+    let name = required_attribute(operation, "name")?;
+    let soap = children(operation, SOAP, "operation").next();
+    let action = soap.and_then(|soap| soap.attribute("soapAction"))
+      .unwrap_or("");
+    let style = soap.and_then(|soap| soap.attribute("style")).unwrap_or(style);
+    let abstract_operation = children(port_type, WSDL, "operation")
+      .find(|abstract_operation| abstract_operation.attribute("name") == Some(name))
+      .ok_or_else(|| Error::Syntax(
+        format!("The operation {} is not in the port type.", name)))?;
+
+    let mut message = |direction: &str, wrapper: String| -> Result<Message> {
+      let reference = children(abstract_operation, WSDL, direction).next()
+        .and_then(|node| node.attribute("message").map(|message| (node, message)))
+        .ok_or_else(|| Error::Syntax(
+          format!("The operation {} has no {} message.", name, direction)))?;
+      let message = find_global(definitions, WSDL, "message",
+                                &qname(reference.0, reference.1)?)?;
+      let parts = children(message, WSDL, "part").collect::<Vec<_>>();
+      if style == "rpc" {
+        let namespace = children(operation, WSDL, direction)
+          .flat_map(|node| children(node, SOAP, "body"))
+          .filter_map(|body| body.attribute("namespace"))
+          .next()
+          .unwrap_or(target_namespace);
+        let mut fields = vec![];
+        for part in parts {
+          let type_ = part.attribute("type").ok_or_else(|| Error::Unsupported(
+            format!("The part of the rpc message {} has no type.", wrapper)))?;
+          fields.push((required_attribute(part, "name")?.to_string(),
+                       converter.type_schema(&qname(part, type_)?)?));
+        }
+        Ok(Message{
+          name: wrapper,
+          namespace: namespace.to_string(),
+          qualified: false,
+          schema: Rc::new(Schema::Record(fields)),
+        })
+      } else {
+        let element = match parts.as_slice() {
+          [part] => part.attribute("element").map(|element| (*part, element)),
+          _ => None,
+        };
+        let (part, element) = element.ok_or_else(|| Error::Unsupported(
+          format!(concat!("The document message of the operation {} does not ",
+                          "have exactly one part with an element."), name)))?;
+        let (namespace, local) = qname(part, element)?;
+        let (schema, element) = converter.global(XSD, "element",
+                                                 &(namespace.clone(), local))?;
+        Ok(Message{
+          name: required_attribute(element, "name")?.to_string(),
+          namespace: namespace,
+          qualified: schema.attribute("elementFormDefault") == Some("qualified"),
+          schema: Rc::new(converter.element_type(element)?),
+        })
+      }
+    };
+    let input = message("input", name.to_string())?;
+    let output = message("output", format!("{}Response", name))?;
+
+    operations.push(Operation{
+      name: name.to_string(),
+      endpoint: endpoint.clone(),
+      action: action.to_string(),
+      input: input,
+      output: output,
+    });
+  }
+  Ok(operations)
+}
+
+/// Converts XML Schema types to schemas.
+struct Converter<'a, 'input: 'a> {
+  schemas: Vec<Node<'a, 'input>>,
+
+  /// The named types being converted, to detect recursion.
+  stack: Vec<(String, String)>,
+}
+
+impl<'a, 'input: 'a> Converter<'a, 'input> {
+  /// Find a global element or type by qualified name. Return the schema that
+  /// declares it, and the declaration.
+  fn global(&self, namespace: &'a str, kind: &'a str, name: &(String, String))
+    -> Result<(Node<'a, 'input>, Node<'a, 'input>)> {
+    self.schemas.iter()
+    .filter(|schema| schema.attribute("targetNamespace").unwrap_or("") == name.0)
+    .flat_map(|schema| {
+      children(*schema, namespace, kind)
+      .filter(|node| node.attribute("name") == Some(&name.1))
+      .map(move |node| (*schema, node))
+    })
+    .next()
+    .ok_or_else(|| Error::Syntax(
+      format!("The WSDL document does not declare the {} {}.", kind, name.1)))
+  }
+
+  /// Return the schema of an element, taking its occurrence into account.
+  fn element(&mut self, element: Node) -> Result<Schema> {
+    let schema = match element.attribute("ref") {
+      Some(reference) => {
+        let name = qname(element, reference)?;
+        let (_, global) = self.global(XSD, "element", &name)?;
+        self.element_type(global)?
+      },
+      None => self.element_type(element)?,
+    };
+    let repeated = match element.attribute("maxOccurs") {
+      Some("unbounded") => true,
+      Some(max) => max.parse::<u32>().map_or(false, |max| max > 1),
+      None => false,
+    };
+    if repeated {
+      Ok(Schema::ArrayOf(Box::new(schema)))
+    } else if element.attribute("minOccurs") == Some("0")
+              || element.attribute("nillable") == Some("true") {
+      Ok(Schema::Optional(Box::new(schema)))
+    } else {
+      Ok(schema)
+    }
+  }
+
+  /// Return the schema of the type of an element, ignoring its occurrence.
+  fn element_type(&mut self, element: Node) -> Result<Schema> {
+    if let Some(type_) = element.attribute("type") {
+      return self.type_schema(&qname(element, type_)?);
+    }
+    match xsd_children(element).next() {
+      Some(child) if child.has_tag_name((XSD, "complexType")) =>
+        self.complex_type(child),
+      Some(child) if child.has_tag_name((XSD, "simpleType")) =>
+        self.simple_type(child),
+      _ => Err(Error::Unsupported(
+        format!("The element {} has no type.",
+                element.attribute("name").unwrap_or("")))),
+    }
+  }
+
+  /// Return the schema of a built-in or named type.
+  fn type_schema(&mut self, name: &(String, String)) -> Result<Schema> {
+    if name.0 == XSD {
+      return builtin(&name.1);
+    }
+    if self.stack.contains(name) {
+      return Err(Error::Unsupported(
+        format!("The type {} is recursive.", name.1)));
+    }
+    let declaration = self.global(XSD, "complexType", name)
+      .or_else(|_| self.global(XSD, "simpleType", name))?.1;
+    self.stack.push(name.clone());
+    let schema = if declaration.has_tag_name((XSD, "complexType")) {
+      self.complex_type(declaration)
+    } else {
+      self.simple_type(declaration)
+    };
+    self.stack.pop();
+    Ok(Schema::Named(name.1.clone(), Box::new(schema?)))
+  }
+
+  /// Return the schema of a complex type with a sequence or all of elements.
+  fn complex_type(&mut self, complex_type: Node) -> Result<Schema> {
+    let mut fields = vec![];
+    for group in xsd_children(complex_type) {
+      if !group.has_tag_name((XSD, "sequence")) && !group.has_tag_name((XSD, "all")) {
+        return Err(Error::Unsupported(
+          format!("Complex types with {} are not supported.",
+                  group.tag_name().name())));
+      }
+      for element in xsd_children(group) {
+        if !element.has_tag_name((XSD, "element")) {
+          return Err(Error::Unsupported(
+            format!("Sequences with {} are not supported.",
+                    element.tag_name().name())));
+        }
+        let name = match element.attribute("ref") {
+          Some(reference) => qname(element, reference)?.1,
+          None => required_attribute(element, "name")?.to_string(),
+        };
+        fields.push((name, self.element(element)?));
+      }
+    }
+    Ok(Schema::Record(fields))
+  }
+
+  /// Return the schema of a simple type that restricts another type,
+  /// possibly to an enumeration.
+  fn simple_type(&mut self, simple_type: Node) -> Result<Schema> {
+    let restriction = xsd_children(simple_type)
+      .find(|child| child.has_tag_name((XSD, "restriction")))
+      .ok_or_else(|| Error::Unsupported(
+        "Only simple types with a restriction are supported.".to_string()))?;
+    let labels = xsd_children(restriction)
+      .filter(|facet| facet.has_tag_name((XSD, "enumeration")))
+      .map(|facet| required_attribute(facet, "value").map(str::to_string))
+      .collect::<Result<Vec<_>>>()?;
+    if labels.is_empty() {
+      self.type_schema(&qname(restriction,
+                              required_attribute(restriction, "base")?)?)
+    } else {
+      Ok(Schema::Enumeration(labels))
+    }
+  }
+}
+
+/// Return the schema of a built-in XML Schema type.
+fn builtin(name: &str) -> Result<Schema> {
+  match name {
+    "boolean" => Ok(Schema::Boolean),
+    "byte" => Ok(Schema::SignedInteger(i8::MIN as i32, i8::MAX as i32)),
+    "short" => Ok(Schema::SignedInteger(i16::MIN as i32, i16::MAX as i32)),
+    "int" => Ok(Schema::SignedInteger(i32::MIN, i32::MAX)),
+    "unsignedByte" => Ok(Schema::SignedInteger(0, u8::MAX as i32)),
+    "unsignedShort" => Ok(Schema::SignedInteger(0, u16::MAX as i32)),
+    "long" => Ok(Schema::SignedInteger64(i64::MIN, i64::MAX)),
+    "unsignedInt" => Ok(Schema::SignedInteger64(0, u32::MAX as i64)),
+    "integer" | "decimal" => Ok(Schema::Decimal),
+    "float" => Ok(Schema::SinglePrecision),
+    "double" => Ok(Schema::DoublePrecision),
+    "base64Binary" => Ok(Schema::ByteString),
+    "string" | "normalizedString" | "token" | "anyURI" => Ok(Schema::String),
+    "date" => Ok(Schema::Date),
+    "dateTime" => Ok(Schema::TimestampWithTimeZone),
+    "duration" => Ok(Schema::Interval),
+    _ => Err(Error::UnsupportedType(format!("xsd:{}", name))),
+  }
+}
+
+/// Resolve a qualified name such as `tns:User` in the scope of a node.
+fn qname(node: Node, value: &str) -> Result<(String, String)> {
+  let (prefix, local) = match value.find(':') {
+    Some(index) => (Some(&value[..index]), &value[index + 1..]),
+    None => (None, value),
+  };
+  match node.lookup_namespace_uri(prefix) {
+    Some(namespace) => Ok((namespace.to_string(), local.to_string())),
+    None if prefix.is_none() => Ok((String::new(), local.to_string())),
+    None => Err(Error::Syntax(
+      format!("The namespace prefix of {} is not declared.", value))),
+  }
+}
+
+/// Find a global WSDL definition by qualified name.
+fn find_global<'a, 'input>(definitions: Node<'a, 'input>, namespace: &'a str,
+                           kind: &'a str, name: &(String, String))
+  -> Result<Node<'a, 'input>> {
+  children(definitions, namespace, kind)
+  .find(|node| node.attribute("name") == Some(&name.1))
+  .ok_or_else(|| Error::Syntax(
+    format!("The WSDL document does not declare the {} {}.", kind, name.1)))
+}
+
+fn children<'a, 'input: 'a>(node: Node<'a, 'input>, namespace: &'a str,
+                            name: &'a str)
+  -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+  node.children().filter(move |child| child.has_tag_name((namespace, name)))
+}
+
+/// Return the XML Schema child elements of a node, except annotations.
+fn xsd_children<'a, 'input: 'a>(node: Node<'a, 'input>)
+  -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+  node.children().filter(|child| {
+    child.is_element() && child.tag_name().namespace() == Some(XSD)
+    && child.tag_name().name() != "annotation"
+  })
+}
+
+fn required_attribute<'a, 'input>(node: Node<'a, 'input>, name: &str)
+  -> Result<&'a str> {
+  node.attribute(name).ok_or_else(|| Error::Syntax(
+    format!("The {} element has no {} attribute.", node.tag_name().name(),
+            name)))
+}
+
+impl HasSchema for Operation {
+  fn schema(&self) -> Result<(Rc<Schema>, Rc<Schema>)> {
+    Ok((self.input.schema.clone(), self.output.schema.clone()))
+  }
+}
+
+impl Rust for Operation {
+  fn rust_context(&self) -> Result<String> {
+    Err(Error::Unsupported(error::RUST.to_string()))
+  }
+
+  fn rust_call(&self, _: &mut io::Write, _: &str) -> Result<()> {
+    Err(Error::Unsupported(error::RUST.to_string()))
+  }
+}
+
+impl ECMAScript for Operation {
+  fn ecmascript_call(&self, write: &mut io::Write) -> Result<()> {
+    let message = |message: &Message| json!({
+      "name": message.name,
+      "namespace": message.namespace,
+      "qualified": message.qualified,
+      "schema": interchange::encode(&message.schema),
+    });
+    let operation = json!({
+      "endpoint": self.endpoint,
+      "action": self.action,
+      "input": message(&self.input),
+      "output": message(&self.output),
+    });
+
+    write!(write, "(function() {{\n")?;
+    write!(write, "var operation = {};\n", escape::ecmascript_json(&operation))?;
+    declare_xml_functions(write)?;
+    write!(write, "return function(endpoint, input, onSuccess, onError) {{\n")?;
+    write!(write, "var prefix = operation.input.qualified ? 'tns:' : '';\n")?;
+    write!(write, "var envelope = '<?xml version=\"1.0\" encoding=\"utf-8\"?>'\n")?;
+    write!(write, "  + '<soap:Envelope xmlns:soap=\"' + SOAP + '\"'\n")?;
+    write!(write, "  + ' xmlns:tns=\"' + escapeXml(operation.input.namespace) + '\">'\n")?;
+    write!(write, "  + '<soap:Body>'\n")?;
+    write!(write, "  + toXml(operation.input.schema, 'tns:' + operation.input.name, prefix, input)\n")?;
+    write!(write, "  + '</soap:Body></soap:Envelope>';\n")?;
+    write!(write, "var xhr = new XMLHttpRequest();\n")?;
+    write!(write, "xhr.addEventListener('load', function() {{\n")?;
+    write!(write, "var output;\n")?;
+    write!(write, "try {{\n")?;
+    write!(write, "output = fromEnvelope(xhr.responseXML);\n")?;
+    write!(write, "}} catch (error) {{\n")?;
+    write!(write, "onError(error);\n")?;
+    write!(write, "return;\n")?;
+    write!(write, "}}\n")?;
+    write!(write, "onSuccess(output);\n")?;
+    write!(write, "}});\n")?;
+    write!(write, "xhr.addEventListener('error', function() {{\n")?;
+    write!(write, "onError(new Error('The SOAP request failed.'));\n")?;
+    write!(write, "}});\n")?;
+    write!(write, "xhr.open('POST', endpoint || operation.endpoint);\n")?;
+    write!(write, "xhr.setRequestHeader('Content-Type', 'text/xml; charset=utf-8');\n")?;
+    write!(write, "xhr.setRequestHeader('SOAPAction', '\"' + operation.action + '\"');\n")?;
+    write!(write, "xhr.send(envelope);\n")?;
+    write!(write, "}};\n")?;
+    write!(write, "}})()")?;
+    Ok(())
+  }
+
+  fn ecmascript_convention(&self) -> Result<ECMAScriptConvention> {
+    Ok(ECMAScriptConvention::Asynchronous)
+  }
+}
+
+/// Generate ECMAScript statements that declare `toXml`, which converts a
+/// value to an element, and `fromEnvelope`, which converts the body of a
+/// response envelope to the output value or throws the SOAP fault.
+fn declare_xml_functions(write: &mut io::Write) -> Result<()> {
+  write!(write, "var SOAP = 'http://schemas.xmlsoap.org/soap/envelope/';\n")?;
+  write!(write, "var XSI = 'http://www.w3.org/2001/XMLSchema-instance';\n")?;
+
+  write!(write, "var escapeXml = function(text) {{\n")?;
+  write!(write, "return String(text).replace(/&/g, '&amp;').replace(/</g, '&lt;')\n")?;
+  write!(write, "  .replace(/>/g, '&gt;').replace(/\"/g, '&quot;');\n")?;
+  write!(write, "}};\n")?;
+
+  write!(write, "var toXml = function(schema, tag, prefix, value) {{\n")?;
+  write!(write, "switch (schema.type) {{\n")?;
+  write!(write, "case 'named':\n")?;
+  write!(write, "return toXml(schema.schema, tag, prefix, value);\n")?;
+  write!(write, "case 'optional':\n")?;
+  write!(write, "return value === null || value === undefined ? ''\n")?;
+  write!(write, "  : toXml(schema.element, tag, prefix, value);\n")?;
+  write!(write, "case 'array_of':\n")?;
+  write!(write, "return value.map(function(element) {{\n")?;
+  write!(write, "return toXml(schema.element, tag, prefix, element);\n")?;
+  write!(write, "}}).join('');\n")?;
+  write!(write, "case 'record':\n")?;
+  write!(write, "return '<' + tag + '>' + schema.fields.map(function(field) {{\n")?;
+  write!(write, "return toXml(field.schema, prefix + field.name, prefix, value[field.name]);\n")?;
+  write!(write, "}}).join('') + '</' + tag + '>';\n")?;
+  write!(write, "case 'boolean':\n")?;
+  write!(write, "return '<' + tag + '>' + (value ? 'true' : 'false') + '</' + tag + '>';\n")?;
+  write!(write, "default:\n")?;
+  write!(write, "return '<' + tag + '>' + escapeXml(value) + '</' + tag + '>';\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "}};\n")?;
+
+  write!(write, "var childElements = function(element, name) {{\n")?;
+  write!(write, "var children = [];\n")?;
+  write!(write, "for (var child = element.firstChild; child !== null; child = child.nextSibling) {{\n")?;
+  write!(write, "if (child.nodeType === 1 && (name === null || child.localName === name)) {{\n")?;
+  write!(write, "children.push(child);\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "return children;\n")?;
+  write!(write, "}};\n")?;
+
+  write!(write, "var fromXml = function(schema, element) {{\n")?;
+  write!(write, "if (schema.type === 'optional') {{\n")?;
+  write!(write, "return element === null || element.getAttributeNS(XSI, 'nil') === 'true' ? null\n")?;
+  write!(write, "  : fromXml(schema.element, element);\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "if (element === null) {{\n")?;
+  write!(write, "throw new Error('The SOAP response lacks a required element.');\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "switch (schema.type) {{\n")?;
+  write!(write, "case 'named':\n")?;
+  write!(write, "return fromXml(schema.schema, element);\n")?;
+  write!(write, "case 'record':\n")?;
+  write!(write, "var result = {{}};\n")?;
+  write!(write, "schema.fields.forEach(function(field) {{\n")?;
+  write!(write, "var children = childElements(element, field.name);\n")?;
+  write!(write, "result[field.name] = field.schema.type === 'array_of'\n")?;
+  write!(write, "  ? children.map(function(child) {{ return fromXml(field.schema.element, child); }})\n")?;
+  write!(write, "  : fromXml(field.schema, children.length === 0 ? null : children[0]);\n")?;
+  write!(write, "}});\n")?;
+  write!(write, "return result;\n")?;
+  write!(write, "case 'boolean':\n")?;
+  write!(write, "return element.textContent === 'true' || element.textContent === '1';\n")?;
+  write!(write, "case 'signed_integer':\n")?;
+  write!(write, "return parseInt(element.textContent, 10);\n")?;
+  write!(write, "case 'single_precision':\n")?;
+  write!(write, "case 'double_precision':\n")?;
+  write!(write, "return parseFloat(element.textContent);\n")?;
+  write!(write, "default:\n")?;
+  write!(write, "return element.textContent;\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "}};\n")?;
+
+  write!(write, "var fromEnvelope = function(document) {{\n")?;
+  write!(write, "var body = document && document.getElementsByTagNameNS(SOAP, 'Body')[0];\n")?;
+  write!(write, "if (!body) {{\n")?;
+  write!(write, "throw new Error('The SOAP response has no body.');\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "var fault = body.getElementsByTagNameNS(SOAP, 'Fault')[0];\n")?;
+  write!(write, "if (fault) {{\n")?;
+  write!(write, "var faultString = childElements(fault, 'faultstring')[0];\n")?;
+  write!(write, "throw new Error(faultString ? faultString.textContent : 'SOAP fault');\n")?;
+  write!(write, "}}\n")?;
+  write!(write, "var element = childElements(body, null)[0];\n")?;
+  write!(write, "return fromXml(operation.output.schema, element === undefined ? null : element);\n")?;
+  write!(write, "}};\n")?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  static WSDL_DOCUMENT: &'static str = r#"<?xml version="1.0"?>
+<definitions xmlns="http://schemas.xmlsoap.org/wsdl/"
+             xmlns:soap="http://schemas.xmlsoap.org/wsdl/soap/"
+             xmlns:xsd="http://www.w3.org/2001/XMLSchema"
+             xmlns:tns="urn:users"
+             targetNamespace="urn:users">
+  <types>
+    <xsd:schema targetNamespace="urn:users" elementFormDefault="qualified">
+      <xsd:simpleType name="Mood">
+        <xsd:restriction base="xsd:string">
+          <xsd:enumeration value="sad"/>
+          <xsd:enumeration value="happy"/>
+        </xsd:restriction>
+      </xsd:simpleType>
+      <xsd:element name="GetUser">
+        <xsd:complexType>
+          <xsd:sequence>
+            <xsd:element name="id" type="xsd:int"/>
+          </xsd:sequence>
+        </xsd:complexType>
+      </xsd:element>
+      <xsd:element name="GetUserResponse">
+        <xsd:complexType>
+          <xsd:sequence>
+            <xsd:element name="name" type="xsd:string" nillable="true"/>
+            <xsd:element name="mood" type="tns:Mood" minOccurs="0"/>
+            <xsd:element name="tags" type="xsd:string" maxOccurs="unbounded"/>
+          </xsd:sequence>
+        </xsd:complexType>
+      </xsd:element>
+    </xsd:schema>
+  </types>
+  <message name="GetUserRequest">
+    <part name="parameters" element="tns:GetUser"/>
+  </message>
+  <message name="GetUserResponse">
+    <part name="parameters" element="tns:GetUserResponse"/>
+  </message>
+  <portType name="Users">
+    <operation name="GetUser">
+      <input message="tns:GetUserRequest"/>
+      <output message="tns:GetUserResponse"/>
+    </operation>
+  </portType>
+  <binding name="UsersSoap" type="tns:Users">
+    <soap:binding style="document" transport="http://schemas.xmlsoap.org/soap/http"/>
+    <operation name="GetUser">
+      <soap:operation soapAction="urn:users/GetUser"/>
+      <input><soap:body use="literal"/></input>
+      <output><soap:body use="literal"/></output>
+    </operation>
+  </binding>
+  <service name="UsersService">
+    <port name="UsersPort" binding="tns:UsersSoap">
+      <soap:address location="http://example.com/users"/>
+    </port>
+  </service>
+</definitions>"#;
+
+  #[test]
+  fn test_parse() {
+    let operations = parse(WSDL_DOCUMENT).map_err(|e| e.to_string());
+    let mood = Schema::Named("Mood".to_string(), Box::new(
+      Schema::Enumeration(vec!["sad".to_string(), "happy".to_string()])));
+    assert_eq!(operations, Ok(vec![Operation{
+      name: "GetUser".to_string(),
+      endpoint: Some("http://example.com/users".to_string()),
+      action: "urn:users/GetUser".to_string(),
+      input: Message{
+        name: "GetUser".to_string(),
+        namespace: "urn:users".to_string(),
+        qualified: true,
+        schema: Rc::new(Schema::Record(vec![
+          ("id".to_string(), Schema::SignedInteger(i32::MIN, i32::MAX)),
+        ])),
+      },
+      output: Message{
+        name: "GetUserResponse".to_string(),
+        namespace: "urn:users".to_string(),
+        qualified: true,
+        schema: Rc::new(Schema::Record(vec![
+          ("name".to_string(), Schema::Optional(Box::new(Schema::String))),
+          ("mood".to_string(), Schema::Optional(Box::new(mood))),
+          ("tags".to_string(), Schema::ArrayOf(Box::new(Schema::String))),
+        ])),
+      },
+    }]));
+  }
+
+  #[test]
+  fn test_parse_rpc() {
+    let document = WSDL_DOCUMENT
+      .replace("style=\"document\"", "style=\"rpc\"")
+      .replace("element=\"tns:GetUser\"", "type=\"xsd:int\"")
+      .replace("element=\"tns:GetUserResponse\"", "type=\"tns:Mood\"");
+    let operations = parse(&document).map_err(|e| e.to_string());
+    let operation = operations.unwrap().pop().unwrap();
+    assert_eq!(operation.output, Message{
+      name: "GetUserResponse".to_string(),
+      namespace: "urn:users".to_string(),
+      qualified: false,
+      schema: Rc::new(Schema::Record(vec![
+        ("parameters".to_string(), Schema::Named("Mood".to_string(), Box::new(
+          Schema::Enumeration(vec!["sad".to_string(), "happy".to_string()])))),
+      ])),
+    });
+  }
+
+  #[test]
+  fn test_parse_unsupported() {
+    let document = WSDL_DOCUMENT.replace("xsd:int", "xsd:QName");
+    let operations = parse(&document).map_err(|e| e.to_string());
+    assert_eq!(operations,
+               Err("The type xsd:QName has no corresponding schema.".to_string()));
+  }
+}